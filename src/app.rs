@@ -1,10 +1,14 @@
 use crate::message::Message;
-use crate::model::{AppState, MediaType};
+use crate::model::{AppState, MediaType, PatternEditor, ProviderKind, RenamePattern};
+use crate::model::{MediaFile, MediaMetadata, SearchResult, Thumbnail};
 use crate::settings::AppSettings;
 use crate::theme::{reel_theme, ThemeMode};
-use crate::utils::{file_scanner, filename_parser, renamer, tmdb};
+use crate::utils::journal::{self, RenameJournal};
+use crate::utils::metadata::{normalize_language, BatchFileInfo, MatchOutcome};
+use crate::utils::{artwork, file_scanner, filename_parser, nfo, renamer, tmdb};
 use crate::view::build_view;
 use iced::event::{self, Event};
+use iced::widget::image;
 use iced::window;
 use iced::{Application, Command, Subscription, Theme};
 use std::time::Duration;
@@ -15,6 +19,158 @@ pub struct ReelApp {
     settings: AppSettings,
 }
 
+impl ReelApp {
+    /// Make a pattern current, regenerate filenames and remember the choice.
+    /// Metadata is fetched again when the pattern asks for another language.
+    fn select_pattern(&mut self, pattern: RenamePattern) -> Command<Message> {
+        self.settings.selected_pattern = Some(pattern.name.clone());
+        let _ = self.settings.save();
+        let language = self.state.effective_language();
+        self.state.rename_pattern = pattern;
+
+        // Regenerate filenames for matched files
+        if let Some(e) = self.regenerate_filenames() {
+            self.state.status = e;
+        }
+
+        if self.state.effective_language() != language {
+            return self.refetch_matched(|_| true);
+        }
+        Command::none()
+    }
+
+    /// Fetch metadata again for matched files, e.g. in another language or
+    /// episode order
+    fn refetch_matched(&mut self, keep: impl Fn(&MediaMetadata) -> bool) -> Command<Message> {
+        let files: Vec<(SearchResult, BatchFileInfo)> = self
+            .state
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, f)| {
+                let metadata = f.matched_metadata.as_ref().filter(|m| keep(m))?;
+                let result = SearchResult {
+                    provider: metadata.provider,
+                    id: metadata.provider_id,
+                    title: metadata.show_name.clone().unwrap_or(metadata.title.clone()),
+                    year: metadata.year,
+                    media_type: f.media_type,
+                    overview: None,
+                    poster_path: None,
+                    vote_average: None,
+                    popularity: None,
+                    confidence: None,
+                };
+                Some((result, BatchFileInfo::from_media_file(i, f)))
+            })
+            .collect();
+        if files.is_empty() {
+            return Command::none();
+        }
+
+        let config = self.state.provider_config();
+        self.state.search_loading = true;
+        self.state.status = format!("Refreshing metadata for {} file(s)...", files.len());
+        Command::perform(
+            async move {
+                let providers = config.build();
+                let fetches = files.iter().map(|(result, info)| {
+                    let providers = &providers;
+                    async move { (info.index, providers.fetch_metadata(result, info).await) }
+                });
+                futures::future::join_all(fetches).await
+            },
+            Message::BatchMetadataFetched,
+        )
+    }
+
+    /// Load the TMDB episode groups of the selected file's show, once per show
+    fn load_episode_groups(&mut self) -> Command<Message> {
+        let Some(show_id) = self.state.selected_show_id() else {
+            return Command::none();
+        };
+        if self.state.episode_groups.contains_key(&show_id) {
+            return Command::none();
+        }
+        // Placeholder so the show isn't requested again while loading
+        self.state.episode_groups.insert(show_id, Vec::new());
+
+        let client = tmdb::TmdbClient::new(self.state.effective_api_key())
+            .with_cache(self.state.tmdb_cache());
+        Command::perform(
+            async move { client.get_episode_groups(show_id).await },
+            move |result| Message::EpisodeGroupsLoaded(show_id, result),
+        )
+    }
+
+    /// Fetch the posters shown next to search results and the selected match
+    fn load_thumbnails(&mut self) -> Command<Message> {
+        let sources = self.state.missing_thumbnails();
+        if sources.is_empty() {
+            return Command::none();
+        }
+        for source in &sources {
+            self.state
+                .thumbnails
+                .insert(source.clone(), Thumbnail::Loading);
+        }
+
+        let client = tmdb::TmdbClient::new(self.state.effective_api_key())
+            .with_cache(self.state.tmdb_cache());
+        Command::perform(
+            async move { client.download_thumbnails(sources).await },
+            Message::ThumbnailsLoaded,
+        )
+    }
+
+    /// Regenerate the new filename of every matched file with the current
+    /// pattern. Returns the pattern error, if any.
+    fn regenerate_filenames(&mut self) -> Option<String> {
+        let mut pattern_error = None;
+        for file in &mut self.state.files {
+            if let Some(metadata) = file.matched_metadata.clone() {
+                pattern_error = pattern_error.or(set_new_filename(
+                    file,
+                    &metadata,
+                    &self.state.rename_pattern,
+                ));
+            }
+        }
+        pattern_error
+    }
+
+    /// Pick up batches the CLI journaled since the GUI loaded the journal
+    fn reload_journal(&mut self) {
+        self.state.rename_journal.merge(RenameJournal::load());
+    }
+
+    fn save_custom_patterns(&mut self) {
+        self.settings.custom_patterns = self.state.custom_patterns.clone();
+        if let Err(e) = self.settings.save() {
+            self.state.status = e;
+        }
+    }
+}
+
+/// Set a file's new filename from `metadata`. On a pattern error the file is
+/// left without a new name and the error is returned for the status line.
+fn set_new_filename(
+    file: &mut MediaFile,
+    metadata: &MediaMetadata,
+    pattern: &RenamePattern,
+) -> Option<String> {
+    match renamer::generate_filename(file, metadata, pattern) {
+        Ok(name) => {
+            file.new_filename = Some(name);
+            None
+        }
+        Err(e) => {
+            file.new_filename = None;
+            Some(format!("Pattern error: {}", e))
+        }
+    }
+}
+
 impl Application for ReelApp {
     type Message = Message;
     type Theme = Theme;
@@ -24,6 +180,27 @@ impl Application for ReelApp {
     fn new(_flags: ()) -> (Self, Command<Message>) {
         let settings = AppSettings::load();
         let mut state = AppState::new();
+        state.rename_journal = RenameJournal::load();
+        state.all_or_nothing = settings.all_or_nothing_rename;
+        state.file_operation = settings.file_operation;
+        state.nfo_policy = settings.nfo_policy;
+        state.artwork = settings.artwork.clone();
+        state.offline_mode = settings.offline_mode;
+        state.cache_ttl = settings.cache_ttl;
+        state.providers = settings.providers.clone();
+        state.tvdb = settings.tvdb.clone();
+        state.episode_orders = settings.episode_orders.clone();
+        state.metadata_language = settings.metadata_language.clone();
+        state.custom_patterns = settings.custom_patterns.clone();
+
+        // Restore the last chosen pattern
+        if let Some(pattern) = settings
+            .selected_pattern
+            .as_deref()
+            .and_then(|name| RenamePattern::find(name, &state.custom_patterns))
+        {
+            state.rename_pattern = pattern;
+        }
 
         // Load saved API key if exists (overrides default)
         if let Some(key) = settings.get_api_key() {
@@ -217,6 +394,12 @@ impl Application for ReelApp {
                     let parsed_title = file.parsed_info.as_ref().map(|p| p.title.clone());
                     let filename = file.filename.clone();
 
+                    let candidates = if file.needs_review() {
+                        file.match_candidates.clone()
+                    } else {
+                        Vec::new()
+                    };
+
                     self.state.selected_file_index = Some(actual_index);
 
                     // Update search input with parsed title
@@ -224,10 +407,16 @@ impl Application for ReelApp {
                         self.state.search_input = title;
                     }
 
-                    // Update status to show selected file
-                    self.state.status = format!("Selected: {}", filename);
+                    if candidates.is_empty() {
+                        // Update status to show selected file
+                        self.state.status = format!("Selected: {}", filename);
+                    } else {
+                        // Offer the ambiguous candidates in the search results
+                        self.state.search_results = candidates;
+                        self.state.status = format!("Pick the right match for {}", filename);
+                    }
                 }
-                Command::none()
+                Command::batch([self.load_episode_groups(), self.load_thumbnails()])
             }
 
             Message::FileSearchChanged(query) => {
@@ -349,6 +538,8 @@ impl Application for ReelApp {
 
                 self.state.search_loading = true;
                 self.state.search_results.clear();
+                // A new search is a chance to fetch posters that failed before
+                self.state.retry_failed_thumbnails();
                 let media_type = self
                     .state
                     .selected_file()
@@ -360,8 +551,9 @@ impl Application for ReelApp {
                     .and_then(|f| f.parsed_info.as_ref())
                     .and_then(|p| p.year);
 
+                let config = self.state.provider_config();
                 Command::perform(
-                    async move { tmdb::search_media(&api_key, &query, media_type, year).await },
+                    async move { config.build().search(&query, media_type, year).await },
                     Message::TmdbSearchCompleted,
                 )
             }
@@ -378,6 +570,17 @@ impl Application for ReelApp {
                         self.state.status = format!("Search error: {}", e);
                     }
                 }
+                self.load_thumbnails()
+            }
+
+            Message::ThumbnailsLoaded(thumbnails) => {
+                for (source, result) in thumbnails {
+                    let thumbnail = match result {
+                        Ok(bytes) => Thumbnail::Loaded(image::Handle::from_memory(bytes)),
+                        Err(_) => Thumbnail::Failed,
+                    };
+                    self.state.thumbnails.insert(source, thumbnail);
+                }
                 Command::none()
             }
 
@@ -395,41 +598,34 @@ impl Application for ReelApp {
                         .iter()
                         .enumerate()
                         .filter(|(_, f)| f.is_selected)
-                        .map(|(i, f)| {
-                            let season = f.parsed_info.as_ref().and_then(|p| p.season);
-                            let episode = f.parsed_info.as_ref().and_then(|p| p.episode);
-                            (i, season, episode)
-                        })
+                        .map(|(i, f)| BatchFileInfo::from_media_file(i, f))
                         .collect();
 
                     // If no selected files, try to use the focused file
-                    let files_to_apply: Vec<(usize, Option<u32>, Option<u32>)> =
-                        if selected_files.is_empty() {
-                            if let Some(idx) = self.state.selected_file_index {
-                                if let Some(file) = self.state.files.get(idx) {
-                                    let season = file.parsed_info.as_ref().and_then(|p| p.season);
-                                    let episode = file.parsed_info.as_ref().and_then(|p| p.episode);
-                                    vec![(idx, season, episode)]
-                                } else {
-                                    self.state.status = "No files to apply".to_string();
-                                    return Command::none();
-                                }
+                    let files_to_apply: Vec<BatchFileInfo> = if selected_files.is_empty() {
+                        if let Some(idx) = self.state.selected_file_index {
+                            if let Some(file) = self.state.files.get(idx) {
+                                vec![BatchFileInfo::from_media_file(idx, file)]
                             } else {
-                                self.state.status = "Select files to apply this result".to_string();
+                                self.state.status = "No files to apply".to_string();
                                 return Command::none();
                             }
                         } else {
-                            selected_files
-                        };
+                            self.state.status = "Select files to apply this result".to_string();
+                            return Command::none();
+                        }
+                    } else {
+                        selected_files
+                    };
 
                     // Update media type for all files
-                    for (idx, _, _) in &files_to_apply {
-                        if let Some(file) = self.state.files.get_mut(*idx) {
+                    for info in &files_to_apply {
+                        if let Some(file) = self.state.files.get_mut(info.index) {
                             file.media_type = result.media_type;
                         }
                     }
 
-                    let api_key = self.state.effective_api_key();
+                    let config = self.state.provider_config();
                     let count = files_to_apply.len();
                     self.state.search_loading = true;
                     self.state.status =
@@ -440,24 +636,16 @@ impl Application for ReelApp {
                         async move {
                             use futures::future::join_all;
 
+                            let providers = config.build();
                             let futures: Vec<_> = files_to_apply
                                 .iter()
-                                .map(|(idx, season, episode)| {
-                                    let api_key = api_key.clone();
-                                    let result = result.clone();
-                                    let idx = *idx;
-                                    let season = *season;
-                                    let episode = *episode;
+                                .map(|info| {
+                                    let providers = &providers;
+                                    let result = &result;
 
                                     async move {
-                                        match tmdb::fetch_metadata(
-                                            &api_key, &result, season, episode,
-                                        )
-                                        .await
-                                        {
-                                            Ok(metadata) => (idx, Ok(metadata)),
-                                            Err(e) => (idx, Err(e)),
-                                        }
+                                        let metadata = providers.fetch_metadata(result, info).await;
+                                        (info.index, metadata)
                                     }
                                 })
                                 .collect();
@@ -473,22 +661,24 @@ impl Application for ReelApp {
             Message::BatchMetadataFetched(results) => {
                 self.state.search_loading = false;
                 let mut success_count = 0;
+                let mut pattern_error = None;
                 for (index, result) in results {
                     if let Ok(metadata) = result {
                         if let Some(file) = self.state.files.get_mut(index) {
-                            let new_name = renamer::generate_filename(
+                            pattern_error = pattern_error.or(set_new_filename(
                                 file,
                                 &metadata,
                                 &self.state.rename_pattern,
-                            );
-                            file.new_filename = Some(new_name);
+                            ));
                             file.matched_metadata = Some(metadata);
+                            file.match_candidates.clear();
                             success_count += 1;
                         }
                     }
                 }
-                self.state.status = format!("Applied metadata to {} file(s)", success_count);
-                Command::none()
+                self.state.status = pattern_error
+                    .unwrap_or_else(|| format!("Applied metadata to {} file(s)", success_count));
+                Command::batch([self.load_episode_groups(), self.load_thumbnails()])
             }
 
             Message::FetchMetadataForSelected => {
@@ -505,25 +695,25 @@ impl Application for ReelApp {
 
             Message::MetadataFetched(file_index, result) => {
                 self.state.search_loading = false;
-                match result {
+                match *result {
                     Ok(metadata) => {
+                        self.state.status = "Metadata applied".to_string();
                         if let Some(file) = self.state.files.get_mut(file_index) {
                             // Generate new filename
-                            let new_name = renamer::generate_filename(
-                                file,
-                                &metadata,
-                                &self.state.rename_pattern,
-                            );
-                            file.new_filename = Some(new_name);
+                            if let Some(e) =
+                                set_new_filename(file, &metadata, &self.state.rename_pattern)
+                            {
+                                self.state.status = e;
+                            }
                             file.matched_metadata = Some(metadata);
+                            file.match_candidates.clear();
                         }
-                        self.state.status = "Metadata applied".to_string();
                     }
                     Err(e) => {
                         self.state.status = format!("Metadata error: {}", e);
                     }
                 }
-                Command::none()
+                self.load_thumbnails()
             }
 
             Message::AutoMatchAll => {
@@ -541,41 +731,13 @@ impl Application for ReelApp {
                     .iter()
                     .enumerate()
                     .filter(|(_, f)| f.matched_metadata.is_none())
-                    .map(|(i, f)| {
-                        let mut title = f
-                            .parsed_info
-                            .as_ref()
-                            .map(|p| p.title.clone())
-                            .unwrap_or_default();
-
-                        // If title is empty but it's a TV show, try to use parent folder name
-                        if title.is_empty() && f.media_type == MediaType::TvShow {
-                            if let Some(parent) = f.path.parent() {
-                                if let Some(folder_name) = parent.file_name() {
-                                    title = folder_name.to_string_lossy().to_string();
-                                }
-                            }
-                        }
-
-                        let year = f.parsed_info.as_ref().and_then(|p| p.year);
-                        let season = f.parsed_info.as_ref().and_then(|p| p.season);
-                        let episode = f.parsed_info.as_ref().and_then(|p| p.episode);
-                        let media_type = f.media_type;
-
-                        tmdb::BatchFileInfo {
-                            index: i,
-                            title,
-                            year,
-                            season,
-                            episode,
-                            media_type,
-                        }
-                    })
+                    .map(|(i, f)| BatchFileInfo::from_media_file(i, f))
                     .collect();
 
                 // Use optimized batch matching
+                let config = self.state.provider_config();
                 Command::perform(
-                    async move { tmdb::batch_match_files(&api_key, files_info).await },
+                    async move { config.build().match_files(files_info).await },
                     Message::AutoMatchCompleted,
                 )
             }
@@ -583,23 +745,44 @@ impl Application for ReelApp {
             Message::AutoMatchCompleted(results) => {
                 self.state.loading = false;
                 let mut success_count = 0;
+                let mut review_count = 0;
+                let mut pattern_error = None;
                 let total = results.len();
                 for (index, result) in results {
-                    if let Ok(metadata) = result {
-                        if let Some(file) = self.state.files.get_mut(index) {
-                            let new_name = renamer::generate_filename(
+                    let Some(file) = self.state.files.get_mut(index) else {
+                        continue;
+                    };
+                    match result {
+                        MatchOutcome::Matched(metadata) => {
+                            // A movie-like name can turn out to be a show's special
+                            if metadata.season_number.is_some() {
+                                file.media_type = MediaType::TvShow;
+                            }
+                            pattern_error = pattern_error.or(set_new_filename(
                                 file,
                                 &metadata,
                                 &self.state.rename_pattern,
-                            );
-                            file.new_filename = Some(new_name);
-                            file.matched_metadata = Some(metadata);
+                            ));
+                            file.matched_metadata = Some(*metadata);
+                            file.match_candidates.clear();
                             success_count += 1;
                         }
+                        MatchOutcome::NeedsReview(candidates) => {
+                            // Left unrenamed until someone confirms a candidate
+                            file.new_filename = None;
+                            file.match_candidates = candidates;
+                            review_count += 1;
+                        }
+                        MatchOutcome::Failed(_) => {}
                     }
                 }
-                let failed = total - success_count;
-                if failed > 0 {
+                let failed = total - success_count - review_count;
+                if review_count > 0 {
+                    self.state.status = format!(
+                        "Matched {} of {} files ({} need review, {} need manual search)",
+                        success_count, total, review_count, failed
+                    );
+                } else if failed > 0 {
                     self.state.status = format!(
                         "Matched {} of {} files ({} need manual search)",
                         success_count, total, failed
@@ -608,32 +791,136 @@ impl Application for ReelApp {
                     self.state.status =
                         format!("Successfully matched all {} files!", success_count);
                 }
-                Command::none()
+                if let Some(e) = pattern_error {
+                    self.state.status = e;
+                }
+                Command::batch([self.load_episode_groups(), self.load_thumbnails()])
             }
 
             // Rename
             Message::PatternChanged(pattern) => {
-                self.state.rename_pattern = pattern;
-                // Regenerate filenames for matched files
-                for file in &mut self.state.files {
-                    if let Some(metadata) = &file.matched_metadata {
-                        let new_name =
-                            renamer::generate_filename(file, metadata, &self.state.rename_pattern);
-                        file.new_filename = Some(new_name);
-                    }
+                if let Err(e) = renamer::validate_pattern(&pattern) {
+                    self.state.status = format!("Invalid pattern: {}", e);
+                    return Command::none();
                 }
+                self.select_pattern(pattern)
+            }
+
+            // Custom patterns
+            Message::NewPattern => {
+                let current = &self.state.rename_pattern;
+                self.state.pattern_editor = Some(PatternEditor {
+                    original_name: None,
+                    name: String::new(),
+                    movie_pattern: current.movie_pattern.clone(),
+                    tv_pattern: current.tv_pattern.clone(),
+                    language: current.language.clone().unwrap_or_default(),
+                });
                 Command::none()
             }
 
-            Message::GenerateNewFilenames => {
-                for file in &mut self.state.files {
-                    if let Some(metadata) = &file.matched_metadata {
-                        let new_name =
-                            renamer::generate_filename(file, metadata, &self.state.rename_pattern);
-                        file.new_filename = Some(new_name);
+            Message::DuplicatePattern => {
+                let current = &self.state.rename_pattern;
+                self.state.pattern_editor = Some(PatternEditor {
+                    original_name: None,
+                    name: format!("{} Copy", current.name),
+                    movie_pattern: current.movie_pattern.clone(),
+                    tv_pattern: current.tv_pattern.clone(),
+                    language: current.language.clone().unwrap_or_default(),
+                });
+                Command::none()
+            }
+
+            Message::EditPattern => {
+                let current = &self.state.rename_pattern;
+                if !current.is_builtin() {
+                    self.state.pattern_editor = Some(PatternEditor {
+                        original_name: Some(current.name.clone()),
+                        name: current.name.clone(),
+                        movie_pattern: current.movie_pattern.clone(),
+                        tv_pattern: current.tv_pattern.clone(),
+                        language: current.language.clone().unwrap_or_default(),
+                    });
+                }
+                Command::none()
+            }
+
+            Message::DeletePattern => {
+                let name = self.state.rename_pattern.name.clone();
+                if self.state.rename_pattern.is_builtin() {
+                    return Command::none();
+                }
+                self.state.custom_patterns.retain(|p| p.name != name);
+                self.save_custom_patterns();
+                self.state.status = format!("Deleted pattern \"{}\"", name);
+                self.select_pattern(RenamePattern::default())
+            }
+
+            Message::PatternNameChanged(name) => {
+                if let Some(editor) = &mut self.state.pattern_editor {
+                    editor.name = name;
+                }
+                Command::none()
+            }
+
+            Message::PatternMovieChanged(pattern) => {
+                if let Some(editor) = &mut self.state.pattern_editor {
+                    editor.movie_pattern = pattern;
+                }
+                Command::none()
+            }
+
+            Message::PatternTvChanged(pattern) => {
+                if let Some(editor) = &mut self.state.pattern_editor {
+                    editor.tv_pattern = pattern;
+                }
+                Command::none()
+            }
+
+            Message::PatternLanguageChanged(language) => {
+                if let Some(editor) = &mut self.state.pattern_editor {
+                    editor.language = language;
+                }
+                Command::none()
+            }
+
+            Message::SavePattern => {
+                let pattern = match self.state.validate_pattern_editor() {
+                    Ok(pattern) => pattern,
+                    Err(e) => {
+                        self.state.status = e;
+                        return Command::none();
                     }
+                };
+                let original_name = self
+                    .state
+                    .pattern_editor
+                    .take()
+                    .and_then(|editor| editor.original_name);
+
+                match original_name.and_then(|name| {
+                    self.state
+                        .custom_patterns
+                        .iter()
+                        .position(|p| p.name == name)
+                }) {
+                    Some(index) => self.state.custom_patterns[index] = pattern.clone(),
+                    None => self.state.custom_patterns.push(pattern.clone()),
                 }
-                self.state.status = "Filenames generated".to_string();
+                self.save_custom_patterns();
+                self.state.status = format!("Saved pattern \"{}\"", pattern.name);
+                self.select_pattern(pattern)
+            }
+
+            Message::CancelPatternEditor => {
+                self.state.pattern_editor = None;
+                Command::none()
+            }
+
+            Message::GenerateNewFilenames => {
+                self.state.status = self
+                    .regenerate_filenames()
+                    .unwrap_or_else(|| "Filenames generated".to_string());
                 Command::none()
             }
 
@@ -700,11 +987,7 @@ impl Application for ReelApp {
                     .files
                     .iter()
                     .filter(|f| f.is_selected)
-                    .filter_map(|f| {
-                        f.new_filename
-                            .as_ref()
-                            .map(|new| (f.path.clone(), new.clone()))
-                    })
+                    .filter_map(renamer::RenameEntry::for_file)
                     .collect();
 
                 if files_to_rename.is_empty() {
@@ -712,52 +995,359 @@ impl Application for ReelApp {
                     return Command::none();
                 }
 
-                let output_dir = self.state.output_directory.clone();
+                let (progress_tx, progress_rx) = futures::channel::mpsc::unbounded();
+                let options = renamer::RenameOptions {
+                    output_dir: self.state.output_directory.clone(),
+                    all_or_nothing: self.state.all_or_nothing,
+                    operation: self.state.file_operation,
+                    progress: Some(progress_tx),
+                };
                 self.state.loading = true;
+                self.state.rename_progress = None;
                 self.state.status = "Renaming files...".to_string();
 
-                Command::perform(
-                    async move { renamer::rename_files(files_to_rename, output_dir).await },
-                    Message::RenameCompleted,
-                )
+                Command::batch([
+                    Command::run(progress_rx, Message::RenameProgress),
+                    Command::perform(
+                        async move { renamer::rename_files(files_to_rename, options).await },
+                        Message::RenameCompleted,
+                    ),
+                ])
             }
 
-            Message::RenameCompleted(result) => {
+            Message::ToggleAllOrNothing(enabled) => {
+                self.state.all_or_nothing = enabled;
+                self.settings.all_or_nothing_rename = enabled;
+                let _ = self.settings.save();
+                Command::none()
+            }
+
+            Message::ToggleOfflineMode(enabled) => {
+                self.state.offline_mode = enabled;
+                self.settings.offline_mode = enabled;
+                let _ = self.settings.save();
+                self.state.status = if enabled {
+                    "Offline mode: using cached TMDB data only".to_string()
+                } else {
+                    "Online mode".to_string()
+                };
+                // Posters that weren't cached can be fetched now
+                self.state.retry_failed_thumbnails();
+                self.load_thumbnails()
+            }
+
+            Message::TvProviderChanged(provider) => {
+                // TMDB stays on as a fallback for shows TheTVDB doesn't know
+                let mut order = vec![provider];
+                if provider != ProviderKind::Tmdb {
+                    order.push(ProviderKind::Tmdb);
+                }
+                self.state.providers.tv = order;
+                self.settings.providers = self.state.providers.clone();
+                let _ = self.settings.save();
+                self.state.status = format!("Using {} for TV shows", provider.display_name());
+                Command::none()
+            }
+
+            Message::TvdbApiKeyChanged(key) => {
+                self.state.tvdb.api_key = key.trim().to_string();
+                self.settings.tvdb = self.state.tvdb.clone();
+                let _ = self.settings.save();
+                Command::none()
+            }
+
+            Message::EpisodeGroupsLoaded(show_id, result) => {
+                match result {
+                    Ok(groups) => {
+                        self.state.episode_groups.insert(show_id, groups);
+                    }
+                    Err(e) => {
+                        // Drop the placeholder so the next selection retries
+                        self.state.episode_groups.remove(&show_id);
+                        self.state.status = format!("Could not load episode orders: {}", e);
+                    }
+                }
+                Command::none()
+            }
+
+            Message::EpisodeOrderSelected(show_id, group_id) => {
+                match group_id {
+                    Some(group_id) => self.state.episode_orders.insert(show_id, group_id),
+                    None => self.state.episode_orders.remove(&show_id),
+                };
+                self.settings.episode_orders = self.state.episode_orders.clone();
+                let _ = self.settings.save();
+
+                // Renumber every file already matched to this show
+                self.refetch_matched(|m| m.provider == ProviderKind::Tmdb && m.tmdb_id == show_id)
+            }
+
+            Message::MetadataLanguageChanged(language) => {
+                self.state.metadata_language = language;
+                Command::none()
+            }
+
+            Message::ApplyMetadataLanguage => {
+                match normalize_language(&self.state.metadata_language) {
+                    Ok(language) => {
+                        self.state.metadata_language = language.unwrap_or_default();
+                        self.settings.metadata_language = self.state.metadata_language.clone();
+                        let _ = self.settings.save();
+                        self.refetch_matched(|_| true)
+                    }
+                    Err(e) => {
+                        self.state.status = e;
+                        Command::none()
+                    }
+                }
+            }
+
+            Message::ClearTmdbCache => match self.state.tmdb_cache() {
+                Some(cache) => Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || cache.clear())
+                            .await
+                            .map_err(|e| format!("Failed to clear cache: {}", e))?
+                    },
+                    Message::TmdbCacheCleared,
+                ),
+                None => {
+                    self.state.status = "No cache directory available".to_string();
+                    Command::none()
+                }
+            },
+
+            Message::TmdbCacheCleared(result) => {
+                self.state.status = match result {
+                    Ok(count) => format!("Cleared {} cached TMDB response(s)", count),
+                    Err(e) => format!("Cache error: {}", e),
+                };
+                Command::none()
+            }
+
+            Message::FileOperationChanged(operation) => {
+                self.state.file_operation = operation;
+                self.settings.file_operation = operation;
+                let _ = self.settings.save();
+                Command::none()
+            }
+
+            Message::RenameProgress(progress) => {
+                // Ignore progress that arrives after the batch has finished
+                if self.state.loading {
+                    self.state.status = format!(
+                        "Copying {} ({}/{})...",
+                        progress.filename,
+                        progress.file_index + 1,
+                        progress.file_count
+                    );
+                    self.state.rename_progress = Some(progress);
+                }
+                Command::none()
+            }
+
+            Message::RenameCompleted(report) => {
+                self.state.loading = false;
+                self.state.rename_progress = None;
+                let renamed = report.renamed();
+
+                // Update renamed files with their new paths
+                let mut nfo_targets = Vec::new();
+                for (old_path, new_path) in &renamed {
+                    if let Some(file) = self.state.files.iter_mut().find(|f| f.path == *old_path) {
+                        nfo_targets.extend(nfo::NfoTarget::for_file(
+                            file,
+                            new_path,
+                            &self.state.rename_pattern,
+                        ));
+
+                        // Copies and links leave the original in place, so keep pointing at it
+                        if !report.operation.keeps_source() {
+                            file.path = new_path.clone();
+                            file.filename = new_path
+                                .file_name()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                        }
+
+                        // Clear the new_filename since it's now applied
+                        file.new_filename = None;
+
+                        // Keep the metadata but mark as no longer needing rename
+                        file.is_selected = false;
+                    } else if !report.operation.keeps_source() {
+                        self.state.move_sidecar(old_path, new_path);
+                    }
+                }
+
+                // Record the batch so it can be undone later
+                let mut journal_error = None;
+                if !renamed.is_empty() {
+                    self.reload_journal();
+                    self.state.rename_journal.record_batch(
+                        &self.state.rename_pattern.name,
+                        report.operation,
+                        &renamed,
+                    );
+                    journal_error = self.state.rename_journal.save().err();
+                }
+
+                let failed = report.failures().len();
+                let done = report.operation.past_tense();
+                let moved = report.moved_summary();
+                self.state.status = match report.first_error() {
+                    None => format!("Successfully {} {}", done, moved),
+                    Some(e) if report.rolled_back && renamed.is_empty() => {
+                        format!("Rename cancelled, no files changed: {}", e)
+                    }
+                    Some(e) if report.rolled_back => {
+                        format!("Rollback incomplete, {} still {}: {}", moved, done, e)
+                    }
+                    Some(e) => format!("{} {}, {} failed: {}", moved, done, failed, e),
+                };
+                if let Some(e) = journal_error {
+                    self.state.status = format!("{} (undo unavailable: {})", self.state.status, e);
+                }
+
+                if report.rolled_back || nfo_targets.is_empty() {
+                    return Command::none();
+                }
+                let mut commands = Vec::new();
+                let policy = self.state.nfo_policy;
+                if policy != nfo::NfoPolicy::Off {
+                    commands.push(Command::perform(
+                        nfo::write_nfos(nfo_targets.clone(), policy),
+                        Message::NfosWritten,
+                    ));
+                }
+                if self.state.artwork.enabled {
+                    let client = tmdb::TmdbClient::new(self.state.effective_api_key())
+                        .with_cache(self.state.tmdb_cache())
+                        .with_language(self.state.effective_language());
+                    commands.push(Command::perform(
+                        artwork::download_artwork(nfo_targets, self.state.artwork.clone(), client),
+                        Message::ArtworkDownloaded,
+                    ));
+                }
+                Command::batch(commands)
+            }
+
+            Message::NfoPolicyChanged(policy) => {
+                self.state.nfo_policy = policy;
+                self.settings.nfo_policy = policy;
+                let _ = self.settings.save();
+                Command::none()
+            }
+
+            Message::ToggleArtwork(enabled) => {
+                self.state.artwork.enabled = enabled;
+                self.settings.artwork = self.state.artwork.clone();
+                let _ = self.settings.save();
+                Command::none()
+            }
+
+            Message::ArtworkDownloaded(summary) => {
+                let downloaded = format!("{} image(s) downloaded", summary.downloaded);
+                self.state.status = match summary.errors.first() {
+                    None => format!("{}, {}", self.state.status, downloaded),
+                    Some(e) => format!(
+                        "{}, {}, {} failed: {}",
+                        self.state.status,
+                        downloaded,
+                        summary.errors.len(),
+                        e
+                    ),
+                };
+                Command::none()
+            }
+
+            Message::NfosWritten(summary) => {
+                let written = format!("{} NFO file(s) written", summary.written);
+                self.state.status = match summary.errors.first() {
+                    None => format!("{}, {}", self.state.status, written),
+                    Some(e) => format!(
+                        "{}, {}, {} failed: {}",
+                        self.state.status,
+                        written,
+                        summary.errors.len(),
+                        e
+                    ),
+                };
+                Command::none()
+            }
+
+            // Undo / history
+            Message::UndoLastRename => {
+                self.reload_journal();
+                match self.state.rename_journal.last_active_batch() {
+                    Some(batch) => self.update(Message::RevertBatch(batch.id)),
+                    None => {
+                        self.state.status = "Nothing to undo".to_string();
+                        Command::none()
+                    }
+                }
+            }
+
+            Message::ShowRenameHistory => {
+                self.reload_journal();
+                self.state.show_rename_history = true;
+                Command::none()
+            }
+
+            Message::HideRenameHistory => {
+                self.state.show_rename_history = false;
+                Command::none()
+            }
+
+            Message::RevertBatch(batch_id) => {
+                let Some(batch) = self.state.rename_journal.batch(batch_id).cloned() else {
+                    return Command::none();
+                };
+                self.state.loading = true;
+                self.state.status = "Reverting renames...".to_string();
+                Command::perform(journal::revert_batch_async(batch), Message::BatchReverted)
+            }
+
+            Message::BatchReverted(result) => {
                 self.state.loading = false;
                 match result {
-                    Ok(renamed) => {
-                        let count = renamed.len();
-
-                        // Update renamed files with their new names
-                        for (old_name, new_name) in &renamed {
-                            if let Some(file) = self
-                                .state
-                                .files
-                                .iter_mut()
-                                .find(|f| f.filename == *old_name)
+                    Ok(report) => {
+                        // Point any loaded files back at their original paths
+                        for (current, restored) in &report.reverted {
+                            if let Some(file) =
+                                self.state.files.iter_mut().find(|f| f.path == *current)
                             {
-                                // Update the filename to the new name
-                                file.filename = new_name.clone();
-
-                                // Update the path to the new location
-                                if let Some(ref output_dir) = self.state.output_directory {
-                                    file.path = output_dir.join(new_name);
-                                } else if let Some(parent) = file.path.parent() {
-                                    file.path = parent.join(new_name);
-                                }
-
-                                // Clear the new_filename since it's now applied
+                                file.path = restored.clone();
+                                file.filename = restored
+                                    .file_name()
+                                    .map(|n| n.to_string_lossy().to_string())
+                                    .unwrap_or_default();
                                 file.new_filename = None;
-
-                                // Keep the metadata but mark as no longer needing rename
-                                file.is_selected = false;
+                            } else {
+                                self.state.move_sidecar(current, restored);
                             }
                         }
 
-                        self.state.status = format!("Successfully renamed {} file(s)", count);
+                        // Before merging, while the report's batch id still matches
+                        self.state.rename_journal.apply_revert(&report);
+                        self.reload_journal();
+
+                        self.state.status = if report.refused.is_empty() {
+                            format!("Reverted {} file(s)", report.reverted.len())
+                        } else {
+                            format!(
+                                "Reverted {} file(s), skipped {} ({})",
+                                report.reverted.len(),
+                                report.refused.len(),
+                                report.refused[0].1
+                            )
+                        };
+                        if let Err(e) = self.state.rename_journal.save() {
+                            self.state.status = format!("{} ({})", self.state.status, e);
+                        }
                     }
                     Err(e) => {
-                        self.state.status = format!("Rename error: {}", e);
+                        self.state.status = format!("Undo error: {}", e);
                     }
                 }
                 Command::none()
@@ -804,6 +1394,7 @@ impl Application for ReelApp {
                 for file in &mut self.state.files {
                     file.matched_metadata = None;
                     file.new_filename = None;
+                    file.match_candidates.clear();
                 }
                 self.state.status = "Cleared all matches - ready to re-match".to_string();
                 Command::none()
@@ -841,6 +1432,7 @@ impl Application for ReelApp {
                         Key::Character("r") => Some(Message::ShowRenamePreview),
                         Key::Character("a") => Some(Message::SelectAllFiles),
                         Key::Character("d") => Some(Message::DeselectAllFiles),
+                        Key::Character("z") => Some(Message::UndoLastRename),
                         _ => None,
                     }
                 } else {
@@ -0,0 +1,696 @@
+//! Headless command-line mode
+//!
+//! Drives the same scan → match → rename pipeline as the GUI without
+//! starting `ReelApp`, so Reel can run on machines with no display:
+//!
+//! ```text
+//! reel scan    <folder>
+//! reel match   <folder> [--api-key KEY] [--language CODE]
+//! reel preview <folder> [--pattern NAME] [--output DIR]
+//! reel rename  <folder> [--pattern NAME] [--output DIR] [--operation OP] [--nfo POLICY] [--artwork] [--dry-run] [--all-or-nothing]
+//! ```
+
+use crate::model::{get_default_api_key, MediaFile, MediaType, RenamePattern};
+use crate::settings::AppSettings;
+use crate::utils::file_ops::FileOperation;
+use crate::utils::journal::RenameJournal;
+use crate::utils::metadata::{normalize_language, BatchFileInfo, MatchOutcome, ProviderConfig};
+use crate::utils::nfo::{self, NfoPolicy, NfoTarget};
+use crate::utils::renamer::RenameStatus;
+use crate::utils::tmdb_cache::TmdbCache;
+use crate::utils::{artwork, file_scanner, filename_parser, renamer, tmdb};
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Usage: reel <command> <folder> [options]
+
+Commands:
+  scan       List video files and what was parsed from their names
+  match      Match files against TMDB
+  preview    Show the old -> new names without renaming
+  rename     Match and rename files
+
+Options:
+  --pattern <name>   Naming pattern: Default, Plex, Jellyfin or a saved custom pattern
+  --output <dir>     Move renamed files into this directory
+  --operation <op>   move (default), copy, hardlink, symlink or reflink
+  --nfo <policy>     Write Kodi NFOs: off, keep, overwrite or merge (defaults to the saved setting)
+  --artwork          Download posters, fanart and episode thumbnails next to renamed files
+  --api-key <key>    TMDB API key (defaults to the saved or built-in key)
+  --offline          Only use TMDB responses cached by earlier runs
+  --language <code>  Metadata language, e.g. de or ja-JP (defaults to the saved setting)
+  --dry-run          Only print what would be renamed
+  --all-or-nothing   Undo the whole batch if any file fails to rename
+  -h, --help         Show this help
+
+Running reel without a command starts the GUI.";
+
+/// CLI subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CliCommand {
+    Scan,
+    Match,
+    Preview,
+    Rename,
+}
+
+impl CliCommand {
+    fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "scan" => Some(CliCommand::Scan),
+            "match" => Some(CliCommand::Match),
+            "preview" => Some(CliCommand::Preview),
+            "rename" => Some(CliCommand::Rename),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed command-line arguments
+#[derive(Debug, Clone)]
+pub struct CliArgs {
+    pub command: CliCommand,
+    pub path: PathBuf,
+    pub pattern: RenamePattern,
+    pub output_dir: Option<PathBuf>,
+    pub operation: FileOperation,
+    pub nfo: Option<NfoPolicy>,
+    pub artwork: bool,
+    pub api_key: Option<String>,
+    pub language: Option<String>,
+    pub offline: bool,
+    pub dry_run: bool,
+    pub all_or_nothing: bool,
+}
+
+/// Parsed invocation: either a subcommand to run or a request for help
+#[derive(Debug, Clone)]
+pub enum CliInvocation {
+    Run(CliArgs),
+    Help,
+}
+
+/// Parse arguments (without the program name). `--pattern` may name a
+/// built-in pattern or one of `custom_patterns`, which are only loaded then.
+///
+/// Returns `Ok(None)` when there are no arguments, in which case the GUI
+/// should be launched instead.
+pub fn parse_args(
+    args: &[String],
+    custom_patterns: impl FnOnce() -> Vec<RenamePattern>,
+) -> Result<Option<CliInvocation>, String> {
+    let Some(first) = args.first() else {
+        return Ok(None);
+    };
+
+    if first == "-h" || first == "--help" || first == "help" {
+        return Ok(Some(CliInvocation::Help));
+    }
+    // macOS adds a process serial number when the app is opened from Finder
+    if first.starts_with("-psn_") {
+        return Ok(None);
+    }
+
+    let command =
+        CliCommand::from_arg(first).ok_or_else(|| format!("Unknown command: {}", first))?;
+
+    let mut path = None;
+    let mut pattern_name = None;
+    let mut output_dir = None;
+    let mut operation = FileOperation::Move;
+    let mut nfo = None;
+    let mut artwork = false;
+    let mut api_key = None;
+    let mut language = None;
+    let mut offline = false;
+    let mut dry_run = false;
+    let mut all_or_nothing = false;
+
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Some(CliInvocation::Help)),
+            "--dry-run" => dry_run = true,
+            "--offline" => offline = true,
+            "--all-or-nothing" => all_or_nothing = true,
+            "--artwork" => artwork = true,
+            "--pattern" => {
+                let name = rest.next().ok_or("--pattern requires a value")?;
+                pattern_name = Some(name);
+            }
+            "--output" => {
+                let dir = rest.next().ok_or("--output requires a value")?;
+                output_dir = Some(PathBuf::from(dir));
+            }
+            "--operation" => {
+                let name = rest.next().ok_or("--operation requires a value")?;
+                operation = FileOperation::from_name(name)
+                    .ok_or_else(|| format!("Unknown operation: {}", name))?;
+            }
+            "--nfo" => {
+                let name = rest.next().ok_or("--nfo requires a value")?;
+                nfo = Some(
+                    NfoPolicy::from_name(name)
+                        .ok_or_else(|| format!("Unknown NFO policy: {}", name))?,
+                );
+            }
+            "--api-key" => {
+                let key = rest.next().ok_or("--api-key requires a value")?;
+                api_key = Some(key.clone());
+            }
+            "--language" => {
+                let code = rest.next().ok_or("--language requires a value")?;
+                language = normalize_language(code)?;
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("Unknown option: {}", other));
+            }
+            other => {
+                if path.is_some() {
+                    return Err(format!("Unexpected argument: {}", other));
+                }
+                path = Some(PathBuf::from(other));
+            }
+        }
+    }
+
+    let path = path.ok_or("Missing folder to scan")?;
+    let pattern = match pattern_name {
+        Some(name) => RenamePattern::find(name, &custom_patterns())
+            .ok_or_else(|| format!("Unknown pattern: {}", name))?,
+        None => RenamePattern::default(),
+    };
+
+    Ok(Some(CliInvocation::Run(CliArgs {
+        command,
+        path,
+        pattern,
+        output_dir,
+        operation,
+        nfo,
+        artwork,
+        api_key,
+        language,
+        offline,
+        dry_run,
+        all_or_nothing,
+    })))
+}
+
+/// Run the CLI if the arguments ask for it.
+///
+/// Returns the process exit code, or `None` if the GUI should start instead.
+pub fn run(args: Vec<String>) -> Option<i32> {
+    let invocation = match parse_args(&args, || AppSettings::load().custom_patterns) {
+        Ok(Some(invocation)) => invocation,
+        Ok(None) => return None,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            return Some(2);
+        }
+    };
+
+    let args = match invocation {
+        CliInvocation::Help => {
+            println!("{}", USAGE);
+            return Some(0);
+        }
+        CliInvocation::Run(args) => args,
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("Error: Failed to start runtime: {}", e);
+            return Some(1);
+        }
+    };
+
+    Some(runtime.block_on(execute(args)))
+}
+
+async fn execute(args: CliArgs) -> i32 {
+    let mut files = match file_scanner::scan_directory(args.path.clone()).await {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+
+    for file in &mut files {
+        let (media_type, parsed_info) = filename_parser::parse_filename(&file.filename);
+        file.media_type = media_type;
+        file.parsed_info = Some(parsed_info);
+        file.is_selected = true;
+    }
+
+    if files.is_empty() {
+        println!("No video files found in {}", args.path.display());
+        return 0;
+    }
+
+    if args.command == CliCommand::Scan {
+        print_scan(&files);
+        return 0;
+    }
+
+    let api_key = resolve_api_key(args.api_key.as_deref());
+    if api_key.is_empty() {
+        eprintln!("Error: TMDB API key not set (use --api-key)");
+        return 1;
+    }
+
+    let settings = AppSettings::load();
+    let nfo_policy = args.nfo.unwrap_or(settings.nfo_policy);
+    let artwork_settings = settings.artwork.clone();
+    let download_artwork = args.artwork || artwork_settings.enabled;
+    let config = ProviderConfig {
+        tmdb_api_key: api_key,
+        tvdb: settings.tvdb,
+        settings: settings.providers,
+        cache: TmdbCache::open_default(settings.cache_ttl, args.offline),
+        episode_orders: settings.episode_orders,
+        language: args
+            .language
+            .clone()
+            .or_else(|| args.pattern.language.clone())
+            .or_else(|| {
+                normalize_language(&settings.metadata_language)
+                    .ok()
+                    .flatten()
+            }),
+    };
+    let failed_matches = match_files(&config, &mut files, &args.pattern).await;
+
+    if args.command == CliCommand::Match {
+        return if failed_matches > 0 { 1 } else { 0 };
+    }
+
+    let preview = renamer::generate_preview(&files, &args.pattern);
+    for (old, new) in &preview {
+        println!("{} → {}", old, new);
+    }
+
+    if args.command == CliCommand::Preview || args.dry_run {
+        if args.dry_run {
+            println!("Dry run: {} file(s) would be renamed", preview.len());
+        }
+        return if failed_matches > 0 { 1 } else { 0 };
+    }
+
+    let files_to_rename: Vec<_> = files
+        .iter()
+        .filter_map(renamer::RenameEntry::for_file)
+        .collect();
+
+    let options = renamer::RenameOptions {
+        output_dir: args.output_dir.clone(),
+        all_or_nothing: args.all_or_nothing,
+        operation: args.operation,
+        ..Default::default()
+    };
+    let report = renamer::rename_files(files_to_rename, options).await;
+
+    for outcome in &report.outcomes {
+        match &outcome.status {
+            RenameStatus::Failed(e) | RenameStatus::RollbackFailed(e) => {
+                eprintln!("Failed: {} ({})", outcome.old_path.display(), e)
+            }
+            RenameStatus::RolledBack => {
+                println!("Rolled back: {}", outcome.old_path.display())
+            }
+            _ => {}
+        }
+    }
+
+    let renamed = report.renamed();
+    println!(
+        "{} {}",
+        report.moved_summary(),
+        report.operation.past_tense()
+    );
+    let nfo_targets: Vec<NfoTarget> = renamed
+        .iter()
+        .filter_map(|(old_path, new_path)| {
+            let file = files.iter().find(|f| f.path == *old_path)?;
+            NfoTarget::for_file(file, new_path, &args.pattern)
+        })
+        .collect();
+    if nfo_policy != NfoPolicy::Off && !report.rolled_back && !nfo_targets.is_empty() {
+        let summary = nfo::write_nfos(nfo_targets.clone(), nfo_policy).await;
+        for e in &summary.errors {
+            eprintln!("Warning: {}", e);
+        }
+        println!("{} NFO file(s) written", summary.written);
+    }
+    if download_artwork && !report.rolled_back && !nfo_targets.is_empty() {
+        let client = tmdb::TmdbClient::new(config.tmdb_api_key.clone())
+            .with_cache(config.cache.clone())
+            .with_language(config.language.clone());
+        let summary = artwork::download_artwork(nfo_targets, artwork_settings, client).await;
+        for e in &summary.errors {
+            eprintln!("Warning: {}", e);
+        }
+        println!(
+            "{} image(s) downloaded, {} already there",
+            summary.downloaded, summary.skipped
+        );
+    }
+
+    if !renamed.is_empty() {
+        // Journal CLI renames too, so they can be undone from the GUI
+        let mut journal = RenameJournal::load();
+        journal.record_batch(&args.pattern.name, args.operation, &renamed);
+        if let Err(e) = journal.save() {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    if failed_matches > 0 || !report.failures().is_empty() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Pick the API key: command line, then saved settings, then built-in key
+fn resolve_api_key(cli_key: Option<&str>) -> String {
+    if let Some(key) = cli_key.filter(|k| !k.is_empty()) {
+        return key.to_string();
+    }
+    AppSettings::load()
+        .get_api_key()
+        .filter(|k| !k.is_empty())
+        .unwrap_or_else(get_default_api_key)
+}
+
+fn print_scan(files: &[MediaFile]) {
+    for file in files {
+        let mut details = String::new();
+        if let Some(parsed) = &file.parsed_info {
+            details.push_str(&parsed.title);
+            if let Some(year) = parsed.year {
+                details.push_str(&format!(" ({})", year));
+            }
+            if let (Some(s), Some(e)) = (parsed.season, parsed.episode) {
+                details.push_str(&format!(" S{:02}E{:02}", s, e));
+                if let Some(end) = parsed.episode_end {
+                    details.push_str(&format!("-E{:02}", end));
+                }
+            }
+            if let Some(air_date) = &parsed.air_date {
+                details.push_str(&format!(" {}", air_date));
+            }
+            if let Some(absolute) = parsed.absolute_episode {
+                details.push_str(&format!(" #{}", absolute));
+            }
+        }
+        println!(
+            "[{}] {}  {}",
+            file.media_type.short_name(),
+            file.filename,
+            details
+        );
+    }
+    println!("{} file(s)", files.len());
+}
+
+/// Match all files online, returning the number that failed, were ambiguous
+/// or couldn't be named
+async fn match_files(
+    config: &ProviderConfig,
+    files: &mut [MediaFile],
+    pattern: &RenamePattern,
+) -> usize {
+    let files_info: Vec<_> = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| BatchFileInfo::from_media_file(i, f))
+        .collect();
+
+    let mut results = config.build().match_files(files_info).await;
+    results.sort_by_key(|(index, _)| *index);
+
+    let mut failed = 0;
+    for (index, result) in results {
+        let Some(file) = files.get_mut(index) else {
+            continue;
+        };
+        if !apply_outcome(file, result, pattern) {
+            failed += 1;
+        }
+    }
+    failed
+}
+
+/// Record a file's match and new name, printing what happened. Returns
+/// `false` if the file won't be renamed.
+fn apply_outcome(file: &mut MediaFile, outcome: MatchOutcome, pattern: &RenamePattern) -> bool {
+    match outcome {
+        MatchOutcome::Matched(metadata) => {
+            println!("Matched: {} → {}", file.filename, metadata.title);
+            // A movie-like name can turn out to be a show's special
+            if metadata.season_number.is_some() {
+                file.media_type = MediaType::TvShow;
+            }
+            let named = match renamer::generate_filename(file, &metadata, pattern) {
+                Ok(name) => {
+                    file.new_filename = Some(name);
+                    true
+                }
+                Err(e) => {
+                    eprintln!("Pattern error for {}: {}", file.filename, e);
+                    false
+                }
+            };
+            file.matched_metadata = Some(*metadata);
+            named
+        }
+        MatchOutcome::NeedsReview(candidates) => {
+            let names: Vec<String> = candidates
+                .iter()
+                .map(|c| match c.year {
+                    Some(year) => format!("{} ({})", c.title, year),
+                    None => c.title.clone(),
+                })
+                .collect();
+            eprintln!(
+                "Ambiguous: {} (could be {}), skipped",
+                file.filename,
+                names.join(", ")
+            );
+            false
+        }
+        MatchOutcome::Failed(e) => {
+            eprintln!("No match: {} ({})", file.filename, e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MediaMetadata;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn parse_run(list: &[&str]) -> CliArgs {
+        match parse_args(&args(list), Vec::new) {
+            Ok(Some(CliInvocation::Run(args))) => args,
+            other => panic!("expected run invocation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_no_args_launches_gui() {
+        assert!(parse_args(&[], Vec::new).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_finder_launch_starts_gui() {
+        assert!(parse_args(&args(&["-psn_0_12345"]), Vec::new)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_unknown_command_is_error() {
+        let error = parse_args(&args(&["scna", "/media"]), Vec::new).unwrap_err();
+        assert_eq!(error, "Unknown command: scna");
+        assert!(parse_args(&args(&["--verbose"]), Vec::new).is_err());
+    }
+
+    #[test]
+    fn test_custom_patterns_load_only_for_pattern_option() {
+        let unused = || -> Vec<RenamePattern> { panic!("custom patterns loaded") };
+        assert!(parse_args(&args(&["scan", "/in"]), unused).is_ok());
+        assert!(parse_args(&args(&["scna", "/in"]), unused).is_err());
+    }
+
+    #[test]
+    fn test_help() {
+        assert!(matches!(
+            parse_args(&args(&["--help"]), Vec::new),
+            Ok(Some(CliInvocation::Help))
+        ));
+        assert!(matches!(
+            parse_args(&args(&["rename", "-h"]), Vec::new),
+            Ok(Some(CliInvocation::Help))
+        ));
+    }
+
+    #[test]
+    fn test_parse_scan() {
+        let parsed = parse_run(&["scan", "/media/downloads"]);
+        assert_eq!(parsed.command, CliCommand::Scan);
+        assert_eq!(parsed.path, PathBuf::from("/media/downloads"));
+        assert!(!parsed.dry_run);
+        assert!(!parsed.all_or_nothing);
+        assert!(!parsed.offline);
+        assert_eq!(parsed.pattern.name, "Default");
+        assert_eq!(parsed.operation, FileOperation::Move);
+        assert_eq!(parsed.language, None);
+        assert_eq!(parsed.nfo, None);
+        assert!(!parsed.artwork);
+    }
+
+    #[test]
+    fn test_parse_rename_with_options() {
+        let parsed = parse_run(&[
+            "rename",
+            "/in",
+            "--dry-run",
+            "--pattern",
+            "plex",
+            "--output",
+            "/out",
+            "--api-key",
+            "abc",
+            "--all-or-nothing",
+            "--operation",
+            "hardlink",
+            "--offline",
+            "--language",
+            "de-de",
+            "--nfo",
+            "merge",
+            "--artwork",
+        ]);
+        assert_eq!(parsed.command, CliCommand::Rename);
+        assert!(parsed.dry_run);
+        assert_eq!(parsed.pattern.name, "Plex");
+        assert_eq!(parsed.output_dir, Some(PathBuf::from("/out")));
+        assert_eq!(parsed.api_key, Some("abc".to_string()));
+        assert!(parsed.all_or_nothing);
+        assert_eq!(parsed.operation, FileOperation::Hardlink);
+        assert!(parsed.offline);
+        assert_eq!(parsed.language, Some("de-DE".to_string()));
+        assert_eq!(parsed.nfo, Some(NfoPolicy::Merge));
+        assert!(parsed.artwork);
+    }
+
+    #[test]
+    fn test_missing_folder_is_error() {
+        assert!(parse_args(&args(&["match"]), Vec::new).is_err());
+    }
+
+    #[test]
+    fn test_parse_custom_pattern() {
+        let custom = RenamePattern {
+            name: "Library DE".to_string(),
+            movie_pattern: "{title}".to_string(),
+            tv_pattern: "{show}".to_string(),
+            language: Some("de-DE".to_string()),
+        };
+        let list = args(&["preview", "/in", "--pattern", "library de"]);
+        let parsed = match parse_args(&list, || vec![custom]) {
+            Ok(Some(CliInvocation::Run(args))) => args,
+            other => panic!("expected run invocation, got {:?}", other),
+        };
+        assert_eq!(parsed.pattern.name, "Library DE");
+        assert_eq!(parsed.pattern.language, Some("de-DE".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_pattern_is_error() {
+        assert!(parse_args(&args(&["preview", "/in", "--pattern", "Kodi"]), Vec::new).is_err());
+    }
+
+    #[test]
+    fn test_unknown_operation_is_error() {
+        assert!(parse_args(
+            &args(&["rename", "/in", "--operation", "teleport"]),
+            Vec::new
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_unknown_language_is_error() {
+        assert!(parse_args(&args(&["match", "/in", "--language", "German"]), Vec::new).is_err());
+    }
+
+    #[test]
+    fn test_unknown_nfo_policy_is_error() {
+        assert!(parse_args(&args(&["rename", "/in", "--nfo", "always"]), Vec::new).is_err());
+    }
+
+    #[test]
+    fn test_unknown_option_is_error() {
+        assert!(parse_args(&args(&["scan", "/in", "--verbose"]), Vec::new).is_err());
+    }
+
+    #[test]
+    fn test_pattern_error_counts_as_failure() {
+        let metadata = MediaMetadata {
+            title: "The Matrix".to_string(),
+            ..Default::default()
+        };
+        let mut file = MediaFile::new(PathBuf::from("/in/The.Matrix.1999.mkv"));
+        let broken = RenamePattern {
+            name: "Broken".to_string(),
+            movie_pattern: "{title} {nope}".to_string(),
+            tv_pattern: "{show}".to_string(),
+            language: None,
+        };
+        let matched = || MatchOutcome::Matched(Box::new(metadata.clone()));
+
+        assert!(!apply_outcome(&mut file, matched(), &broken));
+        assert_eq!(file.new_filename, None);
+
+        assert!(apply_outcome(
+            &mut file,
+            matched(),
+            &RenamePattern::default()
+        ));
+        assert_eq!(file.new_filename.as_deref(), Some("The Matrix.mkv"));
+    }
+
+    #[test]
+    fn test_matched_special_is_renamed_as_episode() {
+        let metadata = MediaMetadata {
+            title: "Doctor Who".to_string(),
+            show_name: Some("Doctor Who".to_string()),
+            season_number: Some(0),
+            episode_number: Some(12),
+            episode_title: Some("Resolution".to_string()),
+            ..Default::default()
+        };
+        let mut file = MediaFile::new(PathBuf::from("/in/Doctor.Who.Special.Resolution.2019.mkv"));
+        file.media_type = MediaType::Movie;
+
+        let matched = MatchOutcome::Matched(Box::new(metadata));
+        assert!(apply_outcome(&mut file, matched, &RenamePattern::default()));
+        assert_eq!(file.media_type, MediaType::TvShow);
+        assert!(file.new_filename.unwrap().contains("S00E12"));
+    }
+
+    #[test]
+    fn test_option_missing_value_is_error() {
+        assert!(parse_args(&args(&["rename", "/in", "--output"]), Vec::new).is_err());
+    }
+}
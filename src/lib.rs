@@ -9,8 +9,10 @@
 //! - Rename files with customizable patterns
 //! - Organize into folder structures
 //! - Support for TV shows and movies
+//! - Headless command-line mode for servers without a display
 
 pub mod app;
+pub mod cli;
 pub mod message;
 pub mod model;
 pub mod settings;
@@ -17,6 +17,11 @@ fn load_icon() -> Option<iced::window::Icon> {
 }
 
 fn main() -> iced::Result {
+    // Headless subcommands (scan, match, preview, rename) skip the GUI entirely
+    if let Some(code) = reel::cli::run(std::env::args().skip(1).collect()) {
+        std::process::exit(code);
+    }
+
     let fira_sans_font = Font::with_name("Fira Sans");
 
     ReelApp::run(Settings {
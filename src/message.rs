@@ -1,4 +1,11 @@
-use crate::model::{MediaFile, MediaMetadata, RenamePattern, SearchResult};
+use crate::model::{MediaFile, MediaMetadata, ProviderKind, RenamePattern, SearchResult};
+use crate::utils::artwork::ArtworkSummary;
+use crate::utils::file_ops::FileOperation;
+use crate::utils::journal::RevertReport;
+use crate::utils::metadata::MatchOutcome;
+use crate::utils::nfo::{NfoPolicy, NfoSummary};
+use crate::utils::renamer::{RenameProgress, RenameReport};
+use crate::utils::tmdb::EpisodeGroup;
 use std::path::PathBuf;
 use std::time::Instant;
 
@@ -36,11 +43,21 @@ pub enum Message {
     TmdbSearchCompleted(Result<Vec<SearchResult>, String>),
     TmdbSearchInputChanged(String),
     ApplySearchResult(usize), // index in search_results
+    ThumbnailsLoaded(Vec<(String, Result<Vec<u8>, String>)>), // poster source, image bytes
     FetchMetadataForSelected,
-    MetadataFetched(usize, Result<MediaMetadata, String>), // file_index, result
+    MetadataFetched(usize, Box<Result<MediaMetadata, String>>), // file_index, result
     BatchMetadataFetched(Vec<(usize, Result<MediaMetadata, String>)>), // for applying search result to multiple files
     AutoMatchAll,
-    AutoMatchCompleted(Vec<(usize, Result<MediaMetadata, String>)>),
+    AutoMatchCompleted(Vec<(usize, MatchOutcome)>),
+    ToggleOfflineMode(bool), // Serve TMDB responses only from the disk cache
+    ClearTmdbCache,
+    TmdbCacheCleared(Result<usize, String>), // number of entries removed
+    TvProviderChanged(ProviderKind),         // Primary metadata provider for TV shows
+    TvdbApiKeyChanged(String),
+    EpisodeGroupsLoaded(u64, Result<Vec<EpisodeGroup>, String>), // show ID, groups
+    EpisodeOrderSelected(u64, Option<String>), // show ID, episode group (None = aired order)
+    MetadataLanguageChanged(String),
+    ApplyMetadataLanguage, // Save the language and fetch matched files' metadata again
 
     // Rename
     PatternChanged(RenamePattern),
@@ -51,7 +68,34 @@ pub enum Message {
     ShowRenamePreview,
     HideRenamePreview,
     ExecuteRename,
-    RenameCompleted(Result<Vec<(String, String)>, String>), // (old, new) pairs
+    ToggleAllOrNothing(bool),
+
+    // Custom patterns
+    NewPattern,
+    DuplicatePattern,
+    EditPattern,
+    DeletePattern,
+    PatternNameChanged(String),
+    PatternMovieChanged(String),
+    PatternTvChanged(String),
+    PatternLanguageChanged(String),
+    SavePattern,
+    CancelPatternEditor,
+
+    FileOperationChanged(FileOperation),
+    RenameProgress(RenameProgress),
+    RenameCompleted(RenameReport),
+    NfoPolicyChanged(NfoPolicy),
+    NfosWritten(NfoSummary),
+    ToggleArtwork(bool),
+    ArtworkDownloaded(ArtworkSummary),
+
+    // Undo / history
+    UndoLastRename,
+    ShowRenameHistory,
+    HideRenameHistory,
+    RevertBatch(u64), // journal batch id
+    BatchReverted(Result<RevertReport, String>),
 
     // Settings
     SaveApiKey,
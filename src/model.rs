@@ -1,4 +1,16 @@
-use std::path::PathBuf;
+use crate::utils::artwork::ArtworkSettings;
+use crate::utils::file_ops::FileOperation;
+use crate::utils::journal::RenameJournal;
+use crate::utils::metadata::{normalize_language, ProviderConfig, ProviderSettings};
+use crate::utils::nfo::NfoPolicy;
+use crate::utils::renamer::{self, RenameProgress};
+use crate::utils::sidecar::Sidecar;
+use crate::utils::tmdb::EpisodeGroup;
+use crate::utils::tmdb_cache::{CacheTtl, TmdbCache};
+use crate::utils::tvdb::TvdbSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Media type classification
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -27,6 +39,24 @@ impl MediaType {
     }
 }
 
+/// Online database metadata is fetched from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    Tmdb,
+    Tvdb,
+}
+
+impl ProviderKind {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ProviderKind::Tmdb => "TMDB",
+            ProviderKind::Tvdb => "TheTVDB",
+        }
+    }
+}
+
 /// Represents a media file to be processed
 #[derive(Debug, Clone)]
 pub struct MediaFile {
@@ -39,6 +69,8 @@ pub struct MediaFile {
     pub matched_metadata: Option<MediaMetadata>,
     pub new_filename: Option<String>,
     pub is_selected: bool,
+    pub match_candidates: Vec<SearchResult>, // Ambiguous auto-match awaiting confirmation
+    pub sidecars: Vec<Sidecar>, // Subtitles, NFOs and artwork renamed along with the video
 }
 
 impl MediaFile {
@@ -63,9 +95,16 @@ impl MediaFile {
             matched_metadata: None,
             new_filename: None,
             is_selected: false,
+            match_candidates: Vec::new(),
+            sidecars: Vec::new(),
         }
     }
 
+    /// Auto-match found several plausible candidates and someone has to pick one
+    pub fn needs_review(&self) -> bool {
+        self.matched_metadata.is_none() && !self.match_candidates.is_empty()
+    }
+
     pub fn formatted_size(&self) -> String {
         let bytes = self.size_bytes as f64;
         if bytes >= 1_073_741_824.0 {
@@ -87,18 +126,25 @@ pub struct ParsedMediaInfo {
     pub year: Option<u32>,
     pub season: Option<u32>,
     pub episode: Option<u32>,
+    pub episode_end: Option<u32>, // Last episode of a multi-episode file (S01E01E02)
+    pub absolute_episode: Option<u32>, // Anime absolute number ("[Group] Show - 137")
+    pub air_date: Option<String>, // Daily shows ("Show.2024.03.15"), as YYYY-MM-DD
+    pub special: bool,            // Special/OVA outside the regular seasons (season 0)
     pub episode_title: Option<String>,
     pub quality: Option<String>, // e.g., "1080p", "720p", "4K"
     pub source: Option<String>,  // e.g., "BluRay", "WEB-DL", "HDTV"
     pub codec: Option<String>,   // e.g., "x264", "x265", "HEVC"
     pub audio: Option<String>,   // e.g., "DTS", "AAC", "AC3"
     pub group: Option<String>,   // Release group
+    pub crc: Option<String>,     // CRC32 tag, e.g. "[ABCD1234]"
 }
 
-/// Metadata from online database (TMDB)
+/// Metadata from an online database
 #[derive(Debug, Clone, Default)]
 pub struct MediaMetadata {
-    pub tmdb_id: u64,
+    pub tmdb_id: u64, // 0 when the metadata didn't come from TMDB
+    pub provider: ProviderKind,
+    pub provider_id: u64, // ID within the provider's database
     pub title: String,
     pub original_title: Option<String>,
     pub year: Option<u32>,
@@ -111,37 +157,60 @@ pub struct MediaMetadata {
     // TV Show specific
     pub season_number: Option<u32>,
     pub episode_number: Option<u32>,
-    pub episode_title: Option<String>,
+    pub episode_end: Option<u32>, // Last episode of a multi-episode file
+    pub absolute_episode: Option<u32>, // Episode number counted across all seasons
+    pub episode_title: Option<String>, // Joined with " & " for multi-episode files
     pub air_date: Option<String>,
     pub show_name: Option<String>,
+    pub still_path: Option<String>, // Episode still; TMDB path or full TheTVDB URL
 }
 
-/// Search result from TMDB
+/// Search result from a metadata provider
 #[derive(Debug, Clone)]
 pub struct SearchResult {
-    pub tmdb_id: u64,
+    pub provider: ProviderKind,
+    pub id: u64, // ID within the provider's database
     pub title: String,
     pub year: Option<u32>,
     pub media_type: MediaType,
     pub overview: Option<String>,
     pub poster_path: Option<String>,
     pub vote_average: Option<f32>,
+    pub popularity: Option<f32>,
+    pub confidence: Option<f32>, // 0.0-1.0 when ranked against a file
 }
 
-/// Rename pattern template
+/// Number of search results listed in the manual search
+pub const SEARCH_RESULTS_SHOWN: usize = 12;
+
+/// Poster thumbnail shown next to a search result or match
 #[derive(Debug, Clone)]
+pub enum Thumbnail {
+    Loading,
+    Loaded(iced::widget::image::Handle),
+    Failed,
+}
+
+/// Rename pattern template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RenamePattern {
     pub name: String,
     pub movie_pattern: String,
     pub tv_pattern: String,
+    /// Metadata language for this pattern, overriding the app setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
 }
 
 impl Default for RenamePattern {
     fn default() -> Self {
         Self {
             name: "Default".to_string(),
-            movie_pattern: "{title} ({year})".to_string(),
-            tv_pattern: "{show} - S{season:02}E{episode:02} - {episode_title}".to_string(),
+            movie_pattern: "{title}[ ({year})]".to_string(),
+            tv_pattern:
+                "{show} - S{season:02}E{episode:02}[-E{episode_end:02}][ - {episode_title}]"
+                    .to_string(),
+            language: None,
         }
     }
 }
@@ -150,16 +219,21 @@ impl RenamePattern {
     pub fn plex() -> Self {
         Self {
             name: "Plex".to_string(),
-            movie_pattern: "{title} ({year})".to_string(),
-            tv_pattern: "{show} - s{season:02}e{episode:02} - {episode_title}".to_string(),
+            movie_pattern: "{title}[ ({year})]".to_string(),
+            tv_pattern:
+                "{show} - s{season:02}e{episode:02}[-e{episode_end:02}][ - {episode_title}]"
+                    .to_string(),
+            language: None,
         }
     }
 
     pub fn jellyfin() -> Self {
         Self {
             name: "Jellyfin".to_string(),
-            movie_pattern: "{title} ({year})".to_string(),
-            tv_pattern: "{show} S{season:02}E{episode:02} {episode_title}".to_string(),
+            movie_pattern: "{title}[ ({year})]".to_string(),
+            tv_pattern: "{show} S{season:02}E{episode:02}[-E{episode_end:02}][ {episode_title}]"
+                .to_string(),
+            language: None,
         }
     }
 
@@ -170,6 +244,41 @@ impl RenamePattern {
             RenamePattern::jellyfin(),
         ]
     }
+
+    /// Built-in patterns can't be edited or deleted
+    pub fn is_builtin(&self) -> bool {
+        Self::all_patterns().iter().any(|p| p.name == self.name)
+    }
+
+    /// Find a built-in or user-defined pattern by name
+    pub fn find(name: &str, custom: &[RenamePattern]) -> Option<RenamePattern> {
+        Self::all_patterns()
+            .into_iter()
+            .chain(custom.iter().cloned())
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Pattern being created or edited in the pattern editor
+#[derive(Debug, Clone, Default)]
+pub struct PatternEditor {
+    /// Name of the custom pattern being edited, None when creating a new one
+    pub original_name: Option<String>,
+    pub name: String,
+    pub movie_pattern: String,
+    pub tv_pattern: String,
+    pub language: String, // Empty = use the app's metadata language
+}
+
+impl PatternEditor {
+    pub fn pattern(&self) -> RenamePattern {
+        RenamePattern {
+            name: self.name.trim().to_string(),
+            movie_pattern: self.movie_pattern.clone(),
+            tv_pattern: self.tv_pattern.clone(),
+            language: normalize_language(&self.language).ok().flatten(),
+        }
+    }
 }
 
 /// Application state
@@ -185,20 +294,39 @@ pub struct AppState {
     pub search_results: Vec<SearchResult>,
     pub search_loading: bool,
     pub search_input: String,
+    pub thumbnails: HashMap<String, Thumbnail>, // Poster thumbnails by TMDB path or URL
 
     // TMDB API
     pub tmdb_api_key: String,    // User-entered key (empty if using default)
     pub using_default_key: bool, // True = use built-in key, input shows placeholder
     pub api_key_valid: Option<bool>, // None = not verified, Some(true) = valid, Some(false) = invalid
     pub api_key_verifying: bool,
+    pub offline_mode: bool, // Serve TMDB responses only from the disk cache
+    pub cache_ttl: CacheTtl,
+    pub providers: ProviderSettings, // Metadata providers per media type
+    pub tvdb: TvdbSettings,
+    pub episode_orders: HashMap<u64, String>, // TMDB episode group ID by show ID
+    pub episode_groups: HashMap<u64, Vec<EpisodeGroup>>, // Groups loaded per show ID
+    pub metadata_language: String,            // e.g. "de-DE"; empty = TMDB's default (English)
 
     // Rename settings
     pub rename_pattern: RenamePattern,
+    pub custom_patterns: Vec<RenamePattern>,
+    pub pattern_editor: Option<PatternEditor>,
     pub output_directory: Option<PathBuf>,
+    pub all_or_nothing: bool, // Undo the whole batch if any file fails
+    pub file_operation: FileOperation, // Move, copy or link files
+    pub nfo_policy: NfoPolicy, // Whether and how to write Kodi NFOs after renaming
+    pub artwork: ArtworkSettings, // Posters and fanart downloaded after renaming
+    pub rename_progress: Option<RenameProgress>, // Copy progress of a cross-filesystem move
 
     // Confirmation modal
     pub show_rename_confirm: bool,
     pub rename_preview: Vec<(String, String)>, // (old_name, new_name)
+
+    // Rename history (undo journal)
+    pub rename_journal: RenameJournal,
+    pub show_rename_history: bool,
 }
 
 /// Default API key - injected at build time via REEL_TMDB_API_KEY environment variable
@@ -225,14 +353,31 @@ impl AppState {
             search_results: Vec::new(),
             search_loading: false,
             search_input: String::new(),
+            thumbnails: HashMap::new(),
             tmdb_api_key: String::new(), // Empty - actual key is hidden
             using_default_key: has_default,
             api_key_valid: None,
             api_key_verifying: false,
+            offline_mode: false,
+            cache_ttl: CacheTtl::default(),
+            providers: ProviderSettings::default(),
+            tvdb: TvdbSettings::default(),
+            episode_orders: HashMap::new(),
+            episode_groups: HashMap::new(),
+            metadata_language: String::new(),
             rename_pattern: RenamePattern::default(),
+            custom_patterns: Vec::new(),
+            pattern_editor: None,
             output_directory: None,
+            all_or_nothing: false,
+            file_operation: FileOperation::Move,
+            nfo_policy: NfoPolicy::Off,
+            artwork: ArtworkSettings::default(),
+            rename_progress: None,
             show_rename_confirm: false,
             rename_preview: Vec::new(),
+            rename_journal: RenameJournal::default(),
+            show_rename_history: false,
         }
     }
 
@@ -245,6 +390,70 @@ impl AppState {
         }
     }
 
+    /// Disk cache for TMDB requests
+    pub fn tmdb_cache(&self) -> Option<TmdbCache> {
+        TmdbCache::open_default(self.cache_ttl, self.offline_mode)
+    }
+
+    /// Provider tried first for TV shows
+    pub fn tv_provider(&self) -> ProviderKind {
+        self.providers.tv.first().copied().unwrap_or_default()
+    }
+
+    /// Everything needed to create metadata providers in a background task
+    pub fn provider_config(&self) -> ProviderConfig {
+        ProviderConfig {
+            tmdb_api_key: self.effective_api_key(),
+            tvdb: self.tvdb.clone(),
+            settings: self.providers.clone(),
+            cache: self.tmdb_cache(),
+            episode_orders: self.episode_orders.clone(),
+            language: self.effective_language(),
+        }
+    }
+
+    /// Language to fetch metadata in: the current pattern's, else the app setting
+    pub fn effective_language(&self) -> Option<String> {
+        self.rename_pattern
+            .language
+            .clone()
+            .or_else(|| normalize_language(&self.metadata_language).ok().flatten())
+    }
+
+    /// TMDB ID of the show the selected file was matched to
+    pub fn selected_show_id(&self) -> Option<u64> {
+        let metadata = self.selected_file()?.matched_metadata.as_ref()?;
+        (metadata.provider == ProviderKind::Tmdb
+            && metadata.tmdb_id != 0
+            && metadata.season_number.is_some())
+        .then_some(metadata.tmdb_id)
+    }
+
+    /// Forget posters that failed to download, so they're requested again
+    pub fn retry_failed_thumbnails(&mut self) {
+        self.thumbnails
+            .retain(|_, thumbnail| !matches!(thumbnail, Thumbnail::Failed));
+    }
+
+    /// Posters of the shown search results and the selected file's match
+    /// that haven't been requested yet
+    pub fn missing_thumbnails(&self) -> Vec<String> {
+        let results = self.search_results.iter().take(SEARCH_RESULTS_SHOWN);
+        let matched = self
+            .selected_file()
+            .and_then(|f| f.matched_metadata.as_ref());
+        let mut sources: Vec<String> = Vec::new();
+        for source in results
+            .filter_map(|r| r.poster_path.as_ref())
+            .chain(matched.and_then(|m| m.poster_path.as_ref()))
+        {
+            if !self.thumbnails.contains_key(source) && !sources.contains(source) {
+                sources.push(source.clone());
+            }
+        }
+        sources
+    }
+
     pub fn filtered_files(&self) -> Vec<(usize, &MediaFile)> {
         if self.search_query.trim().is_empty() {
             self.files.iter().enumerate().collect()
@@ -262,6 +471,18 @@ impl AppState {
         self.selected_file_index.and_then(|idx| self.files.get(idx))
     }
 
+    /// Point a sidecar that was moved on disk at its new path
+    pub fn move_sidecar(&mut self, old_path: &Path, new_path: &Path) {
+        if let Some(sidecar) = self
+            .files
+            .iter_mut()
+            .flat_map(|f| f.sidecars.iter_mut())
+            .find(|s| s.path == old_path)
+        {
+            sidecar.path = new_path.to_path_buf();
+        }
+    }
+
     pub fn selected_file_mut(&mut self) -> Option<&mut MediaFile> {
         self.selected_file_index
             .and_then(|idx| self.files.get_mut(idx))
@@ -274,6 +495,37 @@ impl AppState {
             .count()
     }
 
+    /// Built-in patterns followed by the user's own
+    pub fn all_patterns(&self) -> Vec<RenamePattern> {
+        let mut patterns = RenamePattern::all_patterns();
+        patterns.extend(self.custom_patterns.iter().cloned());
+        patterns
+    }
+
+    /// Validate the pattern editor, returning the pattern it would save
+    pub fn validate_pattern_editor(&self) -> Result<RenamePattern, String> {
+        let Some(editor) = &self.pattern_editor else {
+            return Err("No pattern is being edited".to_string());
+        };
+        let pattern = editor.pattern();
+        if pattern.name.is_empty() {
+            return Err("Pattern name is required".to_string());
+        }
+        let name_taken = self.all_patterns().iter().any(|p| {
+            p.name.eq_ignore_ascii_case(&pattern.name)
+                && editor.original_name.as_deref() != Some(p.name.as_str())
+        });
+        if name_taken {
+            return Err(format!(
+                "A pattern named \"{}\" already exists",
+                pattern.name
+            ));
+        }
+        normalize_language(&editor.language)?;
+        renamer::validate_pattern(&pattern)?;
+        Ok(pattern)
+    }
+
     pub fn files_ready_for_rename(&self) -> Vec<&MediaFile> {
         self.files
             .iter()
@@ -464,6 +716,28 @@ mod tests {
         assert!(file.matched_metadata.is_none());
         assert!(file.new_filename.is_none());
         assert!(!file.is_selected);
+        assert!(!file.needs_review());
+    }
+
+    #[test]
+    fn test_media_file_needs_review_until_matched() {
+        let mut file = MediaFile::new(PathBuf::from("/test/The.Thing.mkv"));
+        file.match_candidates = vec![SearchResult {
+            provider: ProviderKind::Tmdb,
+            id: 1091,
+            title: "The Thing".to_string(),
+            year: Some(1982),
+            media_type: MediaType::Movie,
+            overview: None,
+            poster_path: None,
+            vote_average: None,
+            popularity: None,
+            confidence: Some(0.85),
+        }];
+        assert!(file.needs_review());
+
+        file.matched_metadata = Some(MediaMetadata::default());
+        assert!(!file.needs_review());
     }
 
     // ==================== PARSED MEDIA INFO TESTS ====================
@@ -490,12 +764,17 @@ mod tests {
             year: Some(2023),
             season: Some(1),
             episode: Some(5),
+            episode_end: None,
+            absolute_episode: None,
+            air_date: None,
+            special: false,
             episode_title: Some("Pilot".to_string()),
             quality: Some("1080p".to_string()),
             source: Some("BluRay".to_string()),
             codec: Some("x265".to_string()),
             audio: Some("DTS".to_string()),
             group: Some("SPARKS".to_string()),
+            crc: None,
         };
         assert_eq!(info.title, "Test Movie");
         assert_eq!(info.year, Some(2023));
@@ -519,16 +798,21 @@ mod tests {
     fn test_media_metadata_movie() {
         let metadata = MediaMetadata {
             tmdb_id: 603,
+            provider: ProviderKind::Tmdb,
+            provider_id: 603,
             title: "The Matrix".to_string(),
             original_title: Some("The Matrix".to_string()),
             year: Some(1999),
             overview: Some("Description".to_string()),
             poster_path: Some("/path.jpg".to_string()),
             backdrop_path: None,
+            still_path: None,
             vote_average: Some(8.7),
             genres: vec!["Action".to_string(), "Sci-Fi".to_string()],
             season_number: None,
             episode_number: None,
+            episode_end: None,
+            absolute_episode: None,
             episode_title: None,
             air_date: None,
             show_name: None,
@@ -542,16 +826,21 @@ mod tests {
     fn test_media_metadata_tv_show() {
         let metadata = MediaMetadata {
             tmdb_id: 1396,
+            provider: ProviderKind::Tmdb,
+            provider_id: 1396,
             title: "Pilot".to_string(),
             original_title: None,
             year: Some(2008),
             overview: None,
             poster_path: None,
             backdrop_path: None,
+            still_path: None,
             vote_average: Some(9.5),
             genres: vec!["Drama".to_string()],
             season_number: Some(1),
             episode_number: Some(1),
+            episode_end: None,
+            absolute_episode: None,
             episode_title: Some("Pilot".to_string()),
             air_date: Some("2008-01-20".to_string()),
             show_name: Some("Breaking Bad".to_string()),
@@ -566,15 +855,18 @@ mod tests {
     #[test]
     fn test_search_result() {
         let result = SearchResult {
-            tmdb_id: 603,
+            provider: ProviderKind::Tmdb,
+            id: 603,
             title: "The Matrix".to_string(),
             year: Some(1999),
             media_type: MediaType::Movie,
             overview: Some("A computer hacker learns...".to_string()),
             poster_path: Some("/path.jpg".to_string()),
             vote_average: Some(8.7),
+            popularity: Some(85.3),
+            confidence: None,
         };
-        assert_eq!(result.tmdb_id, 603);
+        assert_eq!(result.id, 603);
         assert_eq!(result.title, "The Matrix");
         assert_eq!(result.media_type, MediaType::Movie);
     }
@@ -585,10 +877,10 @@ mod tests {
     fn test_rename_pattern_default() {
         let pattern = RenamePattern::default();
         assert_eq!(pattern.name, "Default");
-        assert_eq!(pattern.movie_pattern, "{title} ({year})");
+        assert_eq!(pattern.movie_pattern, "{title}[ ({year})]");
         assert_eq!(
             pattern.tv_pattern,
-            "{show} - S{season:02}E{episode:02} - {episode_title}"
+            "{show} - S{season:02}E{episode:02}[-E{episode_end:02}][ - {episode_title}]"
         );
     }
 
@@ -612,6 +904,83 @@ mod tests {
         assert_eq!(patterns.len(), 3);
     }
 
+    #[test]
+    fn test_find_pattern_includes_custom() {
+        let custom = vec![RenamePattern {
+            name: "Kodi".to_string(),
+            movie_pattern: "{title}".to_string(),
+            tv_pattern: "{show}".to_string(),
+            language: None,
+        }];
+        assert_eq!(RenamePattern::find("plex", &custom).unwrap().name, "Plex");
+        assert_eq!(
+            RenamePattern::find("Kodi", &custom).unwrap().movie_pattern,
+            "{title}"
+        );
+        assert!(RenamePattern::find("Kodi", &[]).is_none());
+        assert!(!custom[0].is_builtin());
+        assert!(RenamePattern::default().is_builtin());
+    }
+
+    #[test]
+    fn test_validate_pattern_editor() {
+        let mut state = AppState::new();
+        state.custom_patterns.push(RenamePattern {
+            name: "Mine".to_string(),
+            movie_pattern: "{title}".to_string(),
+            tv_pattern: "{show}".to_string(),
+            language: None,
+        });
+
+        let editor = |original: Option<&str>, name: &str, movie: &str| PatternEditor {
+            original_name: original.map(str::to_string),
+            name: name.to_string(),
+            movie_pattern: movie.to_string(),
+            tv_pattern: "{show}".to_string(),
+            language: String::new(),
+        };
+
+        state.pattern_editor = Some(editor(None, "  ", "{title}"));
+        assert!(state.validate_pattern_editor().is_err());
+
+        // Names must be unique, except when saving a pattern under its own name
+        state.pattern_editor = Some(editor(None, "plex", "{title}"));
+        assert!(state.validate_pattern_editor().is_err());
+        state.pattern_editor = Some(editor(None, "Mine", "{title}"));
+        assert!(state.validate_pattern_editor().is_err());
+        state.pattern_editor = Some(editor(Some("Mine"), "Mine", "{title} {year}"));
+        assert!(state.validate_pattern_editor().is_ok());
+
+        state.pattern_editor = Some(editor(None, "New", "{title} {bogus}"));
+        assert!(state
+            .validate_pattern_editor()
+            .unwrap_err()
+            .contains("{bogus}"));
+
+        let mut german = editor(None, "German", "{title}");
+        german.language = "de-de".to_string();
+        state.pattern_editor = Some(german);
+        assert_eq!(
+            state.validate_pattern_editor().unwrap().language.as_deref(),
+            Some("de-DE")
+        );
+        state.pattern_editor.as_mut().unwrap().language = "Deutsch".to_string();
+        assert!(state.validate_pattern_editor().is_err());
+    }
+
+    #[test]
+    fn test_effective_language_prefers_pattern() {
+        let mut state = AppState::new();
+        assert_eq!(state.effective_language(), None);
+
+        state.metadata_language = "ja-JP".to_string();
+        assert_eq!(state.effective_language().as_deref(), Some("ja-JP"));
+
+        state.rename_pattern.language = Some("de".to_string());
+        assert_eq!(state.effective_language().as_deref(), Some("de"));
+        assert_eq!(state.provider_config().language.as_deref(), Some("de"));
+    }
+
     // ==================== APP STATE TESTS ====================
 
     #[test]
@@ -737,4 +1106,48 @@ mod tests {
 
         assert_eq!(state.effective_api_key(), "custom_key_12345");
     }
+
+    #[test]
+    fn test_missing_thumbnails() {
+        let result = |poster: Option<&str>| SearchResult {
+            provider: ProviderKind::Tmdb,
+            id: 1,
+            title: "Dune".to_string(),
+            year: None,
+            media_type: MediaType::Movie,
+            overview: None,
+            poster_path: poster.map(String::from),
+            vote_average: None,
+            popularity: None,
+            confidence: None,
+        };
+        let mut state = AppState::new();
+        state.search_results = vec![
+            result(Some("/1984.jpg")),
+            result(None),
+            result(Some("/2021.jpg")),
+            result(Some("/1984.jpg")),
+        ];
+        let mut file = MediaFile::new(PathBuf::from("/test/Dune.2021.mkv"));
+        file.matched_metadata = Some(MediaMetadata {
+            poster_path: Some("/2021.jpg".to_string()),
+            ..Default::default()
+        });
+        state.files.push(file);
+        state.selected_file_index = Some(0);
+
+        assert_eq!(state.missing_thumbnails(), vec!["/1984.jpg", "/2021.jpg"]);
+
+        state
+            .thumbnails
+            .insert("/1984.jpg".to_string(), Thumbnail::Loading);
+        assert_eq!(state.missing_thumbnails(), vec!["/2021.jpg"]);
+
+        state
+            .thumbnails
+            .insert("/2021.jpg".to_string(), Thumbnail::Failed);
+        assert!(state.missing_thumbnails().is_empty());
+        state.retry_failed_thumbnails();
+        assert_eq!(state.missing_thumbnails(), vec!["/2021.jpg"]);
+    }
 }
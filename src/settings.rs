@@ -1,5 +1,13 @@
+use crate::model::RenamePattern;
+use crate::utils::artwork::ArtworkSettings;
+use crate::utils::file_ops::FileOperation;
+use crate::utils::metadata::ProviderSettings;
+use crate::utils::nfo::NfoPolicy;
+use crate::utils::tmdb_cache::CacheTtl;
+use crate::utils::tvdb::TvdbSettings;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -9,11 +17,43 @@ pub struct AppSettings {
     pub last_input_directory: Option<PathBuf>,
     pub last_output_directory: Option<PathBuf>,
     pub selected_pattern: Option<String>,
+    #[serde(default)]
+    pub all_or_nothing_rename: bool,
+    #[serde(default)]
+    pub file_operation: FileOperation,
+    #[serde(default)]
+    pub nfo_policy: NfoPolicy,
+    #[serde(default)]
+    pub artwork: ArtworkSettings,
+    #[serde(default)]
+    pub custom_patterns: Vec<RenamePattern>,
+    #[serde(default)]
+    pub offline_mode: bool,
+    #[serde(default)]
+    pub cache_ttl: CacheTtl,
+    #[serde(default)]
+    pub providers: ProviderSettings,
+    #[serde(default)]
+    pub tvdb: TvdbSettings,
+    #[serde(default)]
+    pub episode_orders: HashMap<u64, String>, // TMDB episode group ID by show ID
+    #[serde(default)]
+    pub metadata_language: String, // e.g. "de-DE"; empty = TMDB's default
 }
 
 impl AppSettings {
+    /// Directory holding settings.json and other persistent app files
+    pub fn config_dir() -> Option<PathBuf> {
+        ProjectDirs::from("com", "reel", "Reel").map(|dirs| dirs.config_dir().to_path_buf())
+    }
+
+    /// Directory for larger data such as the TMDB cache
+    pub fn data_dir() -> Option<PathBuf> {
+        ProjectDirs::from("com", "reel", "Reel").map(|dirs| dirs.data_dir().to_path_buf())
+    }
+
     fn config_path() -> Option<PathBuf> {
-        ProjectDirs::from("com", "reel", "Reel").map(|dirs| dirs.config_dir().join("settings.json"))
+        Self::config_dir().map(|dir| dir.join("settings.json"))
     }
 
     pub fn load() -> Self {
@@ -631,3 +631,22 @@ impl iced::widget::container::StyleSheet for MediaTypeBadgeStyle {
         }
     }
 }
+
+// ============== Progress Bar Style ==============
+
+pub struct ProgressBarStyle {
+    pub mode: ThemeMode,
+}
+
+impl iced::widget::progress_bar::StyleSheet for ProgressBarStyle {
+    type Style = iced::Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> iced::widget::progress_bar::Appearance {
+        let colors = get_colors(self.mode);
+        iced::widget::progress_bar::Appearance {
+            background: iced::Background::Color(colors.surface),
+            bar: iced::Background::Color(colors.accent_primary),
+            border_radius: 3.0.into(),
+        }
+    }
+}
@@ -0,0 +1,446 @@
+//! Artwork downloads
+//!
+//! Saves posters, fanart, season posters and episode thumbnails next to
+//! renamed videos, named the way Kodi, Plex and Jellyfin look for them.
+
+use crate::model::ProviderKind;
+use crate::utils::nfo::NfoTarget;
+use crate::utils::tmdb::{ImageConfig, ImageKind, ShowArtwork, TmdbClient};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether to download artwork and in which TMDB sizes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtworkSettings {
+    pub enabled: bool,
+    pub poster_size: String, // TMDB size name, e.g. "w780" or "original"
+    pub fanart_size: String,
+    pub thumb_size: String,
+}
+
+impl Default for ArtworkSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poster_size: "w780".to_string(),
+            fanart_size: "w1280".to_string(),
+            thumb_size: "w300".to_string(),
+        }
+    }
+}
+
+impl ArtworkSettings {
+    fn size(&self, kind: ImageKind) -> &str {
+        match kind {
+            ImageKind::Poster => &self.poster_size,
+            ImageKind::Backdrop => &self.fanart_size,
+            ImageKind::Still => &self.thumb_size,
+        }
+    }
+}
+
+/// An image to save
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtworkFile {
+    pub path: PathBuf,
+    pub kind: ImageKind,
+    pub source: String, // TMDB image path, or a full URL from TheTVDB
+}
+
+/// Outcome of downloading the artwork of a rename batch
+#[derive(Debug, Clone, Default)]
+pub struct ArtworkSummary {
+    pub downloaded: usize,
+    pub skipped: usize, // Already there
+    pub errors: Vec<String>,
+}
+
+/// The images for a renamed video. A movie in its own folder gets `poster.jpg`
+/// and `fanart.jpg`, otherwise `<video name>-poster.jpg` and `-fanart.jpg`. An
+/// episode gets `<video name>-thumb.jpg`; when the pattern gives the show its
+/// own folder, that folder gets `poster.jpg`, `fanart.jpg` and
+/// `season01-poster.jpg`. `show` is the show's TMDB artwork, if known.
+pub fn artwork_files(target: &NfoTarget, show: Option<&ShowArtwork>) -> Vec<ArtworkFile> {
+    let metadata = &target.metadata;
+    let path = &target.video_path;
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut files = Vec::new();
+    let mut add = |path: PathBuf, kind, source: Option<&String>| {
+        if let Some(source) = source.filter(|s| !s.is_empty()) {
+            files.push(ArtworkFile {
+                path: path.with_extension(image_extension(source)),
+                kind,
+                source: source.clone(),
+            });
+        }
+    };
+
+    if metadata.show_name.is_none() {
+        let (poster, fanart) = match target.movie_dir() {
+            Some(movie_dir) => (movie_dir.join("poster.jpg"), movie_dir.join("fanart.jpg")),
+            None => (
+                dir.join(format!("{}-poster.jpg", stem)),
+                dir.join(format!("{}-fanart.jpg", stem)),
+            ),
+        };
+        add(poster, ImageKind::Poster, metadata.poster_path.as_ref());
+        add(fanart, ImageKind::Backdrop, metadata.backdrop_path.as_ref());
+        return files;
+    }
+
+    if let Some(show_dir) = target.show_dir() {
+        let poster = show
+            .and_then(|s| s.poster_path.as_ref())
+            .or(metadata.poster_path.as_ref());
+        let fanart = show
+            .and_then(|s| s.backdrop_path.as_ref())
+            .or(metadata.backdrop_path.as_ref());
+        add(show_dir.join("poster.jpg"), ImageKind::Poster, poster);
+        add(show_dir.join("fanart.jpg"), ImageKind::Backdrop, fanart);
+
+        if let Some(season) = metadata.season_number {
+            let name = match season {
+                0 => "season-specials-poster.jpg".to_string(),
+                n => format!("season{:02}-poster.jpg", n),
+            };
+            let season_poster = show.and_then(|s| s.season_posters.get(&season));
+            add(show_dir.join(name), ImageKind::Poster, season_poster);
+        }
+    }
+
+    if metadata.episode_number.is_some() {
+        add(
+            dir.join(format!("{}-thumb.jpg", stem)),
+            ImageKind::Still,
+            metadata.still_path.as_ref(),
+        );
+    }
+    files
+}
+
+/// Saved images keep PNGs as PNGs; everything else is a JPEG
+fn image_extension(source: &str) -> &'static str {
+    if source.to_lowercase().ends_with(".png") {
+        "png"
+    } else {
+        "jpg"
+    }
+}
+
+/// Download the artwork of a rename batch, skipping images that already
+/// exist. Downloads go through the client's disk cache, so renaming the same
+/// files again doesn't fetch their images twice.
+pub async fn download_artwork(
+    targets: Vec<NfoTarget>,
+    settings: ArtworkSettings,
+    client: TmdbClient,
+) -> ArtworkSummary {
+    let mut summary = ArtworkSummary::default();
+
+    // Show posters, fanart and season posters come from the show's details
+    let mut shows: HashMap<u64, Option<ShowArtwork>> = HashMap::new();
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    for target in &targets {
+        let metadata = &target.metadata;
+        let show_id = metadata.tmdb_id;
+        let show = if metadata.show_name.is_some()
+            && metadata.provider == ProviderKind::Tmdb
+            && show_id != 0
+        {
+            if let Entry::Vacant(entry) = shows.entry(show_id) {
+                let artwork = match client.get_show_artwork(show_id).await {
+                    Ok(artwork) => Some(artwork),
+                    Err(e) => {
+                        summary.errors.push(e);
+                        None
+                    }
+                };
+                entry.insert(artwork);
+            }
+            shows[&show_id].as_ref()
+        } else {
+            None
+        };
+        for file in artwork_files(target, show) {
+            if seen.insert(file.path.clone()) {
+                files.push(file);
+            }
+        }
+    }
+
+    let mut config: Option<Result<ImageConfig, String>> = None;
+    for file in files {
+        if file.path.exists() {
+            summary.skipped += 1;
+            continue;
+        }
+
+        let url = if file.source.starts_with("http") {
+            file.source.clone()
+        } else {
+            if config.is_none() {
+                config = Some(client.get_image_config().await);
+            }
+            match &config {
+                Some(Ok(config)) => config.url(file.kind, settings.size(file.kind), &file.source),
+                Some(Err(e)) => {
+                    if !summary.errors.contains(e) {
+                        summary.errors.push(e.clone());
+                    }
+                    continue;
+                }
+                None => continue,
+            }
+        };
+
+        let result = match client.download_image(&url).await {
+            Ok(bytes) => save_image(&file.path, &bytes),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => summary.downloaded += 1,
+            Err(e) => summary
+                .errors
+                .push(format!("{}: {}", file.path.display(), e)),
+        }
+    }
+    summary
+}
+
+fn save_image(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    fs::write(path, bytes).map_err(|e| format!("Failed to write image: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MediaMetadata;
+    use crate::utils::test_dir::temp_dir;
+    use crate::utils::test_server::{route, serve};
+    use crate::utils::tmdb_cache::{CacheTtl, TmdbCache};
+
+    fn movie() -> MediaMetadata {
+        MediaMetadata {
+            tmdb_id: 603,
+            provider_id: 603,
+            title: "The Matrix".to_string(),
+            poster_path: Some("/poster.jpg".to_string()),
+            backdrop_path: Some("/backdrop.jpg".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn episode() -> MediaMetadata {
+        MediaMetadata {
+            tmdb_id: 1396,
+            provider_id: 1396,
+            title: "Breaking Bad".to_string(),
+            show_name: Some("Breaking Bad".to_string()),
+            season_number: Some(1),
+            episode_number: Some(1),
+            poster_path: Some("/show-poster.jpg".to_string()),
+            still_path: Some("/still.jpg".to_string()),
+            backdrop_path: Some("/show-backdrop.jpg".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn target(path: &Path, title_dir: Option<&Path>, metadata: MediaMetadata) -> NfoTarget {
+        NfoTarget {
+            video_path: path.to_path_buf(),
+            title_dir: title_dir.map(Path::to_path_buf),
+            metadata,
+        }
+    }
+
+    fn names(files: &[ArtworkFile]) -> Vec<(String, String)> {
+        files
+            .iter()
+            .map(|f| (f.path.to_string_lossy().to_string(), f.source.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_movie_artwork_names() {
+        let in_folder = target(
+            Path::new("/lib/Matrix (1999)/Matrix (1999).mkv"),
+            Some(Path::new("/lib/Matrix (1999)")),
+            movie(),
+        );
+        assert_eq!(
+            names(&artwork_files(&in_folder, None)),
+            vec![
+                ("/lib/Matrix (1999)/poster.jpg".into(), "/poster.jpg".into()),
+                (
+                    "/lib/Matrix (1999)/fanart.jpg".into(),
+                    "/backdrop.jpg".into()
+                ),
+            ]
+        );
+
+        let flat = target(Path::new("/lib/Matrix (1999).mkv"), None, movie());
+        assert_eq!(
+            names(&artwork_files(&flat, None)),
+            vec![
+                ("/lib/Matrix (1999)-poster.jpg".into(), "/poster.jpg".into()),
+                (
+                    "/lib/Matrix (1999)-fanart.jpg".into(),
+                    "/backdrop.jpg".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_episode_artwork_names() {
+        let show = ShowArtwork {
+            poster_path: Some("/show.jpg".to_string()),
+            backdrop_path: None,
+            season_posters: HashMap::from([(1, "/season1.png".to_string())]),
+        };
+        let path = Path::new("/tv/Breaking Bad/Season 01/Breaking Bad - S01E01.mkv");
+        let show_dir = Some(Path::new("/tv/Breaking Bad"));
+        assert_eq!(
+            names(&artwork_files(
+                &target(path, show_dir, episode()),
+                Some(&show)
+            )),
+            vec![
+                ("/tv/Breaking Bad/poster.jpg".into(), "/show.jpg".into()),
+                (
+                    "/tv/Breaking Bad/fanart.jpg".into(),
+                    "/show-backdrop.jpg".into()
+                ),
+                (
+                    "/tv/Breaking Bad/season01-poster.png".into(),
+                    "/season1.png".into()
+                ),
+                (
+                    "/tv/Breaking Bad/Season 01/Breaking Bad - S01E01-thumb.jpg".into(),
+                    "/still.jpg".into()
+                ),
+            ]
+        );
+
+        // Without the show's artwork, its poster comes from the episode's metadata
+        let flat = Path::new("/tv/Breaking Bad - S01E01.mkv");
+        assert_eq!(
+            names(&artwork_files(&target(flat, None, episode()), None)),
+            vec![(
+                "/tv/Breaking Bad - S01E01-thumb.jpg".into(),
+                "/still.jpg".into()
+            )]
+        );
+        let files = artwork_files(&target(path, show_dir, episode()), None);
+        assert_eq!(files[0].path, Path::new("/tv/Breaking Bad/poster.jpg"));
+        assert_eq!(files[0].source, "/show-poster.jpg");
+
+        // TheTVDB images are full URLs
+        let mut tvdb = episode();
+        tvdb.provider = ProviderKind::Tvdb;
+        tvdb.still_path = Some("https://artworks.thetvdb.com/e.jpg".to_string());
+        let files = artwork_files(&target(flat, None, tvdb), None);
+        assert_eq!(files[0].source, "https://artworks.thetvdb.com/e.jpg");
+    }
+
+    #[test]
+    fn test_movies_sharing_a_folder_get_named_artwork() {
+        let movies = Path::new("/lib/Movies");
+        let matrix = target(&movies.join("Matrix (1999).mkv"), None, movie());
+        let heat = target(&movies.join("Heat (1995).mkv"), None, movie());
+        let paths: Vec<PathBuf> = [matrix, heat]
+            .iter()
+            .flat_map(|t| artwork_files(t, None))
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                movies.join("Matrix (1999)-poster.jpg"),
+                movies.join("Matrix (1999)-fanart.jpg"),
+                movies.join("Heat (1995)-poster.jpg"),
+                movies.join("Heat (1995)-fanart.jpg"),
+            ]
+        );
+
+        // An episode in a shared "TV" folder doesn't put the show's art there
+        let episode = target(
+            Path::new("/lib/TV/Breaking Bad - S01E01.mkv"),
+            None,
+            episode(),
+        );
+        let files = artwork_files(&episode, None);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].kind, ImageKind::Still);
+    }
+
+    #[tokio::test]
+    async fn test_download_artwork_uses_sizes_cache_and_skips_existing() {
+        let (images, image_log) = serve(vec![
+            route("GET /t/p/w500/poster.jpg", 200, "POSTER"),
+            route("GET /t/p/original/backdrop.jpg", 200, "FANART"),
+        ]);
+        let config = format!(
+            r#"{{"images":{{"secure_base_url":"{}/t/p/","poster_sizes":["w92","w500","original"],"backdrop_sizes":["w300","original"],"still_sizes":["w300"]}}}}"#,
+            images
+        );
+        let (api, _) = serve(vec![route("GET /configuration", 200, &config)]);
+
+        let dir = temp_dir("artwork", "download");
+        let cache = TmdbCache::new(dir.join("cache"), CacheTtl::default(), false);
+        let client = || {
+            TmdbClient::new("key".to_string())
+                .with_base_url(api.clone())
+                .with_cache(Some(cache.clone()))
+        };
+        let settings = ArtworkSettings {
+            enabled: true,
+            poster_size: "w500".to_string(),
+            // Not offered for backdrops, so the original is used
+            fanart_size: "w1280".to_string(),
+            ..Default::default()
+        };
+        let library = dir.join("library");
+        let targets = vec![target(
+            &library.join("Matrix (1999)/Matrix (1999).mkv"),
+            Some(&library.join("Matrix (1999)")),
+            movie(),
+        )];
+
+        let summary = download_artwork(targets.clone(), settings.clone(), client()).await;
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+        assert_eq!((summary.downloaded, summary.skipped), (2, 0));
+        let poster = library.join("Matrix (1999)/poster.jpg");
+        assert_eq!(fs::read_to_string(&poster).unwrap(), "POSTER");
+        assert_eq!(
+            fs::read_to_string(library.join("Matrix (1999)/fanart.jpg")).unwrap(),
+            "FANART"
+        );
+        assert_eq!(image_log.lock().unwrap().len(), 2);
+
+        // Existing images are left alone
+        fs::write(&poster, "MINE").unwrap();
+        fs::remove_file(library.join("Matrix (1999)/fanart.jpg")).unwrap();
+        let summary = download_artwork(targets, settings, client()).await;
+        assert_eq!((summary.downloaded, summary.skipped), (1, 1));
+        assert_eq!(fs::read_to_string(&poster).unwrap(), "MINE");
+        // The fanart came from the disk cache
+        assert_eq!(image_log.lock().unwrap().len(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
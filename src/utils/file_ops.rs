@@ -0,0 +1,432 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, FileTimes};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Buffer size used when streaming a file copy
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// How a file is placed at its new path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileOperation {
+    /// Move the file (the original path disappears)
+    #[default]
+    Move,
+    /// Full copy of the file
+    Copy,
+    /// Hard link sharing the same data (same filesystem only)
+    Hardlink,
+    /// Symbolic link pointing at the original file
+    Symlink,
+    /// Copy-on-write clone (Btrfs, XFS, APFS, ...)
+    Reflink,
+}
+
+impl FileOperation {
+    pub const ALL: [FileOperation; 5] = [
+        FileOperation::Move,
+        FileOperation::Copy,
+        FileOperation::Hardlink,
+        FileOperation::Symlink,
+        FileOperation::Reflink,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileOperation::Move => "Move",
+            FileOperation::Copy => "Copy",
+            FileOperation::Hardlink => "Hardlink",
+            FileOperation::Symlink => "Symlink",
+            FileOperation::Reflink => "Reflink",
+        }
+    }
+
+    /// Lowercase past tense used in status messages, e.g. "renamed 3 file(s)"
+    pub fn past_tense(&self) -> &'static str {
+        match self {
+            FileOperation::Move => "renamed",
+            FileOperation::Copy => "copied",
+            FileOperation::Hardlink => "hardlinked",
+            FileOperation::Symlink => "symlinked",
+            FileOperation::Reflink => "reflinked",
+        }
+    }
+
+    /// Parse a name as accepted on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|op| op.name().eq_ignore_ascii_case(name))
+    }
+
+    /// True if the original file stays where it is
+    pub fn keeps_source(&self) -> bool {
+        *self != FileOperation::Move
+    }
+}
+
+impl std::fmt::Display for FileOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Place `src` at `dst` using the given operation
+pub fn apply(
+    operation: FileOperation,
+    src: &Path,
+    dst: &Path,
+    on_progress: &mut dyn FnMut(u64, u64),
+) -> Result<(), String> {
+    match operation {
+        FileOperation::Move => move_file(src, dst, on_progress),
+        FileOperation::Copy => copy_verified(src, dst, on_progress),
+        FileOperation::Hardlink => fs::hard_link(src, dst)
+            .map_err(|e| format!("Failed to hardlink {}: {}", src.display(), e)),
+        FileOperation::Symlink => symlink(src, dst),
+        FileOperation::Reflink => reflink(src, dst),
+    }
+}
+
+/// Create a symbolic link at `dst` pointing at the absolute path of `src`
+fn symlink(src: &Path, dst: &Path) -> Result<(), String> {
+    // Absolute target so the link survives being placed in another directory
+    let target =
+        fs::canonicalize(src).map_err(|e| format!("Failed to read {}: {}", src.display(), e))?;
+
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(&target, dst);
+    #[cfg(windows)]
+    let result = std::os::windows::fs::symlink_file(&target, dst);
+    #[cfg(not(any(unix, windows)))]
+    let result: io::Result<()> = Err(io::Error::from(io::ErrorKind::Unsupported));
+
+    result.map_err(|e| format!("Failed to symlink {}: {}", src.display(), e))
+}
+
+/// Clone a file's data with the filesystem's copy-on-write support
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dst: &Path) -> Result<(), String> {
+    use std::os::unix::io::AsRawFd;
+
+    let source = File::open(src).map_err(|e| format!("Failed to open {}: {}", src.display(), e))?;
+    let target = File::options()
+        .write(true)
+        .create_new(true)
+        .open(dst)
+        .map_err(|e| format!("Failed to create {}: {}", dst.display(), e))?;
+
+    // SAFETY: both descriptors are valid open files for the duration of the call
+    let result = unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+    if result != 0 {
+        let error = io::Error::last_os_error();
+        drop(target);
+        let _ = fs::remove_file(dst);
+        return Err(format!(
+            "Failed to reflink {} (the filesystem may not support reflinks): {}",
+            src.display(),
+            error
+        ));
+    }
+    Ok(())
+}
+
+/// Clone a file's data with the filesystem's copy-on-write support
+#[cfg(target_os = "macos")]
+fn reflink(src: &Path, dst: &Path) -> Result<(), String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let to_cstring = |path: &Path| {
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| format!("Invalid path: {}", path.display()))
+    };
+    let source = to_cstring(src)?;
+    let target = to_cstring(dst)?;
+
+    // SAFETY: both pointers are valid NUL-terminated strings for the duration of the call
+    if unsafe { libc::clonefile(source.as_ptr(), target.as_ptr(), 0) } != 0 {
+        return Err(format!(
+            "Failed to reflink {} (the filesystem may not support reflinks): {}",
+            src.display(),
+            io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+/// Clone a file's data with the filesystem's copy-on-write support
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(src: &Path, _dst: &Path) -> Result<(), String> {
+    Err(format!(
+        "Failed to reflink {}: reflinks are not supported on this platform",
+        src.display()
+    ))
+}
+
+/// Move a file, falling back to copy-verify-delete when the destination is on
+/// another filesystem. `on_progress` receives (bytes_copied, total_bytes) while copying.
+pub fn move_file(
+    src: &Path,
+    dst: &Path,
+    on_progress: &mut dyn FnMut(u64, u64),
+) -> Result<(), String> {
+    match fs::rename(src, dst) {
+        Ok(()) => Ok(()),
+        Err(e) if is_cross_device(&e) => {
+            copy_verified(src, dst, on_progress)?;
+            if let Err(e) = fs::remove_file(src) {
+                // Keep a single copy of the file: undo the copy rather than leave both
+                let _ = fs::remove_file(dst);
+                return Err(format!(
+                    "Failed to remove {} after copying: {}",
+                    src.display(),
+                    e
+                ));
+            }
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to rename {}: {}", src.display(), e)),
+    }
+}
+
+/// True if a rename failed because source and destination are on different filesystems
+pub fn is_cross_device(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::CrossesDevices
+}
+
+/// Copy a file, verify the copy's size and checksum, and keep the original timestamps.
+/// A partial or mismatched destination is removed on failure.
+pub fn copy_verified(
+    src: &Path,
+    dst: &Path,
+    on_progress: &mut dyn FnMut(u64, u64),
+) -> Result<(), String> {
+    let mut created = false;
+    let result = copy_and_verify(src, dst, on_progress, &mut created);
+    if result.is_err() && created {
+        let _ = fs::remove_file(dst);
+    }
+    result
+}
+
+fn copy_and_verify(
+    src: &Path,
+    dst: &Path,
+    on_progress: &mut dyn FnMut(u64, u64),
+    created: &mut bool,
+) -> Result<(), String> {
+    let src_meta =
+        fs::metadata(src).map_err(|e| format!("Failed to read {}: {}", src.display(), e))?;
+    let total = src_meta.len();
+
+    let mut reader =
+        File::open(src).map_err(|e| format!("Failed to open {}: {}", src.display(), e))?;
+    // create_new: never overwrite an existing file
+    let mut writer = File::options()
+        .write(true)
+        .create_new(true)
+        .open(dst)
+        .map_err(|e| format!("Failed to create {}: {}", dst.display(), e))?;
+    *created = true;
+
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    let mut copied = 0u64;
+    on_progress(0, total);
+
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", src.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| format!("Failed to write {}: {}", dst.display(), e))?;
+        hasher.update(&buffer[..read]);
+        copied += read as u64;
+        on_progress(copied, total);
+    }
+
+    writer
+        .sync_all()
+        .map_err(|e| format!("Failed to flush {}: {}", dst.display(), e))?;
+
+    // Keep the original timestamps and permissions
+    let mut times = FileTimes::new();
+    if let Ok(modified) = src_meta.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = src_meta.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    writer
+        .set_times(times)
+        .map_err(|e| format!("Failed to set timestamps on {}: {}", dst.display(), e))?;
+    drop(writer);
+    let _ = fs::set_permissions(dst, src_meta.permissions());
+
+    // Verify the copy by re-reading it from disk
+    let dst_len = fs::metadata(dst)
+        .map_err(|e| format!("Failed to read {}: {}", dst.display(), e))?
+        .len();
+    if dst_len != total || copied != total {
+        return Err(format!(
+            "Size mismatch after copying {} ({} of {} bytes)",
+            src.display(),
+            dst_len,
+            total
+        ));
+    }
+    if checksum(dst)? != hasher.finalize() {
+        return Err(format!("Checksum mismatch after copying {}", src.display()));
+    }
+
+    Ok(())
+}
+
+/// CRC32 checksum of a file's contents
+fn checksum(path: &Path) -> Result<u32, String> {
+    let mut reader =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::temp_dir;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_copy_verified_copies_contents_and_mtime() {
+        let dir = temp_dir("file_ops", "copy");
+        let src = dir.join("source.mkv");
+        let dst = dir.join("copy.mkv");
+        let contents: Vec<u8> = (0..(COPY_BUFFER_SIZE * 2 + 17))
+            .map(|i| (i % 251) as u8)
+            .collect();
+        fs::write(&src, &contents).unwrap();
+
+        let old_time = SystemTime::now() - Duration::from_secs(86_400 * 30);
+        File::options()
+            .write(true)
+            .open(&src)
+            .unwrap()
+            .set_modified(old_time)
+            .unwrap();
+
+        let mut last_progress = (0, 0);
+        copy_verified(&src, &dst, &mut |copied, total| {
+            last_progress = (copied, total)
+        })
+        .unwrap();
+
+        assert_eq!(fs::read(&dst).unwrap(), contents);
+        assert_eq!(
+            last_progress,
+            (contents.len() as u64, contents.len() as u64)
+        );
+        let src_mtime = fs::metadata(&src).unwrap().modified().unwrap();
+        let dst_mtime = fs::metadata(&dst).unwrap().modified().unwrap();
+        assert_eq!(src_mtime, dst_mtime);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_copy_verified_refuses_existing_destination() {
+        let dir = temp_dir("file_ops", "existing");
+        let src = dir.join("source.mkv");
+        let dst = dir.join("taken.mkv");
+        fs::write(&src, b"new").unwrap();
+        fs::write(&dst, b"keep me").unwrap();
+
+        assert!(copy_verified(&src, &dst, &mut |_, _| {}).is_err());
+        // The pre-existing file must not be removed by the cleanup
+        assert_eq!(fs::read(&dst).unwrap(), b"keep me");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_move_file_same_filesystem() {
+        let dir = temp_dir("file_ops", "move");
+        let src = dir.join("a.mkv");
+        let dst = dir.join("b.mkv");
+        fs::write(&src, b"video").unwrap();
+
+        move_file(&src, &dst, &mut |_, _| {}).unwrap();
+        assert!(!src.exists());
+        assert_eq!(fs::read(&dst).unwrap(), b"video");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_copy_and_links_keep_source() {
+        let dir = temp_dir("file_ops", "apply");
+        let src = dir.join("movie.mkv");
+        fs::write(&src, b"video").unwrap();
+
+        for operation in [
+            FileOperation::Copy,
+            FileOperation::Hardlink,
+            FileOperation::Symlink,
+        ] {
+            let dst = dir.join(format!("{}.mkv", operation.name()));
+            apply(operation, &src, &dst, &mut |_, _| {}).unwrap();
+            assert!(src.exists(), "{} removed the source", operation);
+            assert_eq!(fs::read(&dst).unwrap(), b"video");
+        }
+
+        assert!(fs::symlink_metadata(dir.join("Symlink.mkv"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reflink_failure_leaves_no_file() {
+        let dir = temp_dir("file_ops", "reflink");
+        let src = dir.join("movie.mkv");
+        let dst = dir.join("clone.mkv");
+        fs::write(&src, b"video").unwrap();
+
+        // Succeeds only on copy-on-write filesystems; either way no partial file is left
+        match apply(FileOperation::Reflink, &src, &dst, &mut |_, _| {}) {
+            Ok(()) => assert_eq!(fs::read(&dst).unwrap(), b"video"),
+            Err(_) => assert!(!dst.exists()),
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_file_operation_from_name() {
+        assert_eq!(
+            FileOperation::from_name("hardlink"),
+            Some(FileOperation::Hardlink)
+        );
+        assert_eq!(FileOperation::from_name("MOVE"), Some(FileOperation::Move));
+        assert_eq!(FileOperation::from_name("teleport"), None);
+    }
+
+    #[test]
+    fn test_is_cross_device() {
+        #[cfg(unix)]
+        assert!(is_cross_device(&io::Error::from_raw_os_error(libc::EXDEV)));
+        assert!(!is_cross_device(&io::Error::from(io::ErrorKind::NotFound)));
+    }
+}
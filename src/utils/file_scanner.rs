@@ -1,8 +1,9 @@
 use crate::model::{is_video_file, MediaFile};
+use crate::utils::sidecar;
 use std::path::PathBuf;
 use walkdir::WalkDir;
 
-/// Scan a directory recursively for video files
+/// Scan a directory recursively for video files and their sidecars
 pub async fn scan_directory(path: PathBuf) -> Result<Vec<MediaFile>, String> {
     tokio::task::spawn_blocking(move || scan_directory_sync(&path))
         .await
@@ -29,6 +30,7 @@ fn scan_directory_sync(path: &PathBuf) -> Result<Vec<MediaFile>, String> {
 
     // Sort by filename
     files.sort_by(|a, b| a.filename.to_lowercase().cmp(&b.filename.to_lowercase()));
+    sidecar::attach_sidecars(&mut files);
 
     Ok(files)
 }
@@ -49,6 +51,7 @@ pub async fn scan_files(paths: Vec<PathBuf>) -> Result<Vec<MediaFile>, String> {
 
     // Sort by filename
     files.sort_by(|a, b| a.filename.to_lowercase().cmp(&b.filename.to_lowercase()));
+    sidecar::attach_sidecars(&mut files);
 
     Ok(files)
 }
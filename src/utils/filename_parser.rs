@@ -11,27 +11,40 @@ pub fn parse_filename(filename: &str) -> (MediaType, ParsedMediaInfo) {
 
     // Replace common separators with spaces
     let cleaned = name.replace(['.', '_', '-'], " ");
+    // Same offsets as `cleaned`, but keeps the dash that marks an episode range
+    let dashed = name.replace(['.', '_'], " ");
 
     let mut info = ParsedMediaInfo::default();
 
     // Try to detect TV show patterns - ordered by specificity (most specific first)
 
+    // Fansub release "[Group] Show - 137 [1080p][ABCD1234]" (absolute numbering).
+    // Checked first so tags like "[1920x1080]" aren't read as 1x01
+    if parse_fansub(name, &mut info) {
+        extract_quality_info(&cleaned, &mut info);
+        return (MediaType::TvShow, info);
+    }
+
     // Pattern 1: S01E01, 1x01 - has both season and episode
     let season_episode_patterns = [
-        r"(?i)[Ss](\d{1,2})[Ee](\d{1,2})",              // S01E01
-        r"(?i)(\d{1,2})[xX](\d{1,2})",                  // 1x01
-        r"(?i)Season\s*(\d{1,2}).*Episode\s*(\d{1,2})", // Season 1 Episode 1
+        // S01E01, S01E01E02, S01E01-E03, S01E01-03
+        r"(?i)[Ss](\d{1,2})[Ee](\d{1,2})((?:\s?-?\s?[Ee]\d{1,2}|\s?-\s?\d{1,2}\b)*)",
+        r"(?i)(\d{1,2})[xX](\d{1,2})((?:\s?-\s?\d{1,2}[xX]\d{1,2})*)", // 1x01, 1x01-1x02
+        r"(?i)Season\s*(\d{1,2}).*Episode\s*(\d{1,2})",                // Season 1 Episode 1
     ];
 
     for pattern in &season_episode_patterns {
         if let Ok(re) = Regex::new(pattern) {
-            if let Some(caps) = re.captures(&cleaned) {
+            if let Some(caps) = re.captures(&dashed) {
                 if let (Some(season), Some(episode)) = (caps.get(1), caps.get(2)) {
                     info.season = season.as_str().parse().ok();
                     info.episode = episode.as_str().parse().ok();
+                    info.episode_end = caps
+                        .get(3)
+                        .and_then(|extra| parse_episode_end(extra.as_str(), info.episode));
 
                     // Extract title (everything before the season/episode)
-                    if let Some(m) = re.find(&cleaned) {
+                    if let Some(m) = caps.get(0) {
                         let title = cleaned[..m.start()].trim();
                         info.title = clean_title(title);
 
@@ -49,7 +62,67 @@ pub fn parse_filename(filename: &str) -> (MediaType, ParsedMediaInfo) {
         }
     }
 
-    // Pattern 2: Episode X only (assume season 1)
+    // Pattern 2: Air date for daily shows (Show.2024.03.15.Guest.Name)
+    if let Ok(re) = Regex::new(r"\b((?:19|20)\d{2}) (\d{2}) (\d{2})\b") {
+        if let Some(caps) = re.captures(&cleaned) {
+            let date = format!("{}-{}-{}", &caps[1], &caps[2], &caps[3]);
+            if chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_ok() {
+                info.air_date = Some(date);
+
+                if let Some(m) = caps.get(0) {
+                    info.title = clean_title(cleaned[..m.start()].trim());
+
+                    let after = cleaned[m.end()..].trim();
+                    if !after.is_empty() {
+                        let episode_title = extract_episode_title(after);
+                        if !episode_title.is_empty() {
+                            info.episode_title = Some(episode_title);
+                        }
+                    }
+                }
+
+                extract_quality_info(&cleaned, &mut info);
+                return (MediaType::TvShow, info);
+            }
+        }
+    }
+
+    // Pattern 3: Specials and OVAs (Show.S00.Christmas.2019, Show.Special.2, Show.OVA.2) -
+    // season 0. "Special" alone is too common in titles, so it needs a number next to it.
+    if let Ok(re) = Regex::new(r"(?i)\b(?:S00|(?:sp|specials?)\s?\d{1,3}|ova|oav|oad)\b") {
+        if let Some(m) = re.find(&cleaned) {
+            let title = clean_title(cleaned[..m.start()].trim());
+            if !title.is_empty() {
+                info.title = title;
+                info.season = Some(0);
+                info.special = true;
+
+                // "S00" only marks the season; other markers are part of the name
+                let marker_end = if m.as_str().eq_ignore_ascii_case("s00") {
+                    m.end()
+                } else {
+                    m.start()
+                };
+
+                // A trailing year helps pick between specials, it's not the show's year
+                let mut rest = cleaned[marker_end..].to_string();
+                if let Ok(year_re) = Regex::new(r"\b((?:19|20)\d{2})\b") {
+                    if let Some(caps) = year_re.captures(&rest) {
+                        info.year = caps[1].parse().ok();
+                        rest = year_re.replace(&rest, " ").to_string();
+                    }
+                }
+
+                // Keep the marker ("OVA 2") as the name to look up in season 0
+                info.episode_title = Some(extract_episode_title(&rest));
+
+                extract_quality_info(&cleaned, &mut info);
+                return (MediaType::TvShow, info);
+            }
+        }
+    }
+
+    // Pattern 4: Episode X only (assume season 1)
     let episode_only_patterns = [
         r"(?i)Episode\s*(\d{1,3})", // Episode 1, Episode 01
         r"(?i)\bEp\.?\s*(\d{1,3})", // Ep 1, Ep.1, Ep01
@@ -105,6 +178,56 @@ pub fn parse_filename(filename: &str) -> (MediaType, ParsedMediaInfo) {
     (MediaType::Unknown, info)
 }
 
+/// Parse "[Group] Show - 137" style names. A season in the title ("Show S2 - 05")
+/// makes the number relative to that season, otherwise it is an absolute number.
+fn parse_fansub(name: &str, info: &mut ParsedMediaInfo) -> bool {
+    let name = name.replace('_', " ");
+    let Ok(re) = Regex::new(r"^\[([^\]]+)\]\s*(.+?)\s+-\s+(\d{1,4})(?:v\d+)?(?:\s|$)") else {
+        return false;
+    };
+    let Some(caps) = re.captures(&name) else {
+        return false;
+    };
+    let (Some(group), Some(show), Some(number)) = (caps.get(1), caps.get(2), caps.get(3)) else {
+        return false;
+    };
+    let Ok(number) = number.as_str().parse::<u32>() else {
+        return false;
+    };
+
+    let show = show.as_str().replace('.', " ");
+    let season_caps = Regex::new(r"(?i)^(.+?)\s+(?:S|Season\s*)(\d{1,2})$")
+        .ok()
+        .and_then(|re| re.captures(show.trim()));
+    if let Some(season_caps) = season_caps {
+        info.title = clean_title(&season_caps[1]);
+        info.season = season_caps[2].parse().ok();
+        info.episode = Some(number);
+    } else {
+        info.title = clean_title(&show);
+        info.absolute_episode = Some(number);
+    }
+
+    // Anything after the number that isn't a tag is the episode title
+    let after = name[caps.get(0).map_or(name.len(), |m| m.end())..].trim_start_matches(['-', ' ']);
+    let episode_title = extract_episode_title(after);
+    if !episode_title.is_empty() {
+        info.episode_title = Some(episode_title);
+    }
+
+    info.group = Some(group.as_str().trim().to_string());
+    true
+}
+
+/// Last episode of a multi-episode marker such as "E02", "-03" or "-1x02" following the first episode
+fn parse_episode_end(extra: &str, first: Option<u32>) -> Option<u32> {
+    let last = extra
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse::<u32>().ok())
+        .next()?;
+    (last > first?).then_some(last)
+}
+
 /// Clean up a title string
 fn clean_title(title: &str) -> String {
     // Remove quality indicators, codec info, etc.
@@ -210,6 +333,13 @@ fn extract_quality_info(text: &str, info: &mut ParsedMediaInfo) {
         }
     }
 
+    // CRC32 checksum tag, e.g. "[ABCD1234]"
+    if let Ok(re) = Regex::new(r"\[([0-9A-Fa-f]{8})\]") {
+        if let Some(caps) = re.captures(text) {
+            info.crc = Some(caps[1].to_uppercase());
+        }
+    }
+
     // Release group (usually at the end after a dash)
     if let Ok(re) = Regex::new(r"-([A-Za-z0-9]+)$") {
         if let Some(caps) = re.captures(text.trim()) {
@@ -317,6 +447,50 @@ mod tests {
         assert_eq!(info.episode, Some(6));
     }
 
+    #[test]
+    fn test_parse_multi_episode_consecutive() {
+        let (media_type, info) =
+            parse_filename("Friends.S02E12E13.The.One.After.the.Superbowl.mkv");
+        assert_eq!(media_type, MediaType::TvShow);
+        assert_eq!(info.title, "Friends");
+        assert_eq!(info.season, Some(2));
+        assert_eq!(info.episode, Some(12));
+        assert_eq!(info.episode_end, Some(13));
+    }
+
+    #[test]
+    fn test_parse_multi_episode_range() {
+        let (_, info) = parse_filename("Show.Name.S01E01-E03.720p.mkv");
+        assert_eq!(info.title, "Show Name");
+        assert_eq!(info.episode, Some(1));
+        assert_eq!(info.episode_end, Some(3));
+    }
+
+    #[test]
+    fn test_parse_multi_episode_range_without_second_e() {
+        let (_, info) = parse_filename("Show.S01E01-03.mkv");
+        assert_eq!(info.title, "Show");
+        assert_eq!(info.episode, Some(1));
+        assert_eq!(info.episode_end, Some(3));
+        assert_eq!(info.episode_title, None);
+    }
+
+    #[test]
+    fn test_parse_multi_episode_range_nxnn() {
+        let (media_type, info) = parse_filename("Show.3x01-3x02.Title.mkv");
+        assert_eq!(media_type, MediaType::TvShow);
+        assert_eq!(info.season, Some(3));
+        assert_eq!(info.episode, Some(1));
+        assert_eq!(info.episode_end, Some(2));
+        assert_eq!(info.episode_title.as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn test_parse_single_episode_has_no_end() {
+        let (_, info) = parse_filename("Show.S01E05.mkv");
+        assert_eq!(info.episode_end, None);
+    }
+
     // ==================== MOVIE PARSING ====================
 
     #[test]
@@ -518,6 +692,165 @@ mod tests {
         assert!(info.group.is_none());
     }
 
+    // ==================== DAILY SHOWS ====================
+
+    #[test]
+    fn test_parse_daily_show_air_date() {
+        let (media_type, info) =
+            parse_filename("The.Daily.Show.2024.03.15.Guest.Name.720p.HDTV.x264.mkv");
+        assert_eq!(media_type, MediaType::TvShow);
+        assert_eq!(info.title, "The Daily Show");
+        assert_eq!(info.air_date, Some("2024-03-15".to_string()));
+        assert_eq!(info.year, None);
+        assert_eq!(info.season, None);
+        assert_eq!(info.episode_title, Some("Guest Name".to_string()));
+        assert_eq!(info.quality, Some("720p".to_string()));
+    }
+
+    #[test]
+    fn test_parse_daily_show_dashed_date() {
+        let (_, info) = parse_filename("Late Night - 2023-11-02.mp4");
+        assert_eq!(info.title, "Late Night");
+        assert_eq!(info.air_date, Some("2023-11-02".to_string()));
+    }
+
+    #[test]
+    fn test_parse_invalid_date_is_movie() {
+        // 2024 13 45 is not a real date, so this stays a movie with a year
+        let (media_type, info) = parse_filename("Movie.2024.13.45.1080p.mkv");
+        assert_eq!(media_type, MediaType::Movie);
+        assert_eq!(info.year, Some(2024));
+        assert!(info.air_date.is_none());
+    }
+
+    #[test]
+    fn test_parse_season_episode_wins_over_date() {
+        let (_, info) = parse_filename("Show.S01E02.2024.03.15.mkv");
+        assert_eq!(info.season, Some(1));
+        assert_eq!(info.episode, Some(2));
+        assert!(info.air_date.is_none());
+    }
+
+    // ==================== SPECIALS ====================
+
+    #[test]
+    fn test_parse_special_with_name_and_year() {
+        // Reads like a movie; matching retries it as a special when no movie fits
+        let (media_type, info) = parse_filename("Doctor.Who.Special.Christmas.2019.720p.mkv");
+        assert_eq!(media_type, MediaType::Movie);
+        assert_eq!(info.title, "Doctor Who Special Christmas");
+        assert!(!info.special);
+        assert_eq!(info.year, Some(2019));
+        assert_eq!(info.quality, Some("720p".to_string()));
+    }
+
+    #[test]
+    fn test_parse_s00_special_with_name_and_year() {
+        let (media_type, info) = parse_filename("Doctor.Who.S00.Special.Christmas.2019.720p.mkv");
+        assert_eq!(media_type, MediaType::TvShow);
+        assert_eq!(info.title, "Doctor Who");
+        assert!(info.special);
+        assert_eq!(info.season, Some(0));
+        assert_eq!(info.episode, None);
+        assert_eq!(info.year, Some(2019));
+        assert_eq!(info.episode_title, Some("Special Christmas".to_string()));
+        assert_eq!(info.quality, Some("720p".to_string()));
+    }
+
+    #[test]
+    fn test_parse_ova_number() {
+        let (media_type, info) = parse_filename("Hellsing.OVA.2.mkv");
+        assert_eq!(media_type, MediaType::TvShow);
+        assert_eq!(info.title, "Hellsing");
+        assert!(info.special);
+        assert_eq!(info.season, Some(0));
+        assert_eq!(info.episode_title, Some("OVA 2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_explicit_season_zero_is_not_flagged() {
+        let (_, info) = parse_filename("Show.S00E05.mkv");
+        assert_eq!(info.season, Some(0));
+        assert_eq!(info.episode, Some(5));
+        assert!(!info.special);
+    }
+
+    #[test]
+    fn test_parse_numbered_special() {
+        let (media_type, info) = parse_filename("Sherlock.Special.1.mkv");
+        assert_eq!(media_type, MediaType::TvShow);
+        assert_eq!(info.title, "Sherlock");
+        assert!(info.special);
+        assert_eq!(info.episode_title, Some("Special 1".to_string()));
+    }
+
+    #[test]
+    fn test_parse_special_word_in_movie_title_is_movie() {
+        let (media_type, info) = parse_filename("My.Special.Day.2019.mkv");
+        assert_eq!(media_type, MediaType::Movie);
+        assert_eq!(info.title, "My Special Day");
+        assert_eq!(info.year, Some(2019));
+        assert!(!info.special);
+    }
+
+    #[test]
+    fn test_parse_special_word_at_start_is_movie() {
+        let (media_type, info) = parse_filename("Special.Forces.2011.1080p.mkv");
+        assert_eq!(media_type, MediaType::Movie);
+        assert!(!info.special);
+    }
+
+    // ==================== ANIME / FANSUB ====================
+
+    #[test]
+    fn test_parse_fansub_absolute_episode() {
+        let (media_type, info) =
+            parse_filename("[SubsPlease] One Piece - 1071 [1080p][A1B2C3D4].mkv");
+        assert_eq!(media_type, MediaType::TvShow);
+        assert_eq!(info.title, "One Piece");
+        assert_eq!(info.absolute_episode, Some(1071));
+        assert_eq!(info.season, None);
+        assert_eq!(info.episode, None);
+        assert_eq!(info.group, Some("SubsPlease".to_string()));
+        assert_eq!(info.crc, Some("A1B2C3D4".to_string()));
+        assert_eq!(info.quality, Some("1080p".to_string()));
+        assert!(info.episode_title.is_none());
+    }
+
+    #[test]
+    fn test_parse_fansub_underscores_and_version() {
+        let (_, info) = parse_filename("[Erai-raws]_Shingeki_no_Kyojin_-_25v2_[1920x1080].mkv");
+        assert_eq!(info.title, "Shingeki no Kyojin");
+        assert_eq!(info.absolute_episode, Some(25));
+        assert_eq!(info.season, None);
+        assert_eq!(info.group, Some("Erai-raws".to_string()));
+        assert!(info.crc.is_none());
+    }
+
+    #[test]
+    fn test_parse_fansub_with_season_in_title() {
+        let (_, info) = parse_filename("[Group] Mushoku Tensei S2 - 05 [720p].mkv");
+        assert_eq!(info.title, "Mushoku Tensei");
+        assert_eq!(info.season, Some(2));
+        assert_eq!(info.episode, Some(5));
+        assert!(info.absolute_episode.is_none());
+    }
+
+    #[test]
+    fn test_parse_fansub_episode_title() {
+        let (_, info) = parse_filename("[Group] Show - 137 - The Final Battle [1080p].mkv");
+        assert_eq!(info.absolute_episode, Some(137));
+        assert_eq!(info.episode_title, Some("The Final Battle".to_string()));
+    }
+
+    #[test]
+    fn test_parse_bracket_tag_is_not_fansub_without_number() {
+        let (media_type, info) = parse_filename("[Group] Some Movie (2019) [1080p].mkv");
+        assert_eq!(media_type, MediaType::Movie);
+        assert!(info.absolute_episode.is_none());
+        assert_eq!(info.year, Some(2019));
+    }
+
     // ==================== EDGE CASES ====================
 
     #[test]
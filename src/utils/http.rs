@@ -0,0 +1,115 @@
+use crate::utils::rate_limit::RateLimiter;
+use crate::utils::tmdb_cache::TmdbCache;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::future::Future;
+use std::time::Duration;
+
+/// HTTP client and limiter for one API. Each client keeps the pair in a static
+/// so concurrent lookups reuse connections and share the API's rate limit.
+pub fn shared_http(capacity: u32, per_second: f64) -> (reqwest::Client, RateLimiter) {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .unwrap_or_default();
+    (client, RateLimiter::new(capacity, per_second))
+}
+
+/// Why a request failed
+#[derive(Debug)]
+pub enum RequestError {
+    /// 404 - the resource doesn't exist, e.g. an episode TMDB doesn't list
+    NotFound(String),
+    Failed(String),
+}
+
+impl From<RequestError> for String {
+    fn from(error: RequestError) -> Self {
+        match error {
+            RequestError::NotFound(message) | RequestError::Failed(message) => message,
+        }
+    }
+}
+
+/// Error body sent with non-2xx responses (`status_message` on TMDB,
+/// `message` on TheTVDB)
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(alias = "status_message")]
+    message: String,
+}
+
+/// Turn a failed response into a readable message, preferring the API's own
+pub fn api_error(api: &str, status: StatusCode, body: &str) -> String {
+    match serde_json::from_str::<ErrorBody>(body) {
+        Ok(error) => format!("{} error: {}", api, error.message),
+        Err(_) => format!("{} error: HTTP {}", api, status),
+    }
+}
+
+/// GET `url` with `send`, going through the cache when one is set. Only
+/// successful responses are cached; a stale entry is used if the network fails.
+pub async fn get_cached<F>(
+    api: &str,
+    cache: Option<&TmdbCache>,
+    url: &str,
+    send: F,
+) -> Result<String, RequestError>
+where
+    F: Future<Output = Result<(StatusCode, String), String>>,
+{
+    if let Some(cache) = cache {
+        if cache.is_offline() {
+            return cache.get_stale(url).ok_or_else(|| {
+                RequestError::Failed("Offline mode: no cached response".to_string())
+            });
+        }
+        if let Some(body) = cache.get(url) {
+            return Ok(body);
+        }
+    }
+
+    let (status, body) = match send.await {
+        Ok(response) => response,
+        Err(e) => {
+            let stale = cache.and_then(|c| c.get_stale(url));
+            return stale.ok_or(RequestError::Failed(e));
+        }
+    };
+
+    if status == StatusCode::NOT_FOUND {
+        return Err(RequestError::NotFound(api_error(api, status, &body)));
+    }
+    if !status.is_success() {
+        return Err(RequestError::Failed(api_error(api, status, &body)));
+    }
+
+    if let Some(cache) = cache {
+        // A failed write only costs a refetch next time
+        let _ = cache.put(url, &body);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_uses_the_apis_message() {
+        let tmdb = r#"{"status_code":7,"status_message":"Invalid API key: You must be granted a valid key.","success":false}"#;
+        assert_eq!(
+            api_error("TMDB", StatusCode::UNAUTHORIZED, tmdb),
+            "TMDB error: Invalid API key: You must be granted a valid key."
+        );
+        let tvdb = r#"{"status":"failure","message":"Unauthorized","data":null}"#;
+        assert_eq!(
+            api_error("TheTVDB", StatusCode::UNAUTHORIZED, tvdb),
+            "TheTVDB error: Unauthorized"
+        );
+        assert_eq!(
+            api_error("TMDB", StatusCode::BAD_GATEWAY, "<html>Bad gateway</html>"),
+            "TMDB error: HTTP 502 Bad Gateway"
+        );
+    }
+}
@@ -0,0 +1,481 @@
+use crate::settings::AppSettings;
+use crate::utils::file_ops::{self, FileOperation};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Maximum number of batches kept in the journal
+const MAX_BATCHES: usize = 100;
+
+/// A single completed rename, with enough information to detect later changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub old_path: PathBuf,
+    pub new_path: PathBuf,
+    pub size_bytes: u64,
+    pub modified_secs: Option<u64>,
+    #[serde(default)]
+    pub reverted: bool,
+}
+
+/// All renames performed by one "Rename" action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalBatch {
+    pub id: u64,
+    pub timestamp: String,
+    pub pattern_name: String,
+    /// Batches recorded before operations existed were always moves
+    #[serde(default)]
+    pub operation: FileOperation,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl JournalBatch {
+    /// True if at least one entry can still be reverted
+    pub fn is_active(&self) -> bool {
+        self.entries.iter().any(|e| !e.reverted)
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.entries.iter().filter(|e| !e.reverted).count()
+    }
+}
+
+/// Persistent history of rename batches, stored next to settings.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenameJournal {
+    pub batches: Vec<JournalBatch>,
+}
+
+/// Outcome of reverting a batch
+#[derive(Debug, Clone, Default)]
+pub struct RevertReport {
+    pub batch_id: u64,
+    /// Tells the batch apart from one a merge has since renumbered to its id
+    pub timestamp: String,
+    /// (current path, restored path) for every file moved back
+    pub reverted: Vec<(PathBuf, PathBuf)>,
+    /// Entries left alone, with the reason
+    pub refused: Vec<(PathBuf, String)>,
+}
+
+impl RenameJournal {
+    fn journal_path() -> Option<PathBuf> {
+        AppSettings::config_dir().map(|dir| dir.join("rename_journal.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::journal_path()
+            .map(|path| Self::load_from(&path))
+            .unwrap_or_default()
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        match Self::journal_path() {
+            Some(path) => self.save_to(&path),
+            None => Ok(()),
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize journal: {}", e))?;
+        fs::write(path, contents).map_err(|e| format!("Failed to write journal: {}", e))
+    }
+
+    /// Record a completed batch of (old_path, new_path) renames. Returns the batch id.
+    pub fn record_batch(
+        &mut self,
+        pattern_name: &str,
+        operation: FileOperation,
+        renamed: &[(PathBuf, PathBuf)],
+    ) -> u64 {
+        let id = self.batches.last().map_or(1, |b| b.id + 1);
+        let entries = renamed
+            .iter()
+            .map(|(old_path, new_path)| {
+                let (size_bytes, modified_secs) = file_fingerprint(new_path).unwrap_or((0, None));
+                JournalEntry {
+                    old_path: old_path.clone(),
+                    new_path: new_path.clone(),
+                    size_bytes,
+                    modified_secs,
+                    reverted: false,
+                }
+            })
+            .collect();
+
+        self.batches.push(JournalBatch {
+            id,
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            pattern_name: pattern_name.to_string(),
+            operation,
+            entries,
+        });
+
+        if self.batches.len() > MAX_BATCHES {
+            let excess = self.batches.len() - MAX_BATCHES;
+            self.batches.drain(..excess);
+        }
+
+        id
+    }
+
+    /// Pick up batches another process (e.g. the CLI) journaled since this
+    /// journal was loaded. Reverts recorded on either side are kept.
+    pub fn merge(&mut self, other: RenameJournal) {
+        for batch in other.batches {
+            let same = self
+                .batches
+                .iter_mut()
+                .find(|b| b.id == batch.id && b.timestamp == batch.timestamp);
+            match same {
+                Some(existing) => {
+                    for (entry, theirs) in existing.entries.iter_mut().zip(&batch.entries) {
+                        entry.reverted |= theirs.reverted;
+                    }
+                }
+                None => self.batches.push(batch),
+            }
+        }
+        self.batches
+            .sort_by(|a, b| (a.id, &a.timestamp).cmp(&(b.id, &b.timestamp)));
+
+        // Batches recorded separately under the same id get fresh ones
+        let mut last_id = 0;
+        for batch in &mut self.batches {
+            if batch.id <= last_id {
+                batch.id = last_id + 1;
+            }
+            last_id = batch.id;
+        }
+
+        if self.batches.len() > MAX_BATCHES {
+            let excess = self.batches.len() - MAX_BATCHES;
+            self.batches.drain(..excess);
+        }
+    }
+
+    pub fn batch(&self, id: u64) -> Option<&JournalBatch> {
+        self.batches.iter().find(|b| b.id == id)
+    }
+
+    /// Most recent batch that still has files to revert
+    pub fn last_active_batch(&self) -> Option<&JournalBatch> {
+        self.batches.iter().rev().find(|b| b.is_active())
+    }
+
+    /// Mark the reverted files of a report in the journal
+    pub fn apply_revert(&mut self, report: &RevertReport) {
+        let batch = self
+            .batches
+            .iter_mut()
+            .find(|b| b.id == report.batch_id && b.timestamp == report.timestamp);
+        if let Some(batch) = batch {
+            for entry in &mut batch.entries {
+                if report
+                    .reverted
+                    .iter()
+                    .any(|(current, _)| *current == entry.new_path)
+                {
+                    entry.reverted = true;
+                }
+            }
+        }
+    }
+}
+
+/// Size and modification time (seconds since epoch) of a file
+fn file_fingerprint(path: &Path) -> Option<(u64, Option<u64>)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    Some((metadata.len(), modified))
+}
+
+/// Check whether an entry can be safely moved back (or removed, for copies and links)
+fn check_revertible(entry: &JournalEntry, operation: FileOperation) -> Result<(), String> {
+    let Some((size, modified)) = file_fingerprint(&entry.new_path) else {
+        return Err("File no longer exists".to_string());
+    };
+    if size != entry.size_bytes
+        || (entry.modified_secs.is_some() && modified != entry.modified_secs)
+    {
+        return Err("File has changed since it was renamed".to_string());
+    }
+    if operation.keeps_source() {
+        // The copy or link is only removed while the original is still there
+        if !entry.old_path.exists() {
+            return Err("Original file no longer exists".to_string());
+        }
+        return Ok(());
+    }
+    if entry.old_path.exists() {
+        return Err("Original path is now occupied".to_string());
+    }
+    Ok(())
+}
+
+/// Move every still-valid file of a batch back to its original path.
+/// For copy and link batches the created files are removed instead.
+pub fn revert_batch(batch: &JournalBatch) -> RevertReport {
+    let mut report = RevertReport {
+        batch_id: batch.id,
+        timestamp: batch.timestamp.clone(),
+        ..Default::default()
+    };
+
+    // Undo in reverse order so chained renames unwind correctly
+    for entry in batch.entries.iter().rev().filter(|e| !e.reverted) {
+        if let Err(reason) = check_revertible(entry, batch.operation) {
+            report.refused.push((entry.new_path.clone(), reason));
+            continue;
+        }
+
+        if batch.operation.keeps_source() {
+            match fs::remove_file(&entry.new_path) {
+                Ok(()) => report
+                    .reverted
+                    .push((entry.new_path.clone(), entry.old_path.clone())),
+                Err(e) => report
+                    .refused
+                    .push((entry.new_path.clone(), format!("Failed to remove: {}", e))),
+            }
+            continue;
+        }
+
+        if let Some(parent) = entry.old_path.parent() {
+            if !parent.exists() {
+                if let Err(e) = fs::create_dir_all(parent) {
+                    report.refused.push((
+                        entry.new_path.clone(),
+                        format!("Failed to create directory: {}", e),
+                    ));
+                    continue;
+                }
+            }
+        }
+
+        match file_ops::move_file(&entry.new_path, &entry.old_path, &mut |_, _| {}) {
+            Ok(()) => report
+                .reverted
+                .push((entry.new_path.clone(), entry.old_path.clone())),
+            Err(e) => report.refused.push((
+                entry.new_path.clone(),
+                format!("Failed to move back: {}", e),
+            )),
+        }
+    }
+
+    report
+}
+
+/// Revert a batch on a blocking thread
+pub async fn revert_batch_async(batch: JournalBatch) -> Result<RevertReport, String> {
+    tokio::task::spawn_blocking(move || revert_batch(&batch))
+        .await
+        .map_err(|e| format!("Task error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::temp_dir;
+
+    fn rename(old: &Path, new: &Path) -> (PathBuf, PathBuf) {
+        fs::rename(old, new).unwrap();
+        (old.to_path_buf(), new.to_path_buf())
+    }
+
+    #[test]
+    fn test_record_batch_assigns_increasing_ids() {
+        let mut journal = RenameJournal::default();
+        let first = journal.record_batch("Default", FileOperation::Move, &[]);
+        let second = journal.record_batch("Plex", FileOperation::Move, &[]);
+        assert_eq!(first, 1);
+        assert_eq!(second, 2);
+        assert_eq!(journal.batch(2).unwrap().pattern_name, "Plex");
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = temp_dir("journal", "roundtrip");
+        let path = dir.join("journal.json");
+        let mut journal = RenameJournal::default();
+        journal.record_batch(
+            "Default",
+            FileOperation::Move,
+            &[(dir.join("a.mkv"), dir.join("A (2020).mkv"))],
+        );
+        journal.save_to(&path).unwrap();
+
+        let loaded = RenameJournal::load_from(&path);
+        assert_eq!(loaded.batches.len(), 1);
+        assert_eq!(loaded.batches[0].entries[0].old_path, dir.join("a.mkv"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_merge_keeps_batches_from_both_sides() {
+        let dir = temp_dir("journal", "merge");
+        let path = dir.join("rename_journal.json");
+        let mut gui = RenameJournal::default();
+        gui.record_batch(
+            "Default",
+            FileOperation::Move,
+            &[(dir.join("a"), dir.join("A"))],
+        );
+        gui.save_to(&path).unwrap();
+
+        // The CLI journals a batch while the GUI is open
+        let mut cli = RenameJournal::load_from(&path);
+        cli.record_batch(
+            "Default",
+            FileOperation::Copy,
+            &[(dir.join("b"), dir.join("B"))],
+        );
+        cli.batches[0].entries[0].reverted = true;
+        cli.save_to(&path).unwrap();
+
+        // Meanwhile the GUI recorded another batch under the same id
+        gui.record_batch(
+            "Default",
+            FileOperation::Move,
+            &[(dir.join("c"), dir.join("C"))],
+        );
+        gui.batches[1].timestamp = "9999-01-01 00:00:00".to_string();
+        gui.merge(RenameJournal::load_from(&path));
+
+        let ids: Vec<u64> = gui.batches.iter().map(|b| b.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert!(gui.batches[0].entries[0].reverted);
+        assert_eq!(gui.batches[1].operation, FileOperation::Copy);
+        assert_eq!(gui.batches[2].entries[0].old_path, dir.join("c"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_revert_batch_restores_files() {
+        let dir = temp_dir("journal", "revert");
+        let old = dir.join("the.matrix.1999.mkv");
+        fs::write(&old, b"video").unwrap();
+        let renamed = rename(&old, &dir.join("The Matrix (1999).mkv"));
+
+        let mut journal = RenameJournal::default();
+        let id = journal.record_batch("Default", FileOperation::Move, &[renamed]);
+
+        let report = revert_batch(journal.batch(id).unwrap());
+        assert_eq!(report.reverted.len(), 1);
+        assert!(report.refused.is_empty());
+        assert!(old.exists());
+
+        journal.apply_revert(&report);
+        assert!(journal.last_active_batch().is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_revert_ignores_batch_renumbered_to_its_id() {
+        let dir = temp_dir("journal", "apply_revert");
+        let mut journal = RenameJournal::default();
+        journal.record_batch(
+            "Default",
+            FileOperation::Move,
+            &[(dir.join("a"), dir.join("A"))],
+        );
+        journal.batches[0].timestamp = "2024-01-01 00:00:00".to_string();
+
+        // The report was made for another batch that had id 1 before a merge
+        let report = RevertReport {
+            batch_id: 1,
+            timestamp: "2023-12-31 23:59:59".to_string(),
+            reverted: vec![(dir.join("A"), dir.join("a"))],
+            ..Default::default()
+        };
+        journal.apply_revert(&report);
+        assert!(journal.batches[0].is_active());
+
+        let report = RevertReport {
+            timestamp: "2024-01-01 00:00:00".to_string(),
+            ..report
+        };
+        journal.apply_revert(&report);
+        assert!(!journal.batches[0].is_active());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_revert_copy_batch_removes_copies() {
+        let dir = temp_dir("journal", "revert_copy");
+        let old = dir.join("movie.mkv");
+        let new = dir.join("Movie (2020).mkv");
+        fs::write(&old, b"video").unwrap();
+        fs::copy(&old, &new).unwrap();
+
+        let mut journal = RenameJournal::default();
+        let id = journal.record_batch(
+            "Default",
+            FileOperation::Copy,
+            &[(old.clone(), new.clone())],
+        );
+
+        let report = revert_batch(journal.batch(id).unwrap());
+        assert_eq!(report.reverted.len(), 1);
+        assert!(old.exists());
+        assert!(!new.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_revert_refuses_changed_file() {
+        let dir = temp_dir("journal", "changed");
+        let old = dir.join("show.s01e01.mkv");
+        fs::write(&old, b"video").unwrap();
+        let renamed = rename(&old, &dir.join("Show - S01E01.mkv"));
+        let new_path = renamed.1.clone();
+
+        let mut journal = RenameJournal::default();
+        let id = journal.record_batch("Default", FileOperation::Move, &[renamed]);
+
+        fs::write(&new_path, b"different contents").unwrap();
+
+        let report = revert_batch(journal.batch(id).unwrap());
+        assert!(report.reverted.is_empty());
+        assert_eq!(report.refused.len(), 1);
+        assert!(new_path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_revert_refuses_missing_file() {
+        let dir = temp_dir("journal", "missing");
+        let old = dir.join("movie.mkv");
+        fs::write(&old, b"video").unwrap();
+        let renamed = rename(&old, &dir.join("Movie (2020).mkv"));
+        let new_path = renamed.1.clone();
+
+        let mut journal = RenameJournal::default();
+        let id = journal.record_batch("Default", FileOperation::Move, &[renamed]);
+        fs::remove_file(&new_path).unwrap();
+
+        let report = revert_batch(journal.batch(id).unwrap());
+        assert_eq!(report.refused.len(), 1);
+        assert!(journal.last_active_batch().is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1175 @@
+use crate::model::{MediaFile, MediaMetadata, MediaType, ProviderKind, SearchResult};
+use crate::utils::tmdb::TmdbClient;
+use crate::utils::tmdb_cache::TmdbCache;
+use crate::utils::tvdb::{TvdbClient, TvdbSettings};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A source of movie and TV show metadata
+#[async_trait]
+pub trait MetadataProvider: Send + Sync {
+    fn kind(&self) -> ProviderKind;
+
+    /// Search by title; `MediaType::Unknown` searches movies and shows together
+    async fn search(
+        &self,
+        query: &str,
+        media_type: MediaType,
+        year: Option<u32>,
+    ) -> Result<Vec<SearchResult>, String>;
+
+    async fn movie_details(&self, id: u64) -> Result<MediaMetadata, String>;
+
+    /// Show details along with its season layout
+    async fn show_details(&self, id: u64) -> Result<(MediaMetadata, SeasonLayout), String>;
+
+    /// Episode metadata for a file, given a show from `show_details`
+    async fn episode_details(
+        &self,
+        show: &MediaMetadata,
+        layout: &SeasonLayout,
+        file: &BatchFileInfo,
+    ) -> Result<MediaMetadata, String>;
+}
+
+/// Providers to use per media type, in order - later ones are fallbacks
+/// for when earlier ones find nothing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderSettings {
+    pub movies: Vec<ProviderKind>,
+    pub tv: Vec<ProviderKind>,
+}
+
+impl Default for ProviderSettings {
+    fn default() -> Self {
+        Self {
+            movies: vec![ProviderKind::Tmdb],
+            tv: vec![ProviderKind::Tmdb],
+        }
+    }
+}
+
+/// Credentials and settings needed to create providers
+#[derive(Debug, Clone, Default)]
+pub struct ProviderConfig {
+    pub tmdb_api_key: String,
+    pub tvdb: TvdbSettings,
+    pub settings: ProviderSettings,
+    pub cache: Option<TmdbCache>,
+    pub episode_orders: HashMap<u64, String>, // TMDB episode group ID by show ID
+    pub language: Option<String>,             // e.g. "de-DE"; None = provider default
+}
+
+impl ProviderConfig {
+    fn create(&self, kind: ProviderKind) -> Result<Arc<dyn MetadataProvider>, String> {
+        match kind {
+            ProviderKind::Tmdb => {
+                if self.tmdb_api_key.is_empty() {
+                    return Err("TMDB API key not set".to_string());
+                }
+                let client = TmdbClient::new(self.tmdb_api_key.clone())
+                    .with_cache(self.cache.clone())
+                    .with_episode_orders(self.episode_orders.clone())
+                    .with_language(self.language.clone());
+                Ok(Arc::new(client))
+            }
+            ProviderKind::Tvdb => {
+                if self.tvdb.api_key.is_empty() {
+                    return Err("TheTVDB API key not set".to_string());
+                }
+                let client = TvdbClient::from_settings(&self.tvdb).with_cache(self.cache.clone());
+                Ok(Arc::new(client))
+            }
+        }
+    }
+
+    /// Create the configured providers, skipping ones missing credentials
+    pub fn build(&self) -> Providers {
+        let mut created: HashMap<ProviderKind, Result<Arc<dyn MetadataProvider>, String>> =
+            HashMap::new();
+        let mut chain = |kinds: &[ProviderKind]| -> Vec<Arc<dyn MetadataProvider>> {
+            kinds
+                .iter()
+                .filter_map(|&kind| {
+                    let provider = created.entry(kind).or_insert_with(|| self.create(kind));
+                    provider.as_ref().ok().cloned()
+                })
+                .collect()
+        };
+        let movies = chain(&self.settings.movies);
+        let tv = chain(&self.settings.tv);
+
+        let mut providers = Providers::new(movies, tv);
+        providers.unavailable = created.into_values().filter_map(Result::err).collect();
+        providers
+    }
+}
+
+/// Normalize a metadata language such as "de" or "pt-br" to "pt-BR" form.
+/// An empty code means the provider's default language.
+pub fn normalize_language(code: &str) -> Result<Option<String>, String> {
+    let code = code.trim();
+    if code.is_empty() {
+        return Ok(None);
+    }
+
+    let invalid = || format!("Unknown language code \"{}\" (use e.g. de or de-DE)", code);
+    let (language, region) = match code.split_once(['-', '_']) {
+        Some((language, region)) => (language, Some(region)),
+        None => (code, None),
+    };
+    if language.len() != 2 || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid());
+    }
+    match region {
+        Some(region) if region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Ok(Some(format!(
+                "{}-{}",
+                language.to_ascii_lowercase(),
+                region.to_ascii_uppercase()
+            )))
+        }
+        Some(_) => Err(invalid()),
+        None => Ok(Some(language.to_ascii_lowercase())),
+    }
+}
+
+/// Providers to query for movies and TV shows, in order of preference
+pub struct Providers {
+    movies: Vec<Arc<dyn MetadataProvider>>,
+    tv: Vec<Arc<dyn MetadataProvider>>,
+    unavailable: Vec<String>, // Why configured providers couldn't be created
+}
+
+impl Providers {
+    pub fn new(movies: Vec<Arc<dyn MetadataProvider>>, tv: Vec<Arc<dyn MetadataProvider>>) -> Self {
+        Self {
+            movies,
+            tv,
+            unavailable: Vec::new(),
+        }
+    }
+
+    /// Providers for a media type, primary first
+    fn chain(&self, media_type: MediaType) -> Result<&[Arc<dyn MetadataProvider>], String> {
+        let chain = match media_type {
+            MediaType::TvShow => &self.tv,
+            MediaType::Movie | MediaType::Unknown => &self.movies,
+        };
+        if chain.is_empty() {
+            return Err(self
+                .unavailable
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "No metadata provider configured".to_string()));
+        }
+        Ok(chain)
+    }
+
+    fn get(&self, kind: ProviderKind) -> Option<&Arc<dyn MetadataProvider>> {
+        self.movies
+            .iter()
+            .chain(&self.tv)
+            .find(|provider| provider.kind() == kind)
+    }
+
+    /// Search each provider in turn until one finds something. Errors only
+    /// surface when no provider has results.
+    async fn search_first(
+        &self,
+        query: &str,
+        media_type: MediaType,
+        year: Option<u32>,
+    ) -> Result<Option<(&Arc<dyn MetadataProvider>, Vec<SearchResult>)>, String> {
+        let mut first_error = None;
+        for provider in self.chain(media_type)? {
+            match provider.search(query, media_type, year).await {
+                Ok(results) if !results.is_empty() => return Ok(Some((provider, results))),
+                Ok(_) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    /// Search for media, falling back to later providers when one finds nothing
+    pub async fn search(
+        &self,
+        query: &str,
+        media_type: MediaType,
+        year: Option<u32>,
+    ) -> Result<Vec<SearchResult>, String> {
+        let found = self.search_first(query, media_type, year).await?;
+        Ok(found.map(|(_, results)| results).unwrap_or_default())
+    }
+
+    /// Fetch full metadata for a search result from the provider that found it
+    pub async fn fetch_metadata(
+        &self,
+        result: &SearchResult,
+        file: &BatchFileInfo,
+    ) -> Result<MediaMetadata, String> {
+        let provider = self
+            .get(result.provider)
+            .ok_or_else(|| format!("{} is not configured", result.provider.display_name()))?;
+
+        match result.media_type {
+            MediaType::TvShow => {
+                let (show, layout) = provider.show_details(result.id).await?;
+                provider.episode_details(&show, &layout, file).await
+            }
+            // For unknown type default to movie
+            MediaType::Movie | MediaType::Unknown => provider.movie_details(result.id).await,
+        }
+    }
+
+    /// Optimized batch matching - groups by title, fetches show once, episodes in parallel
+    pub async fn match_files(&self, files: Vec<BatchFileInfo>) -> Vec<(usize, MatchOutcome)> {
+        use futures::future::join_all;
+
+        let mut results: Vec<(usize, MatchOutcome)> = Vec::new();
+
+        // Separate movies and TV shows
+        let mut movies: Vec<BatchFileInfo> = Vec::new();
+        let mut tv_shows: HashMap<String, Vec<BatchFileInfo>> = HashMap::new();
+
+        for file in files {
+            if file.title.is_empty() {
+                let error = "No title parsed".to_string();
+                results.push((file.index, MatchOutcome::Failed(error)));
+                continue;
+            }
+
+            match file.media_type {
+                MediaType::Movie | MediaType::Unknown => movies.push(file),
+                MediaType::TvShow => {
+                    let key = file.title.to_lowercase();
+                    tv_shows.entry(key).or_default().push(file);
+                }
+            }
+        }
+
+        // Process movies (each needs individual search)
+        for movie in movies {
+            let mut outcome = self.match_movie(&movie).await;
+
+            // "Show.Special.Christmas.2019" reads like a movie title, so when no
+            // movie fits, try it as a season 0 special of the show before "Special"
+            if !matches!(outcome, MatchOutcome::Matched(_)) {
+                if let Some(special) = movie.as_bare_special() {
+                    if let Some(metadata) = self.match_special(&special).await {
+                        outcome = MatchOutcome::Matched(Box::new(metadata));
+                    }
+                }
+            }
+            results.push((movie.index, outcome));
+        }
+
+        // Process TV shows - grouped by title
+        for (_, episodes) in tv_shows {
+            if episodes.is_empty() {
+                continue;
+            }
+
+            let first = &episodes[0];
+
+            // Search for the show ONCE
+            // A special's year is when it aired, not when the show started
+            let year = first.year.filter(|_| !first.special);
+            let chosen = match self
+                .search_first(&first.title, MediaType::TvShow, year)
+                .await
+            {
+                Ok(Some((provider, r))) => {
+                    choose_candidate(&first.title, year, MediaType::TvShow, r)
+                        .map(|best| (provider, best))
+                }
+                Ok(None) => Err(MatchOutcome::Failed("No results found".to_string())),
+                Err(e) => Err(MatchOutcome::Failed(e)),
+            };
+            let (provider, search_result) = match chosen {
+                Ok(best) => best,
+                Err(outcome) => {
+                    for ep in &episodes {
+                        results.push((ep.index, outcome.clone()));
+                    }
+                    continue;
+                }
+            };
+
+            // Get show details ONCE
+            let (show_details, layout) = match provider.show_details(search_result.id).await {
+                Ok(d) => d,
+                Err(e) => {
+                    for ep in &episodes {
+                        results.push((ep.index, MatchOutcome::Failed(e.clone())));
+                    }
+                    continue;
+                }
+            };
+
+            // Fetch all episodes concurrently (in batches to avoid rate limits)
+            let batch_size = 5; // 5 concurrent requests
+            for chunk in episodes.chunks(batch_size) {
+                let futures: Vec<_> = chunk
+                    .iter()
+                    .map(|ep| {
+                        // Fetch episodes directly (without re-fetching show details)
+                        let details = provider.episode_details(&show_details, &layout, ep);
+                        async move { (ep.index, MatchOutcome::from(details.await)) }
+                    })
+                    .collect();
+
+                let batch_results = join_all(futures).await;
+                results.extend(batch_results);
+            }
+        }
+
+        results
+    }
+
+    async fn match_movie(&self, movie: &BatchFileInfo) -> MatchOutcome {
+        let found = self
+            .search_first(&movie.title, MediaType::Movie, movie.year)
+            .await;
+        match found {
+            Ok(Some((provider, search_results))) => {
+                let chosen =
+                    choose_candidate(&movie.title, movie.year, MediaType::Movie, search_results);
+                match chosen {
+                    Ok(best) => provider.movie_details(best.id).await.into(),
+                    Err(outcome) => outcome,
+                }
+            }
+            Ok(None) => MatchOutcome::Failed("No results found".to_string()),
+            Err(e) => MatchOutcome::Failed(e),
+        }
+    }
+
+    /// Metadata for a special, only when the show is a confident match and
+    /// one of its season 0 episodes matches the special's name
+    async fn match_special(&self, special: &BatchFileInfo) -> Option<MediaMetadata> {
+        let (provider, results) = self
+            .search_first(&special.title, MediaType::TvShow, None)
+            .await
+            .ok()??;
+        let best = choose_candidate(&special.title, None, MediaType::TvShow, results).ok()?;
+        let (show, layout) = provider.show_details(best.id).await.ok()?;
+        let metadata = provider
+            .episode_details(&show, &layout, special)
+            .await
+            .ok()?;
+        metadata.episode_number.is_some().then_some(metadata)
+    }
+}
+
+/// Episode counts per regular season, used to convert absolute episode numbers
+#[derive(Debug, Clone, Default)]
+pub struct SeasonLayout {
+    seasons: Vec<(u32, u32)>, // (season number, episode count), specials excluded
+    premieres: Vec<(u32, String)>, // (season number, first air date)
+}
+
+impl SeasonLayout {
+    pub fn new(mut seasons: Vec<(u32, u32)>) -> Self {
+        seasons.retain(|&(season, count)| season > 0 && count > 0);
+        seasons.sort_unstable();
+        Self {
+            seasons,
+            premieres: Vec::new(),
+        }
+    }
+
+    /// Attach season premiere dates (YYYY-MM-DD), used for date-based episodes
+    pub fn with_premieres(mut self, mut premieres: Vec<(u32, String)>) -> Self {
+        premieres.retain(|(season, _)| *season > 0);
+        premieres.sort();
+        self.premieres = premieres;
+        self
+    }
+
+    /// Seasons that may contain an episode aired on `date`: the last season that
+    /// premiered on or before it, then the one before in case dates are off
+    pub fn seasons_for_date(&self, date: &str) -> Vec<u32> {
+        // ISO dates compare correctly as strings
+        let started: Vec<u32> = self
+            .premieres
+            .iter()
+            .filter(|(_, premiere)| premiere.as_str() <= date)
+            .map(|(season, _)| *season)
+            .collect();
+        started.iter().rev().take(2).copied().collect()
+    }
+
+    /// Map an absolute episode number onto (season, episode)
+    pub fn to_season_episode(&self, absolute: u32) -> Option<(u32, u32)> {
+        if absolute == 0 {
+            return None;
+        }
+        let mut remaining = absolute;
+        for &(season, count) in &self.seasons {
+            if remaining <= count {
+                return Some((season, remaining));
+            }
+            remaining -= count;
+        }
+        None
+    }
+
+    /// Map (season, episode) onto an absolute episode number
+    pub fn to_absolute(&self, season: u32, episode: u32) -> Option<u32> {
+        let &(_, count) = self.seasons.iter().find(|(s, _)| *s == season)?;
+        if episode == 0 || episode > count {
+            return None;
+        }
+        let before: u32 = self
+            .seasons
+            .iter()
+            .take_while(|(s, _)| *s < season)
+            .map(|(_, c)| c)
+            .sum();
+        Some(before + episode)
+    }
+}
+
+/// Similarity of two titles from 0.0 to 1.0, ignoring case, punctuation and word order
+pub(crate) fn title_similarity(a: &str, b: &str, ignore: &[&str]) -> f32 {
+    let words = |s: &str| -> std::collections::HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .filter(|w| !ignore.contains(&w.as_str()))
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let common = a.intersection(&b).count();
+    2.0 * common as f32 / (a.len() + b.len()) as f32
+}
+
+/// One entry of a season's episode list (TMDB field names)
+#[derive(Debug, Clone, Deserialize)]
+pub struct EpisodeListing {
+    pub episode_number: u32,
+    #[serde(default)]
+    pub name: String,
+    pub air_date: Option<String>,
+}
+
+/// Details of a single episode (TMDB field names)
+#[derive(Debug, Clone, Deserialize)]
+pub struct EpisodeDetails {
+    pub name: String,
+    pub overview: Option<String>,
+    pub still_path: Option<String>,
+    pub air_date: Option<String>,
+    pub vote_average: Option<f32>,
+}
+
+/// Words that mark a special rather than name it
+pub(crate) const SPECIAL_MARKERS: &[&str] =
+    &["special", "specials", "ova", "oav", "oad", "the", "a", "of"];
+
+/// Pick the season 0 episode whose name best matches `name`, preferring ones that
+/// aired in `year`. Returns the episode number.
+pub(crate) fn best_special_match(
+    listing: &[EpisodeListing],
+    name: &str,
+    year: Option<u32>,
+) -> Option<u32> {
+    const MIN_SIMILARITY: f32 = 0.5;
+
+    let year_prefix = year.map(|y| y.to_string());
+    listing
+        .iter()
+        .filter_map(|ep| {
+            let similarity = title_similarity(name, &ep.name, SPECIAL_MARKERS);
+            if similarity < MIN_SIMILARITY {
+                return None;
+            }
+            let same_year = match (&year_prefix, &ep.air_date) {
+                (Some(y), Some(date)) => date.starts_with(y.as_str()),
+                _ => false,
+            };
+            let score = similarity + if same_year { 0.25 } else { 0.0 };
+            Some((score, ep.episode_number))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, episode)| episode)
+}
+
+/// Combine show details with one or more episodes into file metadata
+pub(crate) fn episode_metadata(
+    show: &MediaMetadata,
+    season: u32,
+    episode: u32,
+    episode_end: Option<u32>,
+    episodes: Vec<EpisodeDetails>,
+) -> MediaMetadata {
+    let mut metadata = show.clone();
+    metadata.season_number = Some(season);
+    metadata.episode_number = Some(episode);
+    metadata.episode_end = episode_end.filter(|end| *end > episode);
+
+    let titles: Vec<&str> = episodes
+        .iter()
+        .map(|ep| ep.name.as_str())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if !titles.is_empty() {
+        metadata.episode_title = Some(titles.join(" & "));
+    }
+
+    if let Some(first) = episodes.into_iter().next() {
+        metadata.overview = first.overview;
+        metadata.still_path = first.still_path;
+        metadata.vote_average = first.vote_average.or(metadata.vote_average);
+        metadata.air_date = first.air_date;
+    }
+
+    metadata
+}
+
+/// File info for batch matching
+#[derive(Clone)]
+pub struct BatchFileInfo {
+    pub index: usize,
+    pub title: String,
+    pub year: Option<u32>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    pub episode_end: Option<u32>,
+    pub absolute_episode: Option<u32>,
+    pub air_date: Option<String>,
+    pub special: bool,
+    pub episode_title: Option<String>,
+    pub media_type: MediaType,
+}
+
+impl BatchFileInfo {
+    /// Build batch info from a scanned file's parsed filename
+    pub fn from_media_file(index: usize, file: &MediaFile) -> Self {
+        let parsed = file.parsed_info.as_ref();
+        let mut title = parsed.map(|p| p.title.clone()).unwrap_or_default();
+
+        // If title is empty but it's a TV show, try to use parent folder name
+        if title.is_empty() && file.media_type == MediaType::TvShow {
+            if let Some(folder_name) = file.path.parent().and_then(|p| p.file_name()) {
+                title = folder_name.to_string_lossy().to_string();
+            }
+        }
+
+        Self {
+            index,
+            title,
+            year: parsed.and_then(|p| p.year),
+            season: parsed.and_then(|p| p.season),
+            episode: parsed.and_then(|p| p.episode),
+            episode_end: parsed.and_then(|p| p.episode_end),
+            absolute_episode: parsed.and_then(|p| p.absolute_episode),
+            air_date: parsed.and_then(|p| p.air_date.clone()),
+            special: parsed.is_some_and(|p| p.special),
+            episode_title: parsed.and_then(|p| p.episode_title.clone()),
+            media_type: file.media_type,
+        }
+    }
+
+    /// Read a title like "Doctor Who Special Christmas" as a season 0 special
+    /// named "Special Christmas" of "Doctor Who". "Special" alone is too common
+    /// in movie titles to decide this while parsing.
+    pub fn as_bare_special(&self) -> Option<Self> {
+        let words: Vec<&str> = self.title.split_whitespace().collect();
+        let marker = words
+            .iter()
+            .position(|w| w.eq_ignore_ascii_case("special"))
+            .filter(|&i| i > 0 && i + 1 < words.len())?;
+
+        Some(Self {
+            title: words[..marker].join(" "),
+            season: Some(0),
+            episode: None,
+            episode_end: None,
+            special: true,
+            episode_title: Some(words[marker..].join(" ")),
+            media_type: MediaType::TvShow,
+            ..self.clone()
+        })
+    }
+}
+
+/// Result of auto-matching a single file
+#[derive(Debug, Clone)]
+pub enum MatchOutcome {
+    Matched(Box<MediaMetadata>),
+    /// Low confidence or a near tie - top candidates for someone to confirm
+    NeedsReview(Vec<SearchResult>),
+    Failed(String),
+}
+
+impl From<Result<MediaMetadata, String>> for MatchOutcome {
+    fn from(result: Result<MediaMetadata, String>) -> Self {
+        match result {
+            Ok(metadata) => Self::Matched(Box::new(metadata)),
+            Err(e) => Self::Failed(e),
+        }
+    }
+}
+
+/// Below this score the best candidate isn't trusted
+const MIN_CONFIDENCE: f32 = 0.6;
+/// Candidates closer than this to the best one count as a tie
+const TIE_MARGIN: f32 = 0.05;
+/// How many candidates to offer when a match needs review
+const REVIEW_CANDIDATES: usize = 3;
+
+/// Score search results against a parsed file and sort them best first.
+/// Weighs title similarity, year proximity, media type and popularity.
+pub fn rank_candidates(
+    title: &str,
+    year: Option<u32>,
+    media_type: MediaType,
+    mut results: Vec<SearchResult>,
+) -> Vec<SearchResult> {
+    let max_popularity = results
+        .iter()
+        .filter_map(|r| r.popularity)
+        .fold(0.0_f32, f32::max);
+
+    for result in &mut results {
+        let title_score = title_similarity(title, &result.title, &[]);
+
+        let year_score = match (year, result.year) {
+            (Some(a), Some(b)) => match a.abs_diff(b) {
+                0 => 1.0,
+                1 => 0.6,
+                2 => 0.3,
+                _ => 0.0,
+            },
+            _ => 0.5, // Unknown on either side, neither helps nor hurts
+        };
+
+        let type_score = if media_type == MediaType::Unknown {
+            0.5
+        } else if media_type == result.media_type {
+            1.0
+        } else {
+            0.0
+        };
+
+        // Log scale so a blockbuster doesn't drown out everything else
+        let popularity_score = match result.popularity {
+            Some(p) if max_popularity > 0.0 => (1.0 + p).ln() / (1.0 + max_popularity).ln(),
+            _ => 0.0,
+        };
+
+        let score =
+            0.55 * title_score + 0.25 * year_score + 0.1 * type_score + 0.1 * popularity_score;
+        result.confidence = Some(score.clamp(0.0, 1.0));
+    }
+
+    results.sort_by(|a, b| {
+        b.confidence
+            .unwrap_or(0.0)
+            .total_cmp(&a.confidence.unwrap_or(0.0))
+    });
+    results
+}
+
+/// Whether ranked candidates are too weak or too close to pick automatically
+pub fn needs_review(ranked: &[SearchResult]) -> bool {
+    let score = |i: usize| ranked.get(i).and_then(|r| r.confidence);
+    match (score(0), score(1)) {
+        (Some(best), _) if best < MIN_CONFIDENCE => true,
+        (Some(best), Some(second)) => best - second < TIE_MARGIN,
+        _ => false,
+    }
+}
+
+/// Pick the best candidate, or the top few when it needs review
+fn choose_candidate(
+    title: &str,
+    year: Option<u32>,
+    media_type: MediaType,
+    results: Vec<SearchResult>,
+) -> Result<SearchResult, MatchOutcome> {
+    if results.is_empty() {
+        return Err(MatchOutcome::Failed("No results found".to_string()));
+    }
+    let mut ranked = rank_candidates(title, year, media_type, results);
+    if needs_review(&ranked) {
+        ranked.truncate(REVIEW_CANDIDATES);
+        return Err(MatchOutcome::NeedsReview(ranked));
+    }
+    Ok(ranked.swap_remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_season_layout_absolute_to_season_episode() {
+        // Specials and empty seasons are ignored, seasons sorted by number
+        let layout = SeasonLayout::new(vec![(0, 3), (2, 13), (1, 12), (3, 0)]);
+
+        assert_eq!(layout.to_season_episode(1), Some((1, 1)));
+        assert_eq!(layout.to_season_episode(12), Some((1, 12)));
+        assert_eq!(layout.to_season_episode(13), Some((2, 1)));
+        assert_eq!(layout.to_season_episode(25), Some((2, 13)));
+        assert_eq!(layout.to_season_episode(26), None);
+        assert_eq!(layout.to_season_episode(0), None);
+    }
+
+    #[test]
+    fn test_season_layout_season_episode_to_absolute() {
+        let layout = SeasonLayout::new(vec![(1, 12), (2, 13)]);
+
+        assert_eq!(layout.to_absolute(1, 5), Some(5));
+        assert_eq!(layout.to_absolute(2, 1), Some(13));
+        assert_eq!(layout.to_absolute(2, 14), None);
+        assert_eq!(layout.to_absolute(3, 1), None);
+    }
+
+    #[test]
+    fn test_season_layout_seasons_for_date() {
+        let layout = SeasonLayout::new(vec![(1, 150), (2, 160), (3, 40)]).with_premieres(vec![
+            (0, "2020-01-01".to_string()),
+            (2, "2023-01-09".to_string()),
+            (1, "2022-01-10".to_string()),
+            (3, "2024-01-08".to_string()),
+        ]);
+
+        assert_eq!(layout.seasons_for_date("2024-03-15"), vec![3, 2]);
+        assert_eq!(layout.seasons_for_date("2023-01-09"), vec![2, 1]);
+        assert_eq!(layout.seasons_for_date("2022-05-01"), vec![1]);
+        assert!(layout.seasons_for_date("2021-12-31").is_empty());
+    }
+
+    fn candidate(id: u64, title: &str, year: u32, popularity: f32) -> SearchResult {
+        SearchResult {
+            provider: ProviderKind::Tmdb,
+            id,
+            title: title.to_string(),
+            year: Some(year),
+            media_type: MediaType::Movie,
+            overview: None,
+            poster_path: None,
+            vote_average: None,
+            popularity: Some(popularity),
+            confidence: None,
+        }
+    }
+
+    #[test]
+    fn test_rank_candidates_prefers_matching_year() {
+        let results = vec![
+            candidate(1, "The Thing", 2011, 40.0),
+            candidate(2, "The Thing", 1982, 35.0),
+        ];
+        let ranked = rank_candidates("The Thing", Some(1982), MediaType::Movie, results);
+
+        assert_eq!(ranked[0].id, 2);
+        assert!(ranked[0].confidence > ranked[1].confidence);
+        assert!(!needs_review(&ranked));
+    }
+
+    #[test]
+    fn test_rank_candidates_remake_without_year_needs_review() {
+        let results = vec![
+            candidate(1, "The Thing", 2011, 40.0),
+            candidate(2, "The Thing", 1982, 35.0),
+            candidate(3, "The Thing Called Love", 1993, 5.0),
+        ];
+        let ranked = rank_candidates("The Thing", None, MediaType::Movie, results);
+
+        assert!(needs_review(&ranked));
+        assert_eq!(ranked.last().map(|r| r.id), Some(3));
+    }
+
+    #[test]
+    fn test_rank_candidates_low_confidence_needs_review() {
+        let results = vec![candidate(1, "Something Else Entirely", 2001, 10.0)];
+        let ranked = rank_candidates("The Matrix", Some(1999), MediaType::Movie, results);
+
+        assert!(ranked[0].confidence.unwrap() < MIN_CONFIDENCE);
+        assert!(needs_review(&ranked));
+    }
+
+    #[test]
+    fn test_rank_candidates_single_exact_match_is_confident() {
+        let results = vec![candidate(603, "The Matrix", 1999, 80.0)];
+        let ranked = rank_candidates("The Matrix", Some(1999), MediaType::Movie, results);
+
+        assert_eq!(ranked[0].confidence, Some(1.0));
+        assert!(!needs_review(&ranked));
+        assert!(!needs_review(&[]));
+    }
+
+    #[test]
+    fn test_choose_candidate_truncates_review_list() {
+        let results = (1..=6)
+            .map(|id| candidate(id, "Hamlet", 1990 + id as u32 * 10, 10.0))
+            .collect();
+        match choose_candidate("Hamlet", None, MediaType::Movie, results) {
+            Err(MatchOutcome::NeedsReview(candidates)) => {
+                assert_eq!(candidates.len(), REVIEW_CANDIDATES)
+            }
+            other => panic!("expected review, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_title_similarity() {
+        assert_eq!(title_similarity("The Office", "the office", &[]), 1.0);
+        assert_eq!(
+            title_similarity("Christmas Special", "Special: Christmas", &[]),
+            1.0
+        );
+        assert_eq!(
+            title_similarity("Breaking Bad", "Better Call Saul", &[]),
+            0.0
+        );
+        assert_eq!(title_similarity("", "Anything", &[]), 0.0);
+        assert_eq!(title_similarity("The Office", "Office", &["the"]), 1.0);
+    }
+    fn episode(name: &str, air_date: &str) -> EpisodeDetails {
+        EpisodeDetails {
+            name: name.to_string(),
+            overview: Some(format!("{} overview", name)),
+            still_path: None,
+            air_date: Some(air_date.to_string()),
+            vote_average: None,
+        }
+    }
+
+    fn show() -> MediaMetadata {
+        MediaMetadata {
+            tmdb_id: 1396,
+            title: "Breaking Bad".to_string(),
+            show_name: Some("Breaking Bad".to_string()),
+            poster_path: Some("/show.jpg".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn special(episode_number: u32, name: &str, air_date: &str) -> EpisodeListing {
+        EpisodeListing {
+            episode_number,
+            name: name.to_string(),
+            air_date: Some(air_date.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_special_markers_are_ignored_when_matching_names() {
+        assert!(
+            title_similarity(
+                "Special Christmas",
+                "The Christmas Invasion",
+                SPECIAL_MARKERS
+            ) > 0.5
+        );
+    }
+
+    #[test]
+    fn test_best_special_match_by_name() {
+        let listing = vec![
+            special(1, "OVA 1", "2006-02-10"),
+            special(2, "OVA 2", "2006-08-25"),
+            special(3, "Behind the Scenes", "2007-01-01"),
+        ];
+        assert_eq!(best_special_match(&listing, "OVA 2", None), Some(2));
+        assert_eq!(best_special_match(&listing, "Special Bloopers", None), None);
+    }
+
+    #[test]
+    fn test_best_special_match_prefers_year() {
+        let listing = vec![
+            special(4, "The Christmas Invasion", "2005-12-25"),
+            special(12, "The Christmas Invasion Revisited", "2019-12-25"),
+            special(13, "A Christmas Carol", "2010-12-25"),
+        ];
+        assert_eq!(
+            best_special_match(&listing, "Special Christmas Invasion", None),
+            Some(4)
+        );
+        assert_eq!(
+            best_special_match(&listing, "Special Christmas Invasion", Some(2019)),
+            Some(12)
+        );
+    }
+
+    #[test]
+    fn test_episode_metadata_joins_multi_episode_titles() {
+        let episodes = vec![
+            episode("Pilot", "2008-01-20"),
+            episode("Cat's in the Bag...", "2008-01-27"),
+        ];
+        let metadata = episode_metadata(&show(), 1, 1, Some(2), episodes);
+
+        assert_eq!(metadata.season_number, Some(1));
+        assert_eq!(metadata.episode_number, Some(1));
+        assert_eq!(metadata.episode_end, Some(2));
+        assert_eq!(
+            metadata.episode_title,
+            Some("Pilot & Cat's in the Bag...".to_string())
+        );
+        // Overview and air date come from the first episode
+        assert_eq!(metadata.overview, Some("Pilot overview".to_string()));
+        assert_eq!(metadata.air_date, Some("2008-01-20".to_string()));
+        assert_eq!(metadata.poster_path, Some("/show.jpg".to_string()));
+    }
+
+    #[test]
+    fn test_episode_metadata_keeps_show_poster_beside_still() {
+        let mut pilot = episode("Pilot", "2008-01-20");
+        pilot.still_path = Some("/pilot.jpg".to_string());
+        let metadata = episode_metadata(&show(), 1, 1, None, vec![pilot]);
+
+        assert_eq!(metadata.poster_path, Some("/show.jpg".to_string()));
+        assert_eq!(metadata.still_path, Some("/pilot.jpg".to_string()));
+    }
+
+    #[test]
+    fn test_episode_metadata_without_episodes_keeps_show() {
+        let metadata = episode_metadata(&show(), 2, 5, None, Vec::new());
+
+        assert_eq!(metadata.title, "Breaking Bad");
+        assert_eq!(metadata.season_number, Some(2));
+        assert_eq!(metadata.episode_number, Some(5));
+        assert_eq!(metadata.episode_end, None);
+        assert_eq!(metadata.episode_title, None);
+    }
+
+    /// In-memory provider returning canned search results
+    struct FakeProvider {
+        results: Result<Vec<SearchResult>, String>,
+        title: &'static str,
+        specials: Vec<EpisodeListing>,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for FakeProvider {
+        fn kind(&self) -> ProviderKind {
+            ProviderKind::Tmdb
+        }
+
+        async fn search(
+            &self,
+            _query: &str,
+            _media_type: MediaType,
+            _year: Option<u32>,
+        ) -> Result<Vec<SearchResult>, String> {
+            self.results.clone()
+        }
+
+        async fn movie_details(&self, id: u64) -> Result<MediaMetadata, String> {
+            Ok(MediaMetadata {
+                tmdb_id: id,
+                title: self.title.to_string(),
+                ..Default::default()
+            })
+        }
+
+        async fn show_details(&self, id: u64) -> Result<(MediaMetadata, SeasonLayout), String> {
+            Ok((self.movie_details(id).await?, SeasonLayout::default()))
+        }
+
+        async fn episode_details(
+            &self,
+            show: &MediaMetadata,
+            _layout: &SeasonLayout,
+            file: &BatchFileInfo,
+        ) -> Result<MediaMetadata, String> {
+            let mut metadata = show.clone();
+            metadata.season_number = file.season;
+            metadata.episode_number = file.episode;
+            if let (true, Some(name)) = (file.special, &file.episode_title) {
+                metadata.episode_number = best_special_match(&self.specials, name, file.year);
+            }
+            Ok(metadata)
+        }
+    }
+
+    fn fake(
+        results: Result<Vec<SearchResult>, String>,
+        title: &'static str,
+    ) -> Arc<dyn MetadataProvider> {
+        Arc::new(FakeProvider {
+            results,
+            title,
+            specials: Vec::new(),
+        })
+    }
+
+    fn movie_file(index: usize, title: &str, year: u32) -> BatchFileInfo {
+        BatchFileInfo {
+            index,
+            title: title.to_string(),
+            year: Some(year),
+            season: None,
+            episode: None,
+            episode_end: None,
+            absolute_episode: None,
+            air_date: None,
+            special: false,
+            episode_title: None,
+            media_type: MediaType::Movie,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_match_files_falls_back_when_primary_finds_nothing() {
+        let primary = fake(Ok(Vec::new()), "Primary");
+        let fallback = fake(
+            Ok(vec![candidate(603, "The Matrix", 1999, 80.0)]),
+            "Fallback",
+        );
+        let providers = Providers::new(vec![primary, fallback], Vec::new());
+
+        let results = providers
+            .match_files(vec![movie_file(0, "The Matrix", 1999)])
+            .await;
+        match &results[0].1 {
+            MatchOutcome::Matched(metadata) => {
+                assert_eq!(metadata.title, "Fallback");
+                assert_eq!(metadata.tmdb_id, 603);
+            }
+            other => panic!("expected match, got {:?}", other),
+        }
+    }
+
+    fn show_with_specials(title: &'static str, specials: Vec<EpisodeListing>) -> Providers {
+        let mut result = candidate(57243, title, 2005, 50.0);
+        result.media_type = MediaType::TvShow;
+        let show = Arc::new(FakeProvider {
+            results: Ok(vec![result]),
+            title,
+            specials,
+        });
+        Providers::new(vec![fake(Ok(Vec::new()), "Movie")], vec![show])
+    }
+
+    #[test]
+    fn test_bare_special_splits_show_from_name() {
+        let (_, info) = crate::utils::filename_parser::parse_filename(
+            "Doctor.Who.Special.Christmas.2019.720p.mkv",
+        );
+        let file = movie_file(0, &info.title, info.year.unwrap());
+
+        let special = file.as_bare_special().unwrap();
+        assert_eq!(special.title, "Doctor Who");
+        assert_eq!(special.episode_title.as_deref(), Some("Special Christmas"));
+        assert_eq!(special.season, Some(0));
+        assert_eq!(special.year, Some(2019));
+        assert!(special.special);
+        assert_eq!(special.media_type, MediaType::TvShow);
+
+        // "Special" needs a show before it and a name after it
+        assert!(movie_file(0, "Special Forces", 2011)
+            .as_bare_special()
+            .is_none());
+        assert!(movie_file(0, "Doctor Who Special", 2019)
+            .as_bare_special()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_match_files_retries_unmatched_movie_as_special() {
+        let providers = show_with_specials(
+            "Doctor Who",
+            vec![
+                special(4, "The Christmas Invasion", "2005-12-25"),
+                special(12, "Christmas Special: Resolution", "2019-01-01"),
+            ],
+        );
+
+        let file = movie_file(0, "Doctor Who Special Christmas Resolution", 2019);
+        let results = providers.match_files(vec![file]).await;
+        match &results[0].1 {
+            MatchOutcome::Matched(metadata) => {
+                assert_eq!(metadata.title, "Doctor Who");
+                assert_eq!(metadata.season_number, Some(0));
+                assert_eq!(metadata.episode_number, Some(12));
+            }
+            other => panic!("expected special, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_match_files_keeps_movie_without_matching_special() {
+        // The show is found, but nothing in its season 0 is called "Special Day"
+        let providers = show_with_specials("My", vec![special(1, "Pilot Extras", "2010-01-01")]);
+        let results = providers
+            .match_files(vec![movie_file(0, "My Special Day", 2019)])
+            .await;
+        assert!(matches!(results[0].1, MatchOutcome::Failed(_)));
+
+        // A movie that's found is never second-guessed
+        let movie = fake(Ok(vec![candidate(7, "My Special Day", 2019, 5.0)]), "Movie");
+        let providers = Providers::new(vec![movie], Vec::new());
+        let results = providers
+            .match_files(vec![movie_file(0, "My Special Day", 2019)])
+            .await;
+        match &results[0].1 {
+            MatchOutcome::Matched(metadata) => assert_eq!(metadata.tmdb_id, 7),
+            other => panic!("expected movie, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_reports_primary_error_when_nothing_found() {
+        let primary = fake(Err("TMDB error: HTTP 503".to_string()), "Primary");
+        let fallback = fake(Ok(Vec::new()), "Fallback");
+        let providers = Providers::new(vec![primary.clone(), fallback], Vec::new());
+        let error = providers.search("Anything", MediaType::Movie, None).await;
+        assert_eq!(error.unwrap_err(), "TMDB error: HTTP 503");
+
+        // Results from a fallback win over the primary's error
+        let fallback = fake(Ok(vec![candidate(1, "Anything", 2000, 1.0)]), "Fallback");
+        let providers = Providers::new(vec![primary, fallback], Vec::new());
+        let results = providers.search("Anything", MediaType::Movie, None).await;
+        assert_eq!(results.map(|r| r.len()), Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_missing_credentials_surface_when_matching() {
+        let providers = ProviderConfig::default().build();
+
+        let results = providers
+            .match_files(vec![movie_file(3, "The Matrix", 1999)])
+            .await;
+        match &results[0] {
+            (3, MatchOutcome::Failed(e)) => assert_eq!(e, "TMDB API key not set"),
+            other => panic!("expected failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_normalize_language() {
+        assert_eq!(normalize_language(""), Ok(None));
+        assert_eq!(normalize_language("  "), Ok(None));
+        assert_eq!(normalize_language("DE"), Ok(Some("de".to_string())));
+        assert_eq!(normalize_language("ja-jp"), Ok(Some("ja-JP".to_string())));
+        assert_eq!(normalize_language("pt_BR"), Ok(Some("pt-BR".to_string())));
+        assert!(normalize_language("German").is_err());
+        assert!(normalize_language("de-DEU").is_err());
+    }
+
+    #[test]
+    fn test_provider_settings_default_to_tmdb() {
+        let settings: ProviderSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.movies, vec![ProviderKind::Tmdb]);
+        assert_eq!(settings.tv, vec![ProviderKind::Tmdb]);
+    }
+}
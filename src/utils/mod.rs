@@ -1,4 +1,20 @@
+pub mod artwork;
+pub mod file_ops;
 pub mod file_scanner;
 pub mod filename_parser;
+pub mod http;
+pub mod journal;
+pub mod metadata;
+pub mod nfo;
+pub mod rate_limit;
 pub mod renamer;
+pub mod sidecar;
+pub mod subtitle;
+pub mod template;
+#[cfg(test)]
+pub mod test_dir;
+#[cfg(test)]
+pub mod test_server;
 pub mod tmdb;
+pub mod tmdb_cache;
+pub mod tvdb;
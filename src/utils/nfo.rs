@@ -0,0 +1,776 @@
+//! Kodi NFO files
+//!
+//! Writes `movie.nfo`, `tvshow.nfo` and per-episode `.nfo` files in Kodi's
+//! schema (which Jellyfin and Emby read as well) next to renamed videos.
+
+use crate::model::{MediaFile, MediaMetadata, ProviderKind, RenamePattern};
+use crate::utils::renamer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What to do about NFOs when files are renamed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NfoPolicy {
+    /// Don't write NFOs
+    #[default]
+    Off,
+    /// Write NFOs where there are none yet
+    Keep,
+    /// Replace existing NFOs
+    Overwrite,
+    /// Update the fields Reel knows in existing NFOs, keeping everything else
+    Merge,
+}
+
+impl NfoPolicy {
+    pub const ALL: [NfoPolicy; 4] = [
+        NfoPolicy::Off,
+        NfoPolicy::Keep,
+        NfoPolicy::Overwrite,
+        NfoPolicy::Merge,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NfoPolicy::Off => "Off",
+            NfoPolicy::Keep => "Keep",
+            NfoPolicy::Overwrite => "Overwrite",
+            NfoPolicy::Merge => "Merge",
+        }
+    }
+
+    /// Parse a name as accepted on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A renamed video to write NFOs for
+#[derive(Debug, Clone)]
+pub struct NfoTarget {
+    pub video_path: PathBuf,
+    /// Folder the rename pattern gives this movie or show alone, if any
+    pub title_dir: Option<PathBuf>,
+    pub metadata: MediaMetadata,
+}
+
+impl NfoTarget {
+    /// Target for a matched file that `pattern` renamed to `new_path`
+    pub fn for_file(file: &MediaFile, new_path: &Path, pattern: &RenamePattern) -> Option<Self> {
+        Some(Self {
+            video_path: new_path.to_path_buf(),
+            title_dir: renamer::title_dir(file, new_path, pattern),
+            metadata: file.matched_metadata.clone()?,
+        })
+    }
+
+    /// The movie's own folder, when the video sits directly in it
+    pub fn movie_dir(&self) -> Option<&Path> {
+        self.title_dir
+            .as_deref()
+            .filter(|dir| self.video_path.parent() == Some(*dir))
+    }
+
+    /// The show's own folder, above any season folders
+    pub fn show_dir(&self) -> Option<&Path> {
+        self.title_dir.as_deref()
+    }
+}
+
+/// An NFO file to write
+#[derive(Debug, Clone, PartialEq)]
+pub struct NfoFile {
+    pub path: PathBuf,
+    pub xml: String,
+}
+
+/// What happened to one NFO file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NfoWrite {
+    Created,
+    Replaced,
+    Merged,
+    Kept,
+}
+
+/// Outcome of writing the NFOs of a rename batch
+#[derive(Debug, Clone, Default)]
+pub struct NfoSummary {
+    pub written: usize,
+    pub kept: usize,
+    pub errors: Vec<String>,
+}
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+/// The NFO files for a renamed video. A movie in its own folder gets
+/// `movie.nfo`, otherwise `<video name>.nfo`. An episode gets `<video name>.nfo`,
+/// plus `tvshow.nfo` when the pattern gives the show its own folder.
+pub fn nfo_files(target: &NfoTarget) -> Vec<NfoFile> {
+    let metadata = &target.metadata;
+    let beside_video = target.video_path.with_extension("nfo");
+
+    if metadata.show_name.is_none() {
+        let path = match target.movie_dir() {
+            Some(movie_dir) => movie_dir.join("movie.nfo"),
+            None => beside_video,
+        };
+        return vec![NfoFile {
+            path,
+            xml: movie_nfo(metadata),
+        }];
+    }
+
+    let mut files = Vec::new();
+    if let Some(show_dir) = target.show_dir() {
+        files.push(NfoFile {
+            path: show_dir.join("tvshow.nfo"),
+            xml: tvshow_nfo(metadata),
+        });
+    }
+    if metadata.episode_number.is_some() {
+        files.push(NfoFile {
+            path: beside_video,
+            xml: episode_nfo(metadata),
+        });
+    }
+    files
+}
+
+pub fn movie_nfo(metadata: &MediaMetadata) -> String {
+    let mut xml = Xml::new("movie");
+    xml.field("title", Some(&metadata.title));
+    xml.field("originaltitle", metadata.original_title.as_deref());
+    xml.field("year", metadata.year.map(|y| y.to_string()).as_deref());
+    xml.field("plot", metadata.overview.as_deref());
+    xml.rating(metadata);
+    for genre in &metadata.genres {
+        xml.field("genre", Some(genre));
+    }
+    xml.unique_id(metadata);
+    xml.finish()
+}
+
+pub fn tvshow_nfo(metadata: &MediaMetadata) -> String {
+    // Once an episode is matched, the overview and rating are the episode's
+    let is_show = metadata.episode_number.is_none();
+    let mut xml = Xml::new("tvshow");
+    xml.field(
+        "title",
+        Some(metadata.show_name.as_deref().unwrap_or(&metadata.title)),
+    );
+    xml.field("originaltitle", metadata.original_title.as_deref());
+    xml.field("year", metadata.year.map(|y| y.to_string()).as_deref());
+    if is_show {
+        xml.field("plot", metadata.overview.as_deref());
+        xml.rating(metadata);
+    }
+    for genre in &metadata.genres {
+        xml.field("genre", Some(genre));
+    }
+    xml.unique_id(metadata);
+    xml.finish()
+}
+
+/// One `<episodedetails>` per episode, as Kodi expects for multi-episode files
+pub fn episode_nfo(metadata: &MediaMetadata) -> String {
+    let first = metadata.episode_number.unwrap_or(0);
+    let last = metadata.episode_end.unwrap_or(first).max(first);
+    let count = (last - first + 1) as usize;
+    // Multi-episode titles are joined with " & "; split them back if they line up
+    let titles: Vec<&str> = match metadata.episode_title.as_deref() {
+        Some(title) if title.split(" & ").count() == count => title.split(" & ").collect(),
+        Some(title) => vec![title; count],
+        None => Vec::new(),
+    };
+
+    let mut nfo = String::from(XML_HEADER);
+    for (i, episode) in (first..=last).enumerate() {
+        let mut xml = Xml::new("episodedetails");
+        xml.field("title", titles.get(i).copied());
+        xml.field("showtitle", metadata.show_name.as_deref());
+        xml.field(
+            "season",
+            metadata.season_number.map(|s| s.to_string()).as_deref(),
+        );
+        xml.field("episode", Some(&episode.to_string()));
+        if i == 0 {
+            xml.field("aired", metadata.air_date.as_deref());
+            xml.field("plot", metadata.overview.as_deref());
+            xml.rating(metadata);
+        }
+        nfo.push_str(&xml.element());
+    }
+    nfo
+}
+
+/// Builds one root element with indented children
+struct Xml {
+    root: &'static str,
+    body: String,
+}
+
+impl Xml {
+    fn new(root: &'static str) -> Self {
+        Self {
+            root,
+            body: format!("<{}>\n", root),
+        }
+    }
+
+    fn field(&mut self, name: &str, value: Option<&str>) {
+        if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+            self.body
+                .push_str(&format!("  <{0}>{1}</{0}>\n", name, escape(value.trim())));
+        }
+    }
+
+    fn rating(&mut self, metadata: &MediaMetadata) {
+        if let Some(rating) = metadata.vote_average.filter(|r| *r > 0.0) {
+            self.body.push_str(&format!(
+                "  <ratings>\n    <rating name=\"{}\" max=\"10\" default=\"true\">\n      <value>{:.1}</value>\n    </rating>\n  </ratings>\n",
+                rating_name(metadata.provider),
+                rating
+            ));
+        }
+    }
+
+    fn unique_id(&mut self, metadata: &MediaMetadata) {
+        if metadata.provider_id != 0 {
+            self.body.push_str(&format!(
+                "  <uniqueid type=\"{}\" default=\"true\">{}</uniqueid>\n",
+                id_type(metadata.provider),
+                metadata.provider_id
+            ));
+        }
+        if metadata.tmdb_id != 0 && metadata.provider != ProviderKind::Tmdb {
+            self.body.push_str(&format!(
+                "  <uniqueid type=\"tmdb\">{}</uniqueid>\n",
+                metadata.tmdb_id
+            ));
+        }
+    }
+
+    /// The element without the XML declaration
+    fn element(self) -> String {
+        format!("{}</{}>\n", self.body, self.root)
+    }
+
+    fn finish(self) -> String {
+        format!("{}{}", XML_HEADER, self.element())
+    }
+}
+
+fn id_type(provider: ProviderKind) -> &'static str {
+    match provider {
+        ProviderKind::Tmdb => "tmdb",
+        ProviderKind::Tvdb => "tvdb",
+    }
+}
+
+fn rating_name(provider: ProviderKind) -> &'static str {
+    match provider {
+        ProviderKind::Tmdb => "themoviedb",
+        ProviderKind::Tvdb => "tvdb",
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Write one NFO file according to the policy
+pub fn write_nfo(file: &NfoFile, policy: NfoPolicy) -> Result<NfoWrite, String> {
+    let existing = match fs::read_to_string(&file.path) {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read {}: {}", file.path.display(), e)),
+    };
+
+    let (contents, write) = match (existing, policy) {
+        (_, NfoPolicy::Off) | (Some(_), NfoPolicy::Keep) => return Ok(NfoWrite::Kept),
+        (None, _) => (file.xml.clone(), NfoWrite::Created),
+        (Some(_), NfoPolicy::Overwrite) => (file.xml.clone(), NfoWrite::Replaced),
+        (Some(existing), NfoPolicy::Merge) => match merge(&existing, &file.xml) {
+            Some(merged) => (merged, NfoWrite::Merged),
+            None => {
+                return Err(format!(
+                    "Can't merge {}: not a Kodi NFO of the same kind",
+                    file.path.display()
+                ))
+            }
+        },
+    };
+
+    if let Some(parent) = file.path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    fs::write(&file.path, contents)
+        .map_err(|e| format!("Failed to write {}: {}", file.path.display(), e))?;
+    Ok(write)
+}
+
+/// Write the NFOs of a rename batch. A `tvshow.nfo` shared by several
+/// episodes is written once.
+pub async fn write_nfos(targets: Vec<NfoTarget>, policy: NfoPolicy) -> NfoSummary {
+    tokio::task::spawn_blocking(move || write_nfos_blocking(&targets, policy))
+        .await
+        .unwrap_or_else(|e| NfoSummary {
+            errors: vec![format!("Task error: {}", e)],
+            ..Default::default()
+        })
+}
+
+pub fn write_nfos_blocking(targets: &[NfoTarget], policy: NfoPolicy) -> NfoSummary {
+    let mut summary = NfoSummary::default();
+    if policy == NfoPolicy::Off {
+        return summary;
+    }
+    let mut seen = HashSet::new();
+    for file in targets.iter().flat_map(nfo_files) {
+        if !seen.insert(file.path.clone()) {
+            continue;
+        }
+        match write_nfo(&file, policy) {
+            Ok(NfoWrite::Kept) => summary.kept += 1,
+            Ok(_) => summary.written += 1,
+            Err(e) => summary.errors.push(e),
+        }
+    }
+    summary
+}
+
+/// Update an existing NFO with generated XML: generated elements replace the
+/// existing ones of the same name, in place, and everything else is kept.
+/// `<uniqueid>`s are matched by type and `<rating>`s by name, so IDs and
+/// ratings from other sites survive. Multi-episode files are merged root by root.
+pub fn merge(existing: &str, generated: &str) -> Option<String> {
+    let old_roots = elements(existing)?;
+    let new_roots = elements(generated)?;
+    if old_roots.is_empty() || old_roots[0].name != new_roots.first()?.name {
+        return None;
+    }
+
+    let mut merged = String::from(XML_HEADER);
+    for (i, new_root) in new_roots.iter().enumerate() {
+        let Some(old_root) = old_roots.get(i) else {
+            merged.push_str(new_root.text);
+            merged.push('\n');
+            continue;
+        };
+        merged.push_str(&format!("<{}>\n", new_root.name));
+        merged.push_str(&merge_children(old_root, new_root, "  ")?);
+        merged.push_str(&format!("</{}>\n", new_root.name));
+    }
+    Some(merged)
+}
+
+/// The merged children of two elements, one per line at `indent`
+fn merge_children(old: &Element, new: &Element, indent: &str) -> Option<String> {
+    let old_children = elements(old.inner()?)?;
+    let new_children = elements(new.inner()?)?;
+    let keys: HashSet<_> = new_children.iter().map(Element::merge_key).collect();
+    let mut emitted = HashSet::new();
+
+    let mut merged = String::new();
+    let mut push = |text: &str| {
+        merged.push_str(indent);
+        merged.push_str(text);
+        merged.push('\n');
+    };
+    for child in &old_children {
+        let key = child.merge_key();
+        if !keys.contains(&key) {
+            // The generated ID or rating is the default now
+            match child.name {
+                "uniqueid" | "rating" => push(&child.text.replacen(" default=\"true\"", "", 1)),
+                _ => push(child.text),
+            }
+        } else if emitted.insert(key) {
+            for new_child in new_children.iter().filter(|c| c.merge_key() == key) {
+                if new_child.name == "ratings" {
+                    let ratings = merge_children(child, new_child, &format!("{}  ", indent))?;
+                    push(&format!("<ratings>\n{}{}</ratings>", ratings, indent));
+                } else {
+                    push(new_child.text);
+                }
+            }
+        }
+    }
+    for new_child in new_children
+        .iter()
+        .filter(|c| !emitted.contains(&c.merge_key()))
+    {
+        push(new_child.text);
+    }
+    Some(merged)
+}
+
+/// A top-level element of an XML fragment
+struct Element<'a> {
+    name: &'a str,
+    text: &'a str,
+}
+
+impl<'a> Element<'a> {
+    /// Everything between the start and end tags
+    fn inner(&self) -> Option<&'a str> {
+        let start = self.text.find('>')? + 1;
+        if self.text[..start].ends_with("/>") {
+            return Some("");
+        }
+        let end = self.text.rfind("</")?;
+        self.text.get(start..end)
+    }
+
+    /// Value of an attribute of the start tag
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        let tag = &self.text[..self.text.find('>')?];
+        let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+        let length = tag[start..].find('"')?;
+        Some(&tag[start..start + length])
+    }
+
+    /// What an existing element is replaced by when merging: generated
+    /// elements of the same name, or with the same type/name attribute for IDs
+    /// and ratings
+    fn merge_key(&self) -> (&'a str, Option<&'a str>) {
+        let attribute = match self.name {
+            "uniqueid" => self.attribute("type"),
+            "rating" => self.attribute("name"),
+            _ => None,
+        };
+        (self.name, attribute)
+    }
+}
+
+/// Top-level elements of an XML fragment, skipping the declaration, comments
+/// and text between elements. `None` if the tags don't balance.
+fn elements(xml: &str) -> Option<Vec<Element<'_>>> {
+    let mut elements = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut name = "";
+    let mut pos = 0;
+
+    while let Some(offset) = xml[pos..].find('<') {
+        let tag_start = pos + offset;
+        let rest = &xml[tag_start..];
+        let skip_to = |end: &str| rest.find(end).map(|i| tag_start + i + end.len());
+
+        if rest.starts_with("<?") {
+            pos = skip_to("?>")?;
+        } else if rest.starts_with("<!--") {
+            pos = skip_to("-->")?;
+        } else if rest.starts_with("<![CDATA[") {
+            pos = skip_to("]]>")?;
+        } else if rest.starts_with("<!") {
+            pos = skip_to(">")?;
+        } else if rest.starts_with("</") {
+            pos = skip_to(">")?;
+            depth = depth.checked_sub(1)?;
+            if depth == 0 {
+                elements.push(Element {
+                    name,
+                    text: &xml[start..pos],
+                });
+            }
+        } else {
+            pos = skip_to(">")?;
+            let tag = &xml[tag_start + 1..pos - 1];
+            let self_closing = tag.ends_with('/');
+            let tag_name = tag
+                .trim_end_matches('/')
+                .split_whitespace()
+                .next()
+                .unwrap_or_default();
+            if depth == 0 {
+                start = tag_start;
+                name = tag_name;
+                if self_closing {
+                    elements.push(Element {
+                        name,
+                        text: &xml[start..pos],
+                    });
+                    continue;
+                }
+            }
+            if !self_closing {
+                depth += 1;
+            }
+        }
+    }
+
+    (depth == 0).then_some(elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::temp_dir;
+
+    fn movie() -> MediaMetadata {
+        MediaMetadata {
+            tmdb_id: 603,
+            provider: ProviderKind::Tmdb,
+            provider_id: 603,
+            title: "The Matrix".to_string(),
+            year: Some(1999),
+            overview: Some("Neo learns the truth & fights back.".to_string()),
+            vote_average: Some(8.2),
+            genres: vec!["Action".to_string(), "Science Fiction".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn episode() -> MediaMetadata {
+        MediaMetadata {
+            tmdb_id: 1396,
+            provider: ProviderKind::Tmdb,
+            provider_id: 1396,
+            title: "Breaking Bad".to_string(),
+            year: Some(2008),
+            overview: Some("Walt's first cook.".to_string()),
+            genres: vec!["Drama".to_string()],
+            season_number: Some(1),
+            episode_number: Some(1),
+            episode_title: Some("Pilot".to_string()),
+            air_date: Some("2008-01-20".to_string()),
+            show_name: Some("Breaking Bad".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_movie_nfo() {
+        let xml = movie_nfo(&movie());
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<movie>\n  <title>The Matrix</title>\n  <year>1999</year>"));
+        assert!(xml.contains("<plot>Neo learns the truth &amp; fights back.</plot>"));
+        assert!(xml.contains("<rating name=\"themoviedb\" max=\"10\" default=\"true\">"));
+        assert!(xml.contains("<value>8.2</value>"));
+        assert!(xml.contains("<genre>Action</genre>\n  <genre>Science Fiction</genre>"));
+        assert!(xml.contains("<uniqueid type=\"tmdb\" default=\"true\">603</uniqueid>"));
+        assert!(!xml.contains("originaltitle"));
+        assert!(xml.ends_with("</movie>\n"));
+    }
+
+    #[test]
+    fn test_tv_nfos() {
+        let show = tvshow_nfo(&episode());
+        assert!(show.contains("<tvshow>\n  <title>Breaking Bad</title>"));
+        // The overview belongs to the episode, not the show
+        assert!(!show.contains("<plot>"));
+
+        let mut metadata = episode();
+        metadata.provider = ProviderKind::Tvdb;
+        metadata.provider_id = 81189;
+        let xml = tvshow_nfo(&metadata);
+        assert!(xml.contains("<uniqueid type=\"tvdb\" default=\"true\">81189</uniqueid>"));
+        assert!(xml.contains("<uniqueid type=\"tmdb\">1396</uniqueid>"));
+
+        let xml = episode_nfo(&episode());
+        assert!(xml.contains("<title>Pilot</title>\n  <showtitle>Breaking Bad</showtitle>\n  <season>1</season>\n  <episode>1</episode>\n  <aired>2008-01-20</aired>"));
+    }
+
+    #[test]
+    fn test_multi_episode_nfo() {
+        let mut metadata = episode();
+        metadata.episode_end = Some(2);
+        metadata.episode_title = Some("Pilot & Cat's in the Bag...".to_string());
+        let xml = episode_nfo(&metadata);
+        assert_eq!(xml.matches("<episodedetails>").count(), 2);
+        assert!(xml.contains("<title>Pilot</title>"));
+        assert!(xml.contains("<title>Cat&apos;s in the Bag...</title>"));
+        assert!(xml.contains("<episode>2</episode>"));
+        assert_eq!(xml.matches("<plot>").count(), 1);
+    }
+
+    #[test]
+    fn test_nfo_files_placement() {
+        let target = |path: &str, title_dir: Option<&str>, metadata| NfoTarget {
+            video_path: PathBuf::from(path),
+            title_dir: title_dir.map(PathBuf::from),
+            metadata,
+        };
+        let paths = |target: &NfoTarget| -> Vec<PathBuf> {
+            nfo_files(target).into_iter().map(|f| f.path).collect()
+        };
+
+        let in_folder = target(
+            "/lib/The Matrix (1999)/The Matrix (1999).mkv",
+            Some("/lib/The Matrix (1999)"),
+            movie(),
+        );
+        assert_eq!(
+            paths(&in_folder),
+            vec![PathBuf::from("/lib/The Matrix (1999)/movie.nfo")]
+        );
+        let flat = target("/lib/The Matrix (1999).mkv", None, movie());
+        assert_eq!(
+            paths(&flat),
+            vec![PathBuf::from("/lib/The Matrix (1999).nfo")]
+        );
+
+        let episode_path = "/tv/Breaking Bad/Season 01/Breaking Bad - S01E01.mkv";
+        assert_eq!(
+            paths(&target(episode_path, Some("/tv/Breaking Bad"), episode())),
+            vec![
+                PathBuf::from("/tv/Breaking Bad/tvshow.nfo"),
+                PathBuf::from("/tv/Breaking Bad/Season 01/Breaking Bad - S01E01.nfo"),
+            ]
+        );
+        assert_eq!(
+            paths(&target("/tv/Breaking Bad - S01E01.mkv", None, episode())),
+            vec![PathBuf::from("/tv/Breaking Bad - S01E01.nfo")]
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_unknown_fields() {
+        let existing = "<?xml version=\"1.0\"?>\n<!-- edited by hand -->\n<movie>\n  <title>Old Title</title>\n  <playcount>3</playcount>\n  <genre>Old</genre>\n  <genre>Older</genre>\n  <thumb aspect=\"poster\">http://x/p.jpg</thumb>\n  <set><name>Matrix Collection</name></set>\n  <empty/>\n</movie>\n";
+        let merged = merge(existing, &movie_nfo(&movie())).unwrap();
+
+        assert!(merged.contains("<title>The Matrix</title>"));
+        assert!(!merged.contains("Old Title"));
+        assert!(merged.contains("<playcount>3</playcount>"));
+        assert!(merged.contains("<thumb aspect=\"poster\">http://x/p.jpg</thumb>"));
+        assert!(merged.contains("<set><name>Matrix Collection</name></set>"));
+        assert!(merged.contains("<empty/>"));
+        assert!(!merged.contains("<genre>Old</genre>"));
+        // Replaced fields stay where they were
+        let title = merged.find("<title>").unwrap();
+        let playcount = merged.find("<playcount>").unwrap();
+        let genre = merged.find("<genre>Action").unwrap();
+        assert!(title < playcount && playcount < genre);
+        assert!(merged.contains("<uniqueid type=\"tmdb\" default=\"true\">603</uniqueid>"));
+
+        assert!(merge("<tvshow></tvshow>", &movie_nfo(&movie())).is_none());
+        assert!(merge("https://www.themoviedb.org/movie/603", &movie_nfo(&movie())).is_none());
+        assert!(merge("<movie><title>x</movie>", &movie_nfo(&movie())).is_none());
+    }
+
+    #[test]
+    fn test_movies_sharing_a_folder_get_their_own_nfos() {
+        let pattern = RenamePattern {
+            name: "Shared".to_string(),
+            movie_pattern: "Movies/{title} ({year})".to_string(),
+            tv_pattern: "{show}".to_string(),
+            language: None,
+        };
+        let target = |title: &str, year| {
+            let mut file = MediaFile::new(PathBuf::from(format!("/downloads/{}.mkv", title)));
+            file.matched_metadata = Some(MediaMetadata {
+                title: title.to_string(),
+                year: Some(year),
+                ..movie()
+            });
+            file.new_filename = Some(format!("Movies/{} ({}).mkv", title, year));
+            let new_path = Path::new("/lib").join(file.new_filename.as_ref().unwrap());
+            NfoTarget::for_file(&file, &new_path, &pattern).unwrap()
+        };
+
+        let paths: Vec<PathBuf> = [target("The Matrix", 1999), target("Heat", 1995)]
+            .iter()
+            .flat_map(nfo_files)
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/lib/Movies/The Matrix (1999).nfo"),
+                PathBuf::from("/lib/Movies/Heat (1995).nfo"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_matches_ids_by_type_and_ratings_by_name() {
+        let existing = "<movie>\n  <title>The Matrix</title>\n  <ratings>\n    <rating name=\"imdb\" max=\"10\" default=\"true\">\n      <value>8.7</value>\n    </rating>\n    <rating name=\"themoviedb\" max=\"10\">\n      <value>7.0</value>\n    </rating>\n  </ratings>\n  <uniqueid type=\"imdb\" default=\"true\">tt0133093</uniqueid>\n  <uniqueid type=\"tmdb\">1</uniqueid>\n</movie>\n";
+        let merged = merge(existing, &movie_nfo(&movie())).unwrap();
+
+        assert!(merged.contains("<uniqueid type=\"imdb\">tt0133093</uniqueid>"));
+        assert!(merged.contains("<uniqueid type=\"tmdb\" default=\"true\">603</uniqueid>"));
+        assert!(!merged.contains(">1</uniqueid>"));
+        assert!(merged.contains("<rating name=\"imdb\" max=\"10\">"));
+        assert!(merged.contains("<value>8.7</value>"));
+        assert!(merged.contains("<value>8.2</value>"));
+        assert!(!merged.contains("<value>7.0</value>"));
+        assert_eq!(merged.matches("<ratings>").count(), 1);
+        assert_eq!(merged.matches("default=\"true\"").count(), 2);
+        // Still a well-formed NFO
+        assert_eq!(elements(&merged).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_write_nfos_follows_policy() {
+        let dir = temp_dir("nfo", "policy");
+        let targets = vec![
+            NfoTarget {
+                video_path: dir.join("Show/Season 01/S01E01.mkv"),
+                title_dir: Some(dir.join("Show")),
+                metadata: episode(),
+            },
+            NfoTarget {
+                video_path: dir.join("Show/Season 01/S01E02.mkv"),
+                title_dir: Some(dir.join("Show")),
+                metadata: MediaMetadata {
+                    episode_number: Some(2),
+                    ..episode()
+                },
+            },
+        ];
+        let episode_nfo = dir.join("Show/Season 01/S01E01.nfo");
+
+        let summary = write_nfos_blocking(&targets, NfoPolicy::Off);
+        assert_eq!((summary.written, summary.kept), (0, 0));
+        assert!(!episode_nfo.exists());
+
+        // tvshow.nfo is shared by both episodes
+        let summary = write_nfos_blocking(&targets, NfoPolicy::Keep);
+        assert_eq!((summary.written, summary.kept), (3, 0));
+        assert!(dir.join("Show/tvshow.nfo").exists());
+
+        fs::write(
+            &episode_nfo,
+            "<episodedetails><title>Mine</title><watched>true</watched></episodedetails>",
+        )
+        .unwrap();
+        let summary = write_nfos_blocking(&targets, NfoPolicy::Keep);
+        assert_eq!((summary.written, summary.kept), (0, 3));
+        assert!(fs::read_to_string(&episode_nfo).unwrap().contains("Mine"));
+
+        write_nfos_blocking(&targets, NfoPolicy::Merge);
+        let merged = fs::read_to_string(&episode_nfo).unwrap();
+        assert!(merged.contains("<title>Pilot</title>"));
+        assert!(merged.contains("<watched>true</watched>"));
+
+        write_nfos_blocking(&targets, NfoPolicy::Overwrite);
+        let replaced = fs::read_to_string(&episode_nfo).unwrap();
+        assert!(!replaced.contains("<watched>"));
+
+        fs::write(&episode_nfo, "not xml <").unwrap();
+        let summary = write_nfos_blocking(&targets, NfoPolicy::Merge);
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(fs::read_to_string(&episode_nfo).unwrap(), "not xml <");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_policy_from_name() {
+        assert_eq!(NfoPolicy::from_name("merge"), Some(NfoPolicy::Merge));
+        assert_eq!(NfoPolicy::from_name("OFF"), Some(NfoPolicy::Off));
+        assert_eq!(NfoPolicy::from_name("sometimes"), None);
+    }
+}
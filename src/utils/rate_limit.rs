@@ -0,0 +1,239 @@
+use reqwest::StatusCode;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Retries after a 429, 5xx or timeout before giving up
+const MAX_RETRIES: u32 = 3;
+/// Default wait when a 429 has no usable Retry-After header
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Token bucket shared by every request to an API
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl Bucket {
+    /// Take a token, or say how long to wait for the next one
+    fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(until) = self.paused_until {
+            if now < until {
+                return Err(until - now);
+            }
+            self.paused_until = None;
+        }
+
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.per_second,
+            ))
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Allow bursts of `capacity` requests, refilling `per_second` tokens a second
+    pub fn new(capacity: u32, per_second: f64) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                capacity: f64::from(capacity),
+                per_second,
+                tokens: f64::from(capacity),
+                last_refill: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Wait until a request may be sent
+    pub async fn acquire(&self) {
+        loop {
+            let wait = match self.bucket.lock() {
+                Ok(mut bucket) => match bucket.try_take(Instant::now()) {
+                    Ok(()) => return,
+                    Err(wait) => wait,
+                },
+                // A poisoned lock only means another request panicked; don't block forever
+                Err(_) => return,
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Hold every request back for `duration`, e.g. after a 429 Retry-After
+    pub fn pause_for(&self, duration: Duration) {
+        if let Ok(mut bucket) = self.bucket.lock() {
+            let until = Instant::now() + duration;
+            bucket.paused_until = Some(bucket.paused_until.map_or(until, |u| u.max(until)));
+            bucket.tokens = 0.0;
+        }
+    }
+
+    /// Send the request built by `request` once a token is free, retrying 429s
+    /// (after Retry-After) and 5xx/timeouts (with exponential backoff)
+    pub async fn send(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<(StatusCode, String), String> {
+        let response = self.send_with_retries(request).await?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+        Ok((status, body))
+    }
+
+    /// Like `send`, for binary bodies such as images
+    pub async fn send_bytes(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<(StatusCode, Vec<u8>), String> {
+        let response = self.send_with_retries(request).await?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+        Ok((status, body.to_vec()))
+    }
+
+    async fn send_with_retries(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, String> {
+        let mut attempt = 0;
+        loop {
+            self.acquire().await;
+
+            let response = match request().send().await {
+                Ok(response) => response,
+                Err(e) if (e.is_timeout() || e.is_connect()) && attempt < MAX_RETRIES => {
+                    tokio::time::sleep(backoff_delay(attempt)).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(format!("Network error: {}", e)),
+            };
+
+            let status = response.status();
+            if attempt < MAX_RETRIES {
+                if status == StatusCode::TOO_MANY_REQUESTS {
+                    let wait = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(parse_retry_after)
+                        .unwrap_or(DEFAULT_RETRY_AFTER);
+                    // Every request backs off, not just this one
+                    self.pause_for(wait);
+                    attempt += 1;
+                    continue;
+                }
+                if status.is_server_error() {
+                    tokio::time::sleep(backoff_delay(attempt)).await;
+                    attempt += 1;
+                    continue;
+                }
+            }
+
+            return Ok(response);
+        }
+    }
+}
+
+/// Delay before retry number `attempt` (0-based): 0.5s, 1s, 2s, ...
+pub fn backoff_delay(attempt: u32) -> Duration {
+    Duration::from_millis(500 * 2u64.saturating_pow(attempt.min(6)))
+}
+
+/// Parse a Retry-After header given in seconds, capped to keep the UI responsive
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    const MAX_WAIT_SECS: u64 = 60;
+    let seconds: u64 = value.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds.min(MAX_WAIT_SECS)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket(capacity: f64, per_second: f64, start: Instant) -> Bucket {
+        Bucket {
+            capacity,
+            per_second,
+            tokens: capacity,
+            last_refill: start,
+            paused_until: None,
+        }
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_then_waits() {
+        let start = Instant::now();
+        let mut bucket = bucket(3.0, 10.0, start);
+
+        assert!(bucket.try_take(start).is_ok());
+        assert!(bucket.try_take(start).is_ok());
+        assert!(bucket.try_take(start).is_ok());
+
+        let wait = bucket.try_take(start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(100));
+
+        // One token refills after 100ms
+        assert!(bucket.try_take(start + Duration::from_millis(100)).is_ok());
+    }
+
+    #[test]
+    fn test_bucket_refill_is_capped() {
+        let start = Instant::now();
+        let mut bucket = bucket(2.0, 10.0, start);
+        let later = start + Duration::from_secs(60);
+
+        assert!(bucket.try_take(later).is_ok());
+        assert!(bucket.try_take(later).is_ok());
+        assert!(bucket.try_take(later).is_err());
+    }
+
+    #[test]
+    fn test_bucket_pause() {
+        let start = Instant::now();
+        let mut bucket = bucket(5.0, 10.0, start);
+        bucket.paused_until = Some(start + Duration::from_secs(2));
+
+        assert_eq!(bucket.try_take(start).unwrap_err(), Duration::from_secs(2));
+        assert!(bucket.try_take(start + Duration::from_secs(2)).is_ok());
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles() {
+        assert_eq!(backoff_delay(0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(1), Duration::from_secs(1));
+        assert_eq!(backoff_delay(2), Duration::from_secs(2));
+        assert_eq!(backoff_delay(100), backoff_delay(6));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_retry_after(" 10 "), Some(Duration::from_secs(10)));
+        assert_eq!(parse_retry_after("3600"), Some(Duration::from_secs(60)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+}
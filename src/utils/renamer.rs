@@ -1,99 +1,239 @@
-use crate::model::{MediaFile, MediaMetadata, MediaType, RenamePattern};
-use std::path::PathBuf;
+use crate::model::{MediaFile, MediaMetadata, MediaType, ProviderKind, RenamePattern};
+use crate::utils::file_ops::{self, FileOperation};
+use crate::utils::sidecar::Sidecar;
+use crate::utils::template::{Context, Template, Value};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Placeholders available in rename patterns
+pub const PLACEHOLDERS: &[&str] = &[
+    "title",
+    "year",
+    "show",
+    "season",
+    "episode",
+    "episode_end",
+    "absolute",
+    "episode_title",
+    "quality",
+    "source",
+    "codec",
+    "audio",
+    "group",
+    "original_title",
+    "genres",
+    "tmdb_id",
+    "tvdb_id",
+    "air_date",
+    "vote",
+];
+
+/// Placeholders holding numbers, which can be zero-padded like `{season:02}`
+pub const NUMBER_PLACEHOLDERS: &[&str] = &[
+    "year",
+    "season",
+    "episode",
+    "episode_end",
+    "absolute",
+    "tmdb_id",
+    "tvdb_id",
+];
+
+/// Check that both templates of a pattern parse
+pub fn validate_pattern(pattern: &RenamePattern) -> Result<(), String> {
+    Template::parse(&pattern.movie_pattern, PLACEHOLDERS, NUMBER_PLACEHOLDERS)
+        .map_err(|e| format!("Movie pattern: {}", e))?;
+    Template::parse(&pattern.tv_pattern, PLACEHOLDERS, NUMBER_PLACEHOLDERS)
+        .map_err(|e| format!("TV pattern: {}", e))?;
+    Ok(())
+}
 
-/// Generate a new filename based on metadata and pattern
-pub fn generate_filename(
-    file: &MediaFile,
-    metadata: &MediaMetadata,
-    pattern: &RenamePattern,
-) -> String {
-    let template = match file.media_type {
+/// Field values for a file's pattern, preferring metadata over parsed info
+fn template_context(file: &MediaFile, metadata: &MediaMetadata) -> Context {
+    let parsed = file.parsed_info.as_ref();
+    let mut context = Context::new();
+
+    context.insert("title", Value::Text(metadata.title.clone()));
+    // TV show specific - use show_name from metadata or title
+    context.insert(
+        "show",
+        Value::Text(
+            metadata
+                .show_name
+                .clone()
+                .unwrap_or_else(|| metadata.title.clone()),
+        ),
+    );
+
+    let year = metadata.year.or_else(|| parsed.and_then(|p| p.year));
+    let season = metadata
+        .season_number
+        .or_else(|| parsed.and_then(|p| p.season));
+    let episode = metadata
+        .episode_number
+        .or_else(|| parsed.and_then(|p| p.episode));
+    // Only set for multi-episode files, so "[-E{episode_end:02}]" vanishes otherwise
+    let episode_end = metadata
+        .episode_end
+        .or_else(|| parsed.and_then(|p| p.episode_end))
+        .filter(|end| Some(*end) > episode);
+    let absolute = metadata
+        .absolute_episode
+        .or_else(|| parsed.and_then(|p| p.absolute_episode));
+    let numbers = [
+        ("year", year),
+        ("season", season),
+        ("episode", episode),
+        ("episode_end", episode_end),
+        ("absolute", absolute),
+    ];
+    for (name, value) in numbers {
+        if let Some(n) = value {
+            context.insert(name, Value::Number(i64::from(n)));
+        }
+    }
+
+    let episode_title = metadata
+        .episode_title
+        .clone()
+        .or_else(|| parsed.and_then(|p| p.episode_title.clone()));
+    if let Some(title) = episode_title {
+        context.insert("episode_title", Value::Text(title));
+    }
+
+    // Technical tags parsed from the original filename
+    if let Some(parsed) = parsed {
+        let tags = [
+            ("quality", &parsed.quality),
+            ("source", &parsed.source),
+            ("codec", &parsed.codec),
+            ("audio", &parsed.audio),
+            ("group", &parsed.group),
+        ];
+        for (name, value) in tags {
+            if let Some(v) = value {
+                context.insert(name, Value::Text(v.clone()));
+            }
+        }
+    }
+
+    // Extra metadata fields; missing ones are left out so optional sections drop
+    if let Some(original) = &metadata.original_title {
+        context.insert("original_title", Value::Text(original.clone()));
+    }
+    if !metadata.genres.is_empty() {
+        context.insert("genres", Value::Text(metadata.genres.join(", ")));
+    }
+    if metadata.tmdb_id != 0 {
+        context.insert("tmdb_id", Value::Number(metadata.tmdb_id as i64));
+    }
+    if metadata.provider == ProviderKind::Tvdb && metadata.provider_id != 0 {
+        context.insert("tvdb_id", Value::Number(metadata.provider_id as i64));
+    }
+    let air_date = metadata
+        .air_date
+        .as_ref()
+        .or_else(|| parsed.and_then(|p| p.air_date.as_ref()));
+    if let Some(air_date) = air_date {
+        context.insert("air_date", Value::Text(air_date.clone()));
+    }
+    if let Some(vote) = metadata.vote_average {
+        context.insert("vote", Value::Text(format!("{:.1}", vote)));
+    }
+
+    context
+}
+
+/// The template of a pattern that applies to a media type
+fn pattern_source(pattern: &RenamePattern, media_type: MediaType) -> &str {
+    match media_type {
         MediaType::Movie => &pattern.movie_pattern,
         MediaType::TvShow => &pattern.tv_pattern,
         MediaType::Unknown => &pattern.movie_pattern,
-    };
+    }
+}
 
-    let mut result = template.clone();
+/// Generate a new filename based on metadata and pattern.
+/// A "/" in the pattern produces a relative path with folders.
+pub fn generate_filename(
+    file: &MediaFile,
+    metadata: &MediaMetadata,
+    pattern: &RenamePattern,
+) -> Result<String, String> {
+    let source = pattern_source(pattern, file.media_type);
+    let template = Template::parse(source, PLACEHOLDERS, NUMBER_PLACEHOLDERS)?;
+    let rendered = template.render(&template_context(file, metadata), &sanitize_filename);
+
+    // Each "/" in the pattern starts a folder: clean up and sanitize every component
+    let components: Vec<String> = rendered
+        .split(['/', '\\'])
+        .map(clean_component)
+        .filter(|c| !c.is_empty())
+        .collect();
 
-    // Replace placeholders
-    result = result.replace("{title}", &sanitize_filename(&metadata.title));
+    // Add extension
+    Ok(format!("{}.{}", components.join("/"), file.extension))
+}
 
-    // Year - prefer metadata, fallback to parsed info
-    let year = metadata
-        .year
-        .or_else(|| file.parsed_info.as_ref().and_then(|p| p.year));
-    if let Some(y) = year {
-        result = result.replace("{year}", &y.to_string());
-    } else {
-        result = result.replace("{year}", "");
-        result = result.replace(" ()", ""); // Clean up empty parentheses
+/// The folder `pattern` gives a renamed file's movie or show to itself, e.g.
+/// "Breaking Bad" for "{show}/Season {season:02}/...". `None` when the file
+/// shares its folder with other titles, as with "Movies/{title}".
+pub fn title_dir(file: &MediaFile, new_path: &Path, pattern: &RenamePattern) -> Option<PathBuf> {
+    let source = pattern_source(pattern, file.media_type);
+    let folders: Vec<&str> = source.split('/').collect();
+    let folders = &folders[..folders.len() - 1];
+    // Empty folder names are dropped, so the path must have every folder
+    if file.new_filename.as_ref()?.matches('/').count() != folders.len() {
+        return None;
     }
 
-    // TV show specific - use show_name from metadata or title
-    if let Some(show) = &metadata.show_name {
-        result = result.replace("{show}", &sanitize_filename(show));
-    } else {
-        result = result.replace("{show}", &sanitize_filename(&metadata.title));
+    // Season folders may sit below the title's folder
+    for (depth, folder) in folders.iter().rev().enumerate() {
+        if folder.contains("{title") || folder.contains("{show") {
+            return new_path.ancestors().nth(depth + 1).map(Path::to_path_buf);
+        }
+        if !folder.contains("{season") {
+            return None;
+        }
     }
+    None
+}
 
-    // Season - prefer metadata, fallback to parsed info
-    let season = metadata
-        .season_number
-        .or_else(|| file.parsed_info.as_ref().and_then(|p| p.season));
-    if let Some(s) = season {
-        result = result.replace("{season:02}", &format!("{:02}", s));
-        result = result.replace("{season}", &s.to_string());
-    } else {
-        // Remove unreplaced placeholders
-        result = result.replace("{season:02}", "");
-        result = result.replace("{season}", "");
-    }
+/// Filename a file would get under `pattern`, using its parsed info when it
+/// hasn't been matched yet. Used for live pattern previews.
+pub fn preview_filename(file: &MediaFile, pattern: &RenamePattern) -> Result<String, String> {
+    let metadata = file
+        .matched_metadata
+        .clone()
+        .unwrap_or_else(|| MediaMetadata {
+            title: file
+                .parsed_info
+                .as_ref()
+                .map(|p| p.title.clone())
+                .unwrap_or_default(),
+            ..Default::default()
+        });
+    generate_filename(file, &metadata, pattern)
+}
 
-    // Episode - prefer metadata, fallback to parsed info
-    let episode = metadata
-        .episode_number
-        .or_else(|| file.parsed_info.as_ref().and_then(|p| p.episode));
-    if let Some(e) = episode {
-        result = result.replace("{episode:02}", &format!("{:02}", e));
-        result = result.replace("{episode}", &e.to_string());
-    } else {
-        result = result.replace("{episode:02}", "");
-        result = result.replace("{episode}", "");
-    }
-
-    // Episode title - prefer metadata, fallback to parsed info
-    let ep_title = metadata.episode_title.clone().or_else(|| {
-        file.parsed_info
-            .as_ref()
-            .and_then(|p| p.episode_title.clone())
-    });
-    if let Some(title) = ep_title {
-        result = result.replace("{episode_title}", &sanitize_filename(&title));
-    } else {
-        result = result.replace("{episode_title}", "");
-    }
-
-    // Clean up the result
-    result = result.trim().to_string();
-
-    // Clean up orphan separators and dashes
-    result = result.replace(" - .", "."); // Remove trailing " - " before extension
-    result = result.replace(" -.", ".");
-    result = result.replace("  - ", " ");
-    result = result.replace(" -  ", " ");
-
-    // Remove duplicate spaces
-    while result.contains("  ") {
-        result = result.replace("  ", " ");
-    }
+/// Clean up one folder or file name component of a generated path
+fn clean_component(component: &str) -> String {
+    // Literal text from the pattern may still contain invalid characters
+    let mut result = sanitize_filename(component);
 
-    // Remove trailing dash or hyphen before extension
-    if result.ends_with(" -") {
-        result = result[..result.len() - 2].to_string();
+    // Fields left empty outside optional sections leave their separators behind
+    result = result.replace(" ()", "").replace(" []", "");
+    while result.contains(" - - ") {
+        result = result.replace(" - - ", " - ");
     }
+    let result = result
+        .trim_start_matches("- ")
+        .trim_end_matches(" -")
+        .trim();
 
-    // Add extension
-    format!("{}.{}", result.trim(), file.extension)
+    // Trailing dots are invalid on Windows and "." / ".." would escape the folder
+    result.trim_end_matches(['.', ' ']).to_string()
 }
 
 /// Sanitize a string for use in a filename
@@ -113,70 +253,437 @@ fn sanitize_filename(s: &str) -> String {
     result.trim().to_string()
 }
 
-/// Execute the rename operation
-pub async fn rename_files(
-    files: Vec<(PathBuf, String)>, // (old_path, new_filename)
-    output_dir: Option<PathBuf>,
-) -> Result<Vec<(String, String)>, String> {
-    let mut results = Vec::new();
+/// What happened to a single file in a rename batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenameStatus {
+    /// Placed at the new path (moved, copied or linked)
+    Renamed,
+    /// Already had the target name
+    Unchanged,
+    /// Could not be renamed
+    Failed(String),
+    /// Was renamed, then moved back because another file in the batch failed
+    RolledBack,
+    /// Was renamed but could not be moved back during rollback
+    RollbackFailed(String),
+    /// Never attempted because the batch was aborted
+    Skipped,
+}
+
+/// Per-file result of a rename batch
+#[derive(Debug, Clone)]
+pub struct RenameOutcome {
+    pub old_path: PathBuf,
+    pub new_path: PathBuf,
+    pub status: RenameStatus,
+    /// Index of the video outcome this sidecar follows
+    pub sidecar_of: Option<usize>,
+}
+
+impl RenameOutcome {
+    /// True if the file now lives at `new_path`
+    pub fn is_moved(&self) -> bool {
+        matches!(
+            self.status,
+            RenameStatus::Renamed | RenameStatus::RollbackFailed(_)
+        )
+    }
+}
+
+/// Per-file outcomes of a rename batch
+#[derive(Debug, Clone, Default)]
+pub struct RenameReport {
+    pub outcomes: Vec<RenameOutcome>,
+    /// True if the batch was aborted and completed renames were undone
+    pub rolled_back: bool,
+    /// How files were placed at their new paths
+    pub operation: FileOperation,
+}
+
+impl RenameReport {
+    /// (old_path, new_path) for every file that was actually moved
+    pub fn renamed(&self) -> Vec<(PathBuf, PathBuf)> {
+        self.outcomes
+            .iter()
+            .filter(|o| o.is_moved())
+            .map(|o| (o.old_path.clone(), o.new_path.clone()))
+            .collect()
+    }
+
+    /// How many videos were moved, e.g. "2 file(s)", with the sidecars that
+    /// followed them counted separately
+    pub fn moved_summary(&self) -> String {
+        let moved = self.outcomes.iter().filter(|o| o.is_moved());
+        let (sidecars, videos): (Vec<_>, Vec<_>) = moved.partition(|o| o.sidecar_of.is_some());
+        match sidecars.len() {
+            0 => format!("{} file(s)", videos.len()),
+            n => format!("{} file(s) and {} sidecar file(s)", videos.len(), n),
+        }
+    }
+
+    /// Outcomes that ended in an error
+    pub fn failures(&self) -> Vec<&RenameOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|o| {
+                matches!(
+                    o.status,
+                    RenameStatus::Failed(_) | RenameStatus::RollbackFailed(_)
+                )
+            })
+            .collect()
+    }
+
+    pub fn first_error(&self) -> Option<String> {
+        self.failures().first().map(|o| match &o.status {
+            RenameStatus::Failed(e) | RenameStatus::RollbackFailed(e) => e.clone(),
+            _ => String::new(),
+        })
+    }
+}
+
+/// Copy progress of a file being moved to another filesystem
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenameProgress {
+    pub file_index: usize,
+    pub file_count: usize,
+    pub filename: String,
+    pub bytes_copied: u64,
+    pub total_bytes: u64,
+}
+
+impl RenameProgress {
+    /// Fraction of the current file copied, from 0.0 to 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            1.0
+        } else {
+            self.bytes_copied as f32 / self.total_bytes as f32
+        }
+    }
+}
+
+/// A file to rename, with the sidecars that follow its new name
+#[derive(Debug, Clone)]
+pub struct RenameEntry {
+    pub old_path: PathBuf,
+    pub new_filename: String,
+    pub sidecars: Vec<Sidecar>,
+}
+
+impl From<(PathBuf, String)> for RenameEntry {
+    fn from((old_path, new_filename): (PathBuf, String)) -> Self {
+        Self {
+            old_path,
+            new_filename,
+            sidecars: Vec::new(),
+        }
+    }
+}
+
+impl RenameEntry {
+    /// The file's pending rename, if it has a new filename
+    pub fn for_file(file: &MediaFile) -> Option<Self> {
+        Some(Self {
+            old_path: file.path.clone(),
+            new_filename: file.new_filename.clone()?,
+            sidecars: file.sidecars.clone(),
+        })
+    }
+}
+
+/// Options for a rename batch
+#[derive(Debug, Clone, Default)]
+pub struct RenameOptions {
+    pub output_dir: Option<PathBuf>,
+    /// Check every destination first and undo completed renames if any file fails
+    pub all_or_nothing: bool,
+    /// Move, copy or link files to their new paths
+    pub operation: FileOperation,
+    /// Receives copy progress when a file has to be copied across filesystems
+    pub progress: Option<UnboundedSender<RenameProgress>>,
+}
+
+/// Resolve the destination path of a file. `new_filename` may contain "/"-separated
+/// folders, which are created under the output directory (or the source's folder).
+fn target_path(old_path: &Path, new_filename: &str, output_dir: Option<&Path>) -> PathBuf {
+    let base = match output_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => old_path.parent().unwrap_or(old_path).to_path_buf(),
+    };
+    new_filename
+        .split('/')
+        .filter(|c| !c.is_empty())
+        .fold(base, |path, component| path.join(component))
+}
+
+/// Problems that can be detected before touching the disk
+fn precheck(outcomes: &[RenameOutcome]) -> Vec<Option<String>> {
+    let mut seen = HashSet::new();
+    outcomes
+        .iter()
+        .map(|o| {
+            if o.status == RenameStatus::Unchanged {
+                return None;
+            }
+            if !o.old_path.exists() {
+                return Some(format!("Source no longer exists: {}", o.old_path.display()));
+            }
+            if o.new_path.exists() {
+                return Some(format!(
+                    "Destination already exists: {}",
+                    o.new_path.display()
+                ));
+            }
+            if !seen.insert(o.new_path.clone()) {
+                return Some(format!(
+                    "Several files would be renamed to {}",
+                    o.new_path.display()
+                ));
+            }
+            None
+        })
+        .collect()
+}
 
-    for (old_path, new_filename) in files {
-        let new_path = if let Some(ref dir) = output_dir {
-            dir.join(&new_filename)
+/// Move, copy or link a single file, creating the destination directory if needed.
+/// Newly created directories are appended to `created_dirs`.
+fn place_file(
+    operation: FileOperation,
+    old_path: &Path,
+    new_path: &Path,
+    created_dirs: &mut Vec<PathBuf>,
+    on_progress: &mut dyn FnMut(u64, u64),
+) -> Result<(), String> {
+    // Check if destination exists
+    if new_path.exists() {
+        return Err(format!(
+            "Destination already exists: {}",
+            new_path.display()
+        ));
+    }
+
+    // Create output directory (and any pattern folders) if needed
+    if let Some(parent) = new_path.parent() {
+        if !parent.exists() {
+            let mut missing: Vec<PathBuf> = parent
+                .ancestors()
+                .take_while(|dir| !dir.exists())
+                .map(Path::to_path_buf)
+                .collect();
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+            // Outermost first, so rollback removes the deepest folders first
+            missing.reverse();
+            created_dirs.extend(missing);
+        }
+    }
+
+    // Rename/move the file (copying across filesystems if needed), or copy/link it
+    file_ops::apply(operation, old_path, new_path, on_progress)
+}
+
+/// Move every renamed file back to where it came from, or remove the copies/links created
+fn rollback(operation: FileOperation, outcomes: &mut [RenameOutcome], created_dirs: &[PathBuf]) {
+    for outcome in outcomes.iter_mut().rev() {
+        if outcome.status != RenameStatus::Renamed {
+            continue;
+        }
+        let result = if operation.keeps_source() {
+            std::fs::remove_file(&outcome.new_path).map_err(|e| e.to_string())
         } else {
-            old_path.parent().unwrap_or(&old_path).join(&new_filename)
+            file_ops::move_file(&outcome.new_path, &outcome.old_path, &mut |_, _| {})
+        };
+        outcome.status = match result {
+            Ok(()) => RenameStatus::RolledBack,
+            Err(e) => RenameStatus::RollbackFailed(format!(
+                "Failed to roll back {}: {}",
+                outcome.new_path.display(),
+                e
+            )),
         };
+    }
 
+    // Remove directories created for this batch if they ended up empty
+    for dir in created_dirs.iter().rev() {
+        let _ = std::fs::remove_dir(dir);
+    }
+}
+
+/// Execute the rename operation, reporting the outcome of every file
+pub async fn rename_files<E: Into<RenameEntry>>(
+    files: Vec<E>,
+    options: RenameOptions,
+) -> RenameReport {
+    let files: Vec<RenameEntry> = files.into_iter().map(Into::into).collect();
+    let paths: Vec<PathBuf> = files.iter().map(|f| f.old_path.clone()).collect();
+    let operation = options.operation;
+    // Cross-filesystem moves copy whole files, so keep them off the async runtime
+    match tokio::task::spawn_blocking(move || rename_files_blocking(files, options)).await {
+        Ok(report) => report,
+        Err(e) => RenameReport {
+            outcomes: paths
+                .into_iter()
+                .map(|path| RenameOutcome {
+                    new_path: path.clone(),
+                    old_path: path,
+                    status: RenameStatus::Failed(format!("Task error: {}", e)),
+                    sidecar_of: None,
+                })
+                .collect(),
+            rolled_back: false,
+            operation,
+        },
+    }
+}
+
+fn rename_files_blocking(files: Vec<RenameEntry>, options: RenameOptions) -> RenameReport {
+    let output_dir = options.output_dir.as_deref();
+    // Sidecars land next to their video, even when they came from a "Subs" folder
+    let outcome = |old_path: PathBuf, video_path: &Path, new_filename: &str, sidecar_of| {
+        let new_path = target_path(video_path, new_filename, output_dir);
         // Skip if same path
-        if old_path == new_path {
-            continue;
+        let status = if old_path == new_path {
+            RenameStatus::Unchanged
+        } else {
+            RenameStatus::Skipped
+        };
+        RenameOutcome {
+            old_path,
+            new_path,
+            status,
+            sidecar_of,
         }
+    };
 
-        // Check if destination exists
-        if new_path.exists() {
-            return Err(format!(
-                "Destination already exists: {}",
-                new_path.display()
+    // Each video is followed by its sidecars
+    let mut outcomes: Vec<RenameOutcome> = Vec::new();
+    for entry in files {
+        let video = outcomes.len();
+        outcomes.push(outcome(
+            entry.old_path.clone(),
+            &entry.old_path,
+            &entry.new_filename,
+            None,
+        ));
+        for sidecar in entry.sidecars {
+            let new_filename = sidecar.target_name(&entry.new_filename);
+            outcomes.push(outcome(
+                sidecar.path,
+                &entry.old_path,
+                &new_filename,
+                Some(video),
             ));
         }
+    }
+
+    // In all-or-nothing mode, refuse the whole batch if any destination is unusable
+    if options.all_or_nothing {
+        let problems = precheck(&outcomes);
+        if problems.iter().any(|p| p.is_some()) {
+            for (outcome, problem) in outcomes.iter_mut().zip(problems) {
+                if let Some(reason) = problem {
+                    outcome.status = RenameStatus::Failed(reason);
+                }
+            }
+            return RenameReport {
+                outcomes,
+                rolled_back: true,
+                operation: options.operation,
+            };
+        }
+    }
 
-        // Create output directory if needed
-        if let Some(parent) = new_path.parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create directory: {}", e))?;
+    let mut created_dirs = Vec::new();
+    let file_count = outcomes.len();
+    for i in 0..file_count {
+        if outcomes[i].status == RenameStatus::Unchanged {
+            continue;
+        }
+        // Sidecars only follow a video that made it to its new name
+        if let Some(video) = outcomes[i].sidecar_of {
+            if !matches!(
+                outcomes[video].status,
+                RenameStatus::Renamed | RenameStatus::Unchanged
+            ) {
+                continue;
             }
         }
 
-        // Rename/move the file
-        std::fs::rename(&old_path, &new_path)
-            .map_err(|e| format!("Failed to rename {}: {}", old_path.display(), e))?;
+        let outcome = &mut outcomes[i];
+        let filename = outcome
+            .old_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut last_percent = None;
+        let mut on_progress = |bytes_copied: u64, total_bytes: u64| {
+            let Some(sender) = &options.progress else {
+                return;
+            };
+            // Only send whole-percent steps to keep the GUI responsive
+            let percent = (bytes_copied * 100).checked_div(total_bytes).unwrap_or(100);
+            if last_percent == Some(percent) {
+                return;
+            }
+            last_percent = Some(percent);
+            let _ = sender.unbounded_send(RenameProgress {
+                file_index: i,
+                file_count,
+                filename: filename.clone(),
+                bytes_copied,
+                total_bytes,
+            });
+        };
 
-        results.push((
-            old_path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            new_filename,
-        ));
+        match place_file(
+            options.operation,
+            &outcome.old_path,
+            &outcome.new_path,
+            &mut created_dirs,
+            &mut on_progress,
+        ) {
+            Ok(()) => outcome.status = RenameStatus::Renamed,
+            Err(e) => {
+                outcome.status = RenameStatus::Failed(e);
+                if options.all_or_nothing {
+                    rollback(options.operation, &mut outcomes, &created_dirs);
+                    return RenameReport {
+                        outcomes,
+                        rolled_back: true,
+                        operation: options.operation,
+                    };
+                }
+            }
+        }
     }
 
-    Ok(results)
+    RenameReport {
+        outcomes,
+        rolled_back: false,
+        operation: options.operation,
+    }
 }
 
-/// Generate rename preview
+/// Generate rename preview, each video followed by its sidecars
 pub fn generate_preview(files: &[MediaFile], pattern: &RenamePattern) -> Vec<(String, String)> {
     files
         .iter()
         .filter_map(|f| {
-            if let Some(metadata) = &f.matched_metadata {
-                let new_name = generate_filename(f, metadata, pattern);
-                Some((f.filename.clone(), new_name))
-            } else {
-                None
-            }
+            let metadata = f.matched_metadata.as_ref()?;
+            let new_name = generate_filename(f, metadata, pattern).ok()?;
+            let sidecars = f.sidecars.iter().map(|s| {
+                let old_name = s
+                    .path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                (old_name, s.target_name(&new_name))
+            });
+            let video = (f.filename.clone(), new_name.clone());
+            Some(std::iter::once(video).chain(sidecars).collect::<Vec<_>>())
         })
+        .flatten()
         .collect()
 }
 
@@ -184,6 +691,8 @@ pub fn generate_preview(files: &[MediaFile], pattern: &RenamePattern) -> Vec<(St
 mod tests {
     use super::*;
     use crate::model::ParsedMediaInfo;
+    use crate::utils::sidecar::Sidecar;
+    use crate::utils::test_dir::temp_dir;
 
     // ==================== SANITIZE FILENAME TESTS ====================
 
@@ -264,12 +773,16 @@ mod tests {
             matched_metadata: None,
             new_filename: None,
             is_selected: false,
+            match_candidates: Vec::new(),
+            sidecars: Vec::new(),
         }
     }
 
     fn create_movie_metadata() -> MediaMetadata {
         MediaMetadata {
             tmdb_id: 603,
+            provider: ProviderKind::Tmdb,
+            provider_id: 603,
             title: "The Matrix".to_string(),
             original_title: Some("The Matrix".to_string()),
             year: Some(1999),
@@ -278,10 +791,13 @@ mod tests {
             ),
             poster_path: None,
             backdrop_path: None,
+            still_path: None,
             vote_average: Some(8.7),
             genres: vec!["Action".to_string(), "Sci-Fi".to_string()],
             season_number: None,
             episode_number: None,
+            episode_end: None,
+            absolute_episode: None,
             episode_title: None,
             air_date: None,
             show_name: None,
@@ -294,7 +810,7 @@ mod tests {
         let metadata = create_movie_metadata();
         let pattern = RenamePattern::default();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         assert_eq!(result, "The Matrix (1999).mkv");
     }
 
@@ -304,7 +820,7 @@ mod tests {
         let metadata = create_movie_metadata();
         let pattern = RenamePattern::plex();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         assert_eq!(result, "The Matrix (1999).mkv");
     }
 
@@ -319,7 +835,7 @@ mod tests {
         metadata.year = None;
         let pattern = RenamePattern::default();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         assert_eq!(result, "The Matrix.mkv");
     }
 
@@ -330,7 +846,7 @@ mod tests {
         metadata.title = "Test: The Movie?".to_string();
         let pattern = RenamePattern::default();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         assert!(!result.contains(':'));
         assert!(!result.contains('?'));
     }
@@ -354,12 +870,16 @@ mod tests {
             matched_metadata: None,
             new_filename: None,
             is_selected: false,
+            match_candidates: Vec::new(),
+            sidecars: Vec::new(),
         }
     }
 
     fn create_tv_metadata() -> MediaMetadata {
         MediaMetadata {
             tmdb_id: 1396,
+            provider: ProviderKind::Tmdb,
+            provider_id: 1396,
             title: "Pilot".to_string(),
             original_title: None,
             year: Some(2008),
@@ -368,10 +888,13 @@ mod tests {
             ),
             poster_path: None,
             backdrop_path: None,
+            still_path: None,
             vote_average: Some(9.5),
             genres: vec!["Drama".to_string(), "Crime".to_string()],
             season_number: Some(1),
             episode_number: Some(1),
+            episode_end: None,
+            absolute_episode: None,
             episode_title: Some("Pilot".to_string()),
             air_date: Some("2008-01-20".to_string()),
             show_name: Some("Breaking Bad".to_string()),
@@ -384,7 +907,7 @@ mod tests {
         let metadata = create_tv_metadata();
         let pattern = RenamePattern::default();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         assert_eq!(result, "Breaking Bad - S01E01 - Pilot.mkv");
     }
 
@@ -394,7 +917,7 @@ mod tests {
         let metadata = create_tv_metadata();
         let pattern = RenamePattern::plex();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         assert_eq!(result, "Breaking Bad - s01e01 - Pilot.mkv");
     }
 
@@ -404,7 +927,7 @@ mod tests {
         let metadata = create_tv_metadata();
         let pattern = RenamePattern::jellyfin();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         assert_eq!(result, "Breaking Bad S01E01 Pilot.mkv");
     }
 
@@ -419,7 +942,7 @@ mod tests {
         metadata.episode_title = Some("Episode Ten".to_string());
         let pattern = RenamePattern::default();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         assert!(result.contains("E10"));
     }
 
@@ -430,12 +953,76 @@ mod tests {
         metadata.episode_title = None;
         let pattern = RenamePattern::default();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         // Should not have dangling " - " at the end
         assert!(!result.ends_with(" -.mkv"));
         assert!(!result.contains(" - .mkv"));
     }
 
+    #[test]
+    fn test_generate_tv_filename_multi_episode() {
+        let mut file = create_tv_file("Breaking.Bad.S01E01E02.mkv");
+        if let Some(ref mut parsed) = file.parsed_info {
+            parsed.episode_end = Some(2);
+        }
+        let mut metadata = create_tv_metadata();
+        metadata.episode_end = Some(2);
+        metadata.episode_title = Some("Pilot & Cat's in the Bag".to_string());
+
+        let result = generate_filename(&file, &metadata, &RenamePattern::default()).unwrap();
+        assert_eq!(
+            result,
+            "Breaking Bad - S01E01-E02 - Pilot & Cat's in the Bag.mkv"
+        );
+
+        let result = generate_filename(&file, &metadata, &RenamePattern::plex()).unwrap();
+        assert!(result.contains("s01e01-e02"));
+    }
+
+    #[test]
+    fn test_generate_tv_filename_absolute_episode() {
+        let file = create_tv_file("[Group] Breaking Bad - 07 [1080p].mkv");
+        let mut metadata = create_tv_metadata();
+        metadata.absolute_episode = Some(7);
+        let pattern = RenamePattern {
+            name: "Anime".to_string(),
+            movie_pattern: "{title}".to_string(),
+            tv_pattern: "{show} - {absolute:03} (S{season:02}E{episode:02})".to_string(),
+            language: None,
+        };
+
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
+        assert_eq!(result, "Breaking Bad - 007 (S01E01).mkv");
+
+        // Without an absolute number an optional section is dropped
+        metadata.absolute_episode = None;
+        let pattern = RenamePattern {
+            tv_pattern: "{show}[ - {absolute}]".to_string(),
+            ..pattern
+        };
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
+        assert_eq!(result, "Breaking Bad.mkv");
+    }
+
+    #[test]
+    fn test_generate_tv_filename_air_date_from_parsed_info() {
+        let mut file = create_tv_file("The.Daily.Show.2024.03.15.mkv");
+        if let Some(ref mut parsed) = file.parsed_info {
+            parsed.air_date = Some("2024-03-15".to_string());
+        }
+        let mut metadata = create_tv_metadata();
+        metadata.air_date = None;
+        let pattern = RenamePattern {
+            name: "Daily".to_string(),
+            movie_pattern: "{title}".to_string(),
+            tv_pattern: "{show} - {air_date}".to_string(),
+            language: None,
+        };
+
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
+        assert_eq!(result, "Breaking Bad - 2024-03-15.mkv");
+    }
+
     #[test]
     fn test_generate_tv_filename_fallback_to_parsed_info() {
         let file = create_tv_file("Show.S05E12.mkv");
@@ -445,12 +1032,237 @@ mod tests {
         // Should fallback to parsed_info values
         let pattern = RenamePattern::default();
 
-        let result = generate_filename(&file, &metadata, &pattern);
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
         // Check it uses some season/episode info
         assert!(result.contains('S') || result.contains('s'));
         assert!(result.contains('E') || result.contains('e'));
     }
 
+    // ==================== GENERATE FILENAME - FOLDER TESTS ====================
+
+    fn folder_pattern() -> RenamePattern {
+        RenamePattern {
+            name: "Folders".to_string(),
+            movie_pattern: "Movies/{title} ({year})/{title} ({year})".to_string(),
+            tv_pattern: "TV/{show}/Season {season:02}/{show} - S{season:02}E{episode:02}"
+                .to_string(),
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_generate_movie_filename_with_folders() {
+        let file = create_movie_file("The.Matrix.1999.mkv");
+        let result = generate_filename(&file, &create_movie_metadata(), &folder_pattern()).unwrap();
+        assert_eq!(result, "Movies/The Matrix (1999)/The Matrix (1999).mkv");
+    }
+
+    #[test]
+    fn test_generate_tv_filename_with_folders() {
+        let file = create_tv_file("Breaking.Bad.S01E01.mkv");
+        let result = generate_filename(&file, &create_tv_metadata(), &folder_pattern()).unwrap();
+        assert_eq!(
+            result,
+            "TV/Breaking Bad/Season 01/Breaking Bad - S01E01.mkv"
+        );
+    }
+
+    #[test]
+    fn test_generate_filename_sanitizes_each_component() {
+        let file = create_movie_file("test.mkv");
+        let mut metadata = create_movie_metadata();
+        metadata.title = "AC/DC: Live..".to_string();
+        let pattern = RenamePattern {
+            name: "Folders".to_string(),
+            movie_pattern: "../{title}/{title}".to_string(),
+            tv_pattern: String::new(),
+            language: None,
+        };
+
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
+        // Slashes in metadata never create folders, and ".." can't escape the output dir
+        assert_eq!(result, "ACDC Live/ACDC Live.mkv");
+    }
+
+    #[test]
+    fn test_generate_filename_cleans_up_separators_of_empty_fields() {
+        let mut file = create_tv_file("Show.S01E01.mkv");
+        if let Some(ref mut parsed) = file.parsed_info {
+            parsed.episode_title = None;
+        }
+        let mut metadata = create_tv_metadata();
+        metadata.episode_title = None;
+        metadata.air_date = None;
+        let pattern = RenamePattern {
+            name: "Plain".to_string(),
+            movie_pattern: "{title} ({year})".to_string(),
+            tv_pattern: "{show} - {air_date} - S{season:02}E{episode:02} - {episode_title}"
+                .to_string(),
+            language: None,
+        };
+
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
+        assert_eq!(result, "Breaking Bad - S01E01.mkv");
+
+        let mut movie = create_movie_file("Movie.mkv");
+        movie.parsed_info = None;
+        let mut metadata = create_movie_metadata();
+        metadata.year = None;
+        let result = generate_filename(&movie, &metadata, &pattern).unwrap();
+        assert_eq!(result, "The Matrix.mkv");
+    }
+
+    #[test]
+    fn test_title_dir_follows_pattern_folders() {
+        let pattern = |movie: &str, tv: &str| RenamePattern {
+            name: "Folders".to_string(),
+            movie_pattern: movie.to_string(),
+            tv_pattern: tv.to_string(),
+            language: None,
+        };
+        let title_dir = |mut file: MediaFile, pattern: &RenamePattern| {
+            let metadata = file.matched_metadata.clone().unwrap();
+            let new_filename = generate_filename(&file, &metadata, pattern).unwrap();
+            let new_path = Path::new("/lib").join(&new_filename);
+            file.new_filename = Some(new_filename);
+            title_dir(&file, &new_path, pattern)
+        };
+        let mut movie = create_movie_file("The.Matrix.1999.mkv");
+        movie.matched_metadata = Some(create_movie_metadata());
+        let mut episode = create_tv_file("Breaking.Bad.S01E01.mkv");
+        episode.matched_metadata = Some(create_tv_metadata());
+
+        let own = pattern(
+            "{title} ({year})/{title} ({year})",
+            "{show}/Season {season:02}/{show} - S{season:02}E{episode:02}",
+        );
+        assert_eq!(
+            title_dir(movie.clone(), &own),
+            Some(PathBuf::from("/lib/The Matrix (1999)"))
+        );
+        assert_eq!(
+            title_dir(episode.clone(), &own),
+            Some(PathBuf::from("/lib/Breaking Bad"))
+        );
+
+        // Every movie in "Movies" and every episode in "TV" share the folder
+        let shared = pattern(
+            "Movies/{title} ({year})",
+            "TV/{show} - S{season:02}E{episode:02}",
+        );
+        assert_eq!(title_dir(movie.clone(), &shared), None);
+        assert_eq!(title_dir(episode.clone(), &shared), None);
+        let flat = pattern("{title} ({year})", "{show} - S{season:02}E{episode:02}");
+        assert_eq!(title_dir(movie, &flat), None);
+    }
+
+    #[test]
+    fn test_generate_filename_technical_tags() {
+        let mut file = create_movie_file("The.Matrix.1999.1080p.BluRay.x264.DTS-GROUP.mkv");
+        if let Some(ref mut parsed) = file.parsed_info {
+            parsed.quality = Some("1080p".to_string());
+            parsed.source = Some("BluRay".to_string());
+            parsed.codec = Some("x264".to_string());
+            parsed.audio = Some("DTS".to_string());
+            parsed.group = Some("GROUP".to_string());
+        }
+        let pattern = RenamePattern {
+            name: "Archive".to_string(),
+            movie_pattern: r"{title} ({year}) \[{quality} {source} {codec} {audio}\]-{group}"
+                .to_string(),
+            tv_pattern: String::new(),
+            language: None,
+        };
+
+        let result = generate_filename(&file, &create_movie_metadata(), &pattern).unwrap();
+        assert_eq!(
+            result,
+            "The Matrix (1999) [1080p BluRay x264 DTS]-GROUP.mkv"
+        );
+    }
+
+    #[test]
+    fn test_generate_filename_missing_tags_in_optional_sections() {
+        let file = create_movie_file("The.Matrix.1999.mkv");
+        let pattern = RenamePattern {
+            name: "Archive".to_string(),
+            movie_pattern: r"{title}[ {quality}][-{group}] \{tmdb-{tmdb_id}\}".to_string(),
+            tv_pattern: String::new(),
+            language: None,
+        };
+
+        let result = generate_filename(&file, &create_movie_metadata(), &pattern).unwrap();
+        assert_eq!(result, "The Matrix {tmdb-603}.mkv");
+    }
+
+    #[test]
+    fn test_generate_filename_metadata_fields() {
+        let file = create_tv_file("Breaking.Bad.S01E01.mkv");
+        let pattern = RenamePattern {
+            name: "Metadata".to_string(),
+            movie_pattern: String::new(),
+            tv_pattern: "{show} {air_date} {genres} {vote}[ {original_title}]".to_string(),
+            language: None,
+        };
+
+        let result = generate_filename(&file, &create_tv_metadata(), &pattern).unwrap();
+        assert_eq!(result, "Breaking Bad 2008-01-20 Drama, Crime 9.5.mkv");
+    }
+
+    #[test]
+    fn test_generate_filename_localized_or_original_title() {
+        let file = create_movie_file("Spirited.Away.2001.mkv");
+        let metadata = MediaMetadata {
+            title: "Chihiros Reise ins Zauberland".to_string(),
+            original_title: Some("千と千尋の神隠し".to_string()),
+            year: Some(2001),
+            ..Default::default()
+        };
+        let pattern = |movie_pattern: &str| RenamePattern {
+            name: "Titles".to_string(),
+            movie_pattern: movie_pattern.to_string(),
+            tv_pattern: String::new(),
+            language: Some("de-DE".to_string()),
+        };
+
+        let localized = generate_filename(&file, &metadata, &pattern("{title} ({year})")).unwrap();
+        assert_eq!(localized, "Chihiros Reise ins Zauberland (2001).mkv");
+        let original =
+            generate_filename(&file, &metadata, &pattern("{original_title} ({year})")).unwrap();
+        assert_eq!(original, "千と千尋の神隠し (2001).mkv");
+    }
+
+    #[test]
+    fn test_generate_filename_provider_ids() {
+        let file = create_tv_file("Breaking.Bad.S01E01.mkv");
+        let pattern = RenamePattern {
+            name: "Ids".to_string(),
+            movie_pattern: String::new(),
+            tv_pattern: r"{show}[ \{tmdb-{tmdb_id}\}][ \{tvdb-{tvdb_id}\}]".to_string(),
+            language: None,
+        };
+
+        // TMDB metadata has no TheTVDB ID
+        let result = generate_filename(&file, &create_tv_metadata(), &pattern).unwrap();
+        assert_eq!(result, "Breaking Bad {tmdb-1396}.mkv");
+
+        let metadata = MediaMetadata {
+            tmdb_id: 0,
+            provider: ProviderKind::Tvdb,
+            provider_id: 81189,
+            ..create_tv_metadata()
+        };
+        let result = generate_filename(&file, &metadata, &pattern).unwrap();
+        assert_eq!(result, "Breaking Bad {tvdb-81189}.mkv");
+    }
+
+    #[test]
+    fn test_preview_filename_uses_parsed_info_when_unmatched() {
+        let file = create_movie_file("The.Matrix.1999.mkv");
+        let result = preview_filename(&file, &RenamePattern::default()).unwrap();
+        assert_eq!(result, "The Matrix (1999).mkv");
+    }
+
     // ==================== GENERATE PREVIEW TESTS ====================
 
     #[test]
@@ -493,8 +1305,355 @@ mod tests {
         assert_eq!(preview.len(), 2); // Only matched files
     }
 
+    #[test]
+    fn test_generate_preview_lists_sidecars() {
+        let mut file = create_movie_file("The.Matrix.1999.mkv");
+        file.matched_metadata = Some(create_movie_metadata());
+        file.sidecars = vec![Sidecar {
+            path: PathBuf::from("/test/The.Matrix.1999.en.forced.srt"),
+            suffix: ".en.forced.srt".to_string(),
+        }];
+
+        let preview = generate_preview(&[file], &RenamePattern::default());
+        assert_eq!(
+            preview[1],
+            (
+                "The.Matrix.1999.en.forced.srt".to_string(),
+                "The Matrix (1999).en.forced.srt".to_string()
+            )
+        );
+    }
+
+    // ==================== RENAME FILES TESTS ====================
+
+    fn run_rename(files: Vec<(PathBuf, String)>, all_or_nothing: bool) -> RenameReport {
+        run_with_operation(files, all_or_nothing, FileOperation::Move)
+    }
+
+    fn run_with_operation(
+        files: Vec<(PathBuf, String)>,
+        all_or_nothing: bool,
+        operation: FileOperation,
+    ) -> RenameReport {
+        let options = RenameOptions {
+            all_or_nothing,
+            operation,
+            ..Default::default()
+        };
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(rename_files(files, options))
+    }
+
+    #[test]
+    fn test_rename_files_reports_each_file() {
+        let dir = temp_dir("renamer", "report");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+        std::fs::write(dir.join("b.mkv"), b"b").unwrap();
+
+        let report = run_rename(
+            vec![
+                (dir.join("a.mkv"), "A (2020).mkv".to_string()),
+                (dir.join("b.mkv"), "b.mkv".to_string()),
+            ],
+            false,
+        );
+
+        assert_eq!(report.outcomes[0].status, RenameStatus::Renamed);
+        assert_eq!(report.outcomes[1].status, RenameStatus::Unchanged);
+        assert_eq!(report.renamed().len(), 1);
+        assert!(dir.join("A (2020).mkv").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_best_effort_continues_after_failure() {
+        let dir = temp_dir("renamer", "best_effort");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+        std::fs::write(dir.join("b.mkv"), b"b").unwrap();
+        std::fs::write(dir.join("taken.mkv"), b"x").unwrap();
+
+        let report = run_rename(
+            vec![
+                (dir.join("a.mkv"), "taken.mkv".to_string()),
+                (dir.join("b.mkv"), "B.mkv".to_string()),
+            ],
+            false,
+        );
+
+        assert!(matches!(report.outcomes[0].status, RenameStatus::Failed(_)));
+        assert_eq!(report.outcomes[1].status, RenameStatus::Renamed);
+        assert!(!report.rolled_back);
+        assert_eq!(report.failures().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_all_or_nothing_precheck_touches_nothing() {
+        let dir = temp_dir("renamer", "precheck");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+        std::fs::write(dir.join("b.mkv"), b"b").unwrap();
+        std::fs::write(dir.join("taken.mkv"), b"x").unwrap();
+
+        let report = run_rename(
+            vec![
+                (dir.join("a.mkv"), "A.mkv".to_string()),
+                (dir.join("b.mkv"), "taken.mkv".to_string()),
+            ],
+            true,
+        );
+
+        assert!(report.rolled_back);
+        assert_eq!(report.outcomes[0].status, RenameStatus::Skipped);
+        assert!(matches!(report.outcomes[1].status, RenameStatus::Failed(_)));
+        assert!(dir.join("a.mkv").exists());
+        assert!(!dir.join("A.mkv").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_all_or_nothing_detects_duplicate_targets() {
+        let dir = temp_dir("renamer", "duplicates");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+        std::fs::write(dir.join("b.mkv"), b"b").unwrap();
+
+        let report = run_rename(
+            vec![
+                (dir.join("a.mkv"), "Same.mkv".to_string()),
+                (dir.join("b.mkv"), "Same.mkv".to_string()),
+            ],
+            true,
+        );
+
+        assert!(report.rolled_back);
+        assert!(report.renamed().is_empty());
+        assert!(dir.join("a.mkv").exists());
+        assert!(dir.join("b.mkv").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_all_or_nothing_rolls_back_midway_failure() {
+        let dir = temp_dir("renamer", "rollback");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+        std::fs::write(dir.join("b.mkv"), b"b").unwrap();
+        // A regular file where a directory is needed makes the second move fail
+        std::fs::write(dir.join("blocker"), b"").unwrap();
+
+        let report = run_rename(
+            vec![
+                (dir.join("a.mkv"), "A.mkv".to_string()),
+                (dir.join("b.mkv"), "blocker/B.mkv".to_string()),
+            ],
+            true,
+        );
+
+        assert!(report.rolled_back);
+        assert_eq!(report.outcomes[0].status, RenameStatus::RolledBack);
+        assert!(matches!(report.outcomes[1].status, RenameStatus::Failed(_)));
+        assert!(dir.join("a.mkv").exists());
+        assert!(!dir.join("A.mkv").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_hardlink_keeps_source() {
+        let dir = temp_dir("renamer", "hardlink");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+
+        let report = run_with_operation(
+            vec![(dir.join("a.mkv"), "A (2020).mkv".to_string())],
+            false,
+            FileOperation::Hardlink,
+        );
+
+        assert_eq!(report.outcomes[0].status, RenameStatus::Renamed);
+        assert!(dir.join("a.mkv").exists());
+        assert!(dir.join("A (2020).mkv").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_copy_rollback_removes_copies() {
+        let dir = temp_dir("renamer", "copy_rollback");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+        std::fs::write(dir.join("b.mkv"), b"b").unwrap();
+        std::fs::write(dir.join("blocker"), b"").unwrap();
+
+        let report = run_with_operation(
+            vec![
+                (dir.join("a.mkv"), "A.mkv".to_string()),
+                (dir.join("b.mkv"), "blocker/B.mkv".to_string()),
+            ],
+            true,
+            FileOperation::Copy,
+        );
+
+        assert!(report.rolled_back);
+        assert_eq!(report.outcomes[0].status, RenameStatus::RolledBack);
+        assert!(dir.join("a.mkv").exists());
+        assert!(!dir.join("A.mkv").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_creates_pattern_folders() {
+        let dir = temp_dir("renamer", "folders");
+        let output = dir.join("library");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+
+        let options = RenameOptions {
+            output_dir: Some(output.clone()),
+            ..Default::default()
+        };
+        let report = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(rename_files(
+                vec![(
+                    dir.join("a.mkv"),
+                    "Movies/A (2020)/A (2020).mkv".to_string(),
+                )],
+                options,
+            ));
+
+        assert_eq!(report.outcomes[0].status, RenameStatus::Renamed);
+        assert!(output.join("Movies/A (2020)/A (2020).mkv").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_moves_sidecars_in_step() {
+        let dir = temp_dir("renamer", "sidecars");
+        let output = dir.join("library");
+        for name in ["a.mkv", "a.en.forced.srt", "a-poster.jpg", "b.mkv", "b.srt"] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+        // b's destination is taken, so its subtitle has to stay put too
+        std::fs::create_dir_all(&output).unwrap();
+        std::fs::write(output.join("B.mkv"), b"taken").unwrap();
+
+        let sidecar = |name: &str, suffix: &str| Sidecar {
+            path: dir.join(name),
+            suffix: suffix.to_string(),
+        };
+        let entries = vec![
+            RenameEntry {
+                old_path: dir.join("a.mkv"),
+                new_filename: "A (2020)/A (2020).mkv".to_string(),
+                sidecars: vec![
+                    sidecar("a.en.forced.srt", ".en.forced.srt"),
+                    sidecar("a-poster.jpg", "-poster.jpg"),
+                ],
+            },
+            RenameEntry {
+                old_path: dir.join("b.mkv"),
+                new_filename: "B.mkv".to_string(),
+                sidecars: vec![sidecar("b.srt", ".srt")],
+            },
+        ];
+        let options = RenameOptions {
+            output_dir: Some(output.clone()),
+            ..Default::default()
+        };
+        let report = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(rename_files(entries, options));
+
+        let statuses: Vec<_> = report.outcomes.iter().map(|o| o.status.clone()).collect();
+        assert!(statuses[..3].iter().all(|s| *s == RenameStatus::Renamed));
+        assert!(matches!(statuses[3], RenameStatus::Failed(_)));
+        assert_eq!(statuses[4], RenameStatus::Skipped);
+        assert_eq!(report.outcomes[4].sidecar_of, Some(3));
+        assert_eq!(report.moved_summary(), "1 file(s) and 2 sidecar file(s)");
+
+        assert!(output.join("A (2020)/A (2020).en.forced.srt").exists());
+        assert!(output.join("A (2020)/A (2020)-poster.jpg").exists());
+        assert!(dir.join("b.srt").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_moves_subs_folder_subtitles_beside_video() {
+        let dir = temp_dir("renamer", "subs_folder");
+        std::fs::create_dir_all(dir.join("Subs")).unwrap();
+        std::fs::write(dir.join("movie.mkv"), b"movie").unwrap();
+        std::fs::write(dir.join("Subs").join("2_English.srt"), b"subs").unwrap();
+
+        let entries = vec![RenameEntry {
+            old_path: dir.join("movie.mkv"),
+            new_filename: "Movie (2019).mkv".to_string(),
+            sidecars: vec![Sidecar {
+                path: dir.join("Subs").join("2_English.srt"),
+                suffix: ".en.srt".to_string(),
+            }],
+        }];
+        let report = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(rename_files(entries, RenameOptions::default()));
+
+        assert_eq!(report.outcomes[1].status, RenameStatus::Renamed);
+        assert!(dir.join("Movie (2019).en.srt").exists());
+        assert!(!dir.join("Subs").join("2_English.srt").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_files_rollback_removes_pattern_folders() {
+        let dir = temp_dir("renamer", "folder_rollback");
+        std::fs::write(dir.join("a.mkv"), b"a").unwrap();
+        std::fs::write(dir.join("b.mkv"), b"b").unwrap();
+        std::fs::write(dir.join("blocker"), b"").unwrap();
+
+        let report = run_rename(
+            vec![
+                (dir.join("a.mkv"), "Show/Season 01/A.mkv".to_string()),
+                (dir.join("b.mkv"), "blocker/B.mkv".to_string()),
+            ],
+            true,
+        );
+
+        assert!(report.rolled_back);
+        assert!(dir.join("a.mkv").exists());
+        assert!(!dir.join("Show").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     // ==================== RENAME PATTERN TESTS ====================
 
+    #[test]
+    fn test_preset_patterns_are_valid() {
+        for pattern in RenamePattern::all_patterns() {
+            assert!(validate_pattern(&pattern).is_ok(), "{}", pattern.name);
+        }
+    }
+
+    #[test]
+    fn test_validate_pattern_reports_unknown_placeholder() {
+        let pattern = RenamePattern {
+            name: "Broken".to_string(),
+            movie_pattern: "{title}".to_string(),
+            tv_pattern: "{show} {resolution}".to_string(),
+            language: None,
+        };
+        let err = validate_pattern(&pattern).unwrap_err();
+        assert!(err.starts_with("TV pattern: Unknown placeholder {resolution}"));
+    }
+
+    #[test]
+    fn test_generate_filename_with_filters() {
+        let file = create_tv_file("Breaking.Bad.S01E01.mkv");
+        let pattern = RenamePattern {
+            name: "Filters".to_string(),
+            movie_pattern: String::new(),
+            tv_pattern:
+                "{show|replace( ,.)|lower}.s{season:02}e{episode:03}[.{episode_title|upper}]"
+                    .to_string(),
+            language: None,
+        };
+        let result = generate_filename(&file, &create_tv_metadata(), &pattern).unwrap();
+        assert_eq!(result, "breaking.bad.s01e001.PILOT.mkv");
+    }
+
     #[test]
     fn test_rename_pattern_default() {
         let pattern = RenamePattern::default();
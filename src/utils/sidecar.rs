@@ -0,0 +1,385 @@
+use crate::model::{is_video_file, MediaFile};
+use crate::utils::subtitle::{self, SubtitleTags};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions of files that belong to a video: subtitles, NFOs and artwork
+pub const SIDECAR_EXTENSIONS: &[&str] = &[
+    "srt", "ass", "ssa", "sub", "idx", "vtt", "sup", "smi", "nfo", "jpg", "jpeg", "png", "tbn",
+];
+
+/// Folders next to a video that hold its subtitles
+const SUBTITLE_FOLDERS: &[&str] = &["subs", "subtitles"];
+
+/// A file next to a video that shares its name, e.g. `Movie.2019.en.srt`,
+/// or a subtitle in a "Subs" folder beside it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sidecar {
+    pub path: PathBuf,
+    /// Everything after the video's name, e.g. ".en.forced.srt" or "-poster.jpg"
+    pub suffix: String,
+}
+
+impl Sidecar {
+    /// Filename (or "/"-separated path) this sidecar gets when its video is
+    /// renamed to `video_filename`
+    pub fn target_name(&self, video_filename: &str) -> String {
+        let stem = match video_filename.rfind('.') {
+            Some(dot) if !video_filename[dot..].contains('/') => &video_filename[..dot],
+            _ => video_filename,
+        };
+        format!("{}{}", stem, self.suffix)
+    }
+}
+
+pub fn is_sidecar_file(extension: &str) -> bool {
+    SIDECAR_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+}
+
+/// What follows `video_stem` in a sidecar's filename, if it belongs to that video
+pub fn sidecar_suffix(video_stem: &str, filename: &str) -> Option<String> {
+    let rest = filename.get(video_stem.len()..)?;
+    if !filename[..video_stem.len()].eq_ignore_ascii_case(video_stem) {
+        return None;
+    }
+    // "Movie.en.srt" or "Movie-poster.jpg", but not "Movie2.srt"
+    if !rest.starts_with(['.', '-']) {
+        return None;
+    }
+    let (_, extension) = rest.rsplit_once('.')?;
+    is_sidecar_file(extension).then(|| rest.to_string())
+}
+
+/// Subtitle suffix in Plex/Jellyfin form (".en.forced.srt"), guessing the
+/// language from the text if the name has none. Suffixes with nothing to
+/// normalize are kept as they are.
+fn subtitle_suffix(path: &Path, suffix: &str) -> String {
+    let Some((name, extension)) = suffix.rsplit_once('.') else {
+        return suffix.to_string();
+    };
+    let tags = subtitle::read_tags(path, name);
+    if tags == SubtitleTags::default() {
+        suffix.to_string()
+    } else {
+        tags.suffix(extension)
+    }
+}
+
+/// Everything in a folder, or nothing if it can't be read
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries.flatten().map(|e| e.path()).collect()
+}
+
+fn is_subtitle_folder(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|n| SUBTITLE_FOLDERS.contains(&n.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// Find the sidecars of every video, reading each folder once. A sidecar that
+/// matches several videos in its folder (`Movie.srt` vs `Movie.Extended.srt`)
+/// goes to the video with the longest name, even if that one isn't in `files`.
+/// Subtitles in a "Subs" folder belong to the folder's only video, or to the
+/// video named like the subfolder they're in (`Subs/Show.S01E01/2_English.srt`).
+pub fn attach_sidecars(files: &mut [MediaFile]) {
+    let mut by_dir: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        if let Some(dir) = file.path.parent() {
+            by_dir.entry(dir.to_path_buf()).or_default().push(i);
+        }
+    }
+
+    for (dir, indices) in by_dir {
+        let entries = list_files(&dir);
+        let paths: Vec<&PathBuf> = entries.iter().filter(|p| p.is_file()).collect();
+        let videos: Vec<(&PathBuf, String)> = paths
+            .iter()
+            .filter(|p| {
+                p.extension()
+                    .is_some_and(|ext| is_video_file(&ext.to_string_lossy()))
+            })
+            .filter_map(|p| Some((*p, p.file_stem()?.to_string_lossy().to_string())))
+            .collect();
+        let mut attach = |video: &PathBuf, sidecar: Sidecar| {
+            if let Some(&i) = indices.iter().find(|&&i| files[i].path == *video) {
+                files[i].sidecars.push(sidecar);
+            }
+        };
+
+        for path in &paths {
+            let Some(filename) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let owner = videos
+                .iter()
+                .filter_map(|(video, stem)| {
+                    Some((*video, stem.len(), sidecar_suffix(stem, &filename)?))
+                })
+                .max_by_key(|(_, stem_len, _)| *stem_len);
+            let Some((video, _, mut suffix)) = owner else {
+                continue;
+            };
+            if suffix
+                .rsplit_once('.')
+                .is_some_and(|(_, ext)| subtitle::is_subtitle_file(ext))
+            {
+                suffix = subtitle_suffix(path, &suffix);
+            }
+            attach(
+                video,
+                Sidecar {
+                    path: (*path).clone(),
+                    suffix,
+                },
+            );
+        }
+
+        for folder in entries
+            .iter()
+            .filter(|p| p.is_dir() && is_subtitle_folder(p))
+        {
+            for path in list_files(folder) {
+                let owner = if path.is_dir() {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    videos
+                        .iter()
+                        .find(|(_, stem)| stem.eq_ignore_ascii_case(&name))
+                        .map(|(video, _)| (*video, list_files(&path)))
+                } else if let [(video, _)] = videos.as_slice() {
+                    Some((*video, vec![path]))
+                } else {
+                    None
+                };
+                let Some((video, subtitles)) = owner else {
+                    continue;
+                };
+                for subtitle in subtitles {
+                    let (Some(name), Some(extension)) = (
+                        subtitle
+                            .file_stem()
+                            .map(|n| n.to_string_lossy().to_string()),
+                        subtitle
+                            .extension()
+                            .map(|e| e.to_string_lossy().to_string()),
+                    ) else {
+                        continue;
+                    };
+                    if !subtitle.is_file() || !subtitle::is_subtitle_file(&extension) {
+                        continue;
+                    }
+                    // Keep the original name when there's no language to go by
+                    let suffix = subtitle_suffix(&subtitle, &format!(".{}.{}", name, extension));
+                    attach(
+                        video,
+                        Sidecar {
+                            path: subtitle,
+                            suffix,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    for file in files {
+        file.sidecars.sort_by(|a, b| a.path.cmp(&b.path));
+        dedupe_suffixes(&mut file.sidecars);
+    }
+}
+
+/// Number repeated suffixes (".en.srt", ".en.2.srt") so two subtitles in the
+/// same language don't collide
+fn dedupe_suffixes(sidecars: &mut [Sidecar]) {
+    let mut seen = HashSet::new();
+    for sidecar in sidecars {
+        if seen.insert(sidecar.suffix.to_lowercase()) {
+            continue;
+        }
+        let Some((name, extension)) = sidecar.suffix.rsplit_once('.') else {
+            continue;
+        };
+        let (name, extension) = (name.to_string(), extension.to_string());
+        for n in 2.. {
+            let suffix = format!("{}.{}.{}", name, n, extension);
+            if seen.insert(suffix.to_lowercase()) {
+                sidecar.suffix = suffix;
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::temp_dir;
+
+    #[test]
+    fn test_sidecar_suffix() {
+        let stem = "Movie.2019";
+        assert_eq!(
+            sidecar_suffix(stem, "Movie.2019.en.forced.srt").as_deref(),
+            Some(".en.forced.srt")
+        );
+        assert_eq!(
+            sidecar_suffix(stem, "Movie.2019-poster.jpg").as_deref(),
+            Some("-poster.jpg")
+        );
+        assert_eq!(
+            sidecar_suffix(stem, "movie.2019.nfo").as_deref(),
+            Some(".nfo")
+        );
+        assert_eq!(
+            sidecar_suffix(stem, "Movie.2019.idx").as_deref(),
+            Some(".idx")
+        );
+        assert_eq!(sidecar_suffix(stem, "Movie.20190.srt"), None);
+        assert_eq!(sidecar_suffix(stem, "Movie.2019.mkv"), None);
+        assert_eq!(sidecar_suffix(stem, "Other.2019.srt"), None);
+        assert_eq!(sidecar_suffix(stem, "Movie"), None);
+    }
+
+    #[test]
+    fn test_target_name_keeps_suffix() {
+        let sidecar = Sidecar {
+            path: PathBuf::from("/in/Movie.2019.en.forced.srt"),
+            suffix: ".en.forced.srt".to_string(),
+        };
+        assert_eq!(
+            sidecar.target_name("Title (2019).mkv"),
+            "Title (2019).en.forced.srt"
+        );
+        assert_eq!(
+            sidecar.target_name("Movies/Title (2019)/Title (2019).mkv"),
+            "Movies/Title (2019)/Title (2019).en.forced.srt"
+        );
+        assert_eq!(
+            sidecar.target_name("Folder.v2/Title"),
+            "Folder.v2/Title.en.forced.srt"
+        );
+    }
+
+    #[test]
+    fn test_attach_sidecars_prefers_longest_name() {
+        let dir = temp_dir("sidecar", "attach");
+        for name in [
+            "Movie.mkv",
+            "Movie.Extended.mkv",
+            "Movie.en.srt",
+            "Movie.Extended.en.srt",
+            "Movie.Extended-poster.jpg",
+            "Movie.idx",
+            "Movie.sub",
+            "notes.txt",
+            "Unrelated.srt",
+        ] {
+            fs::write(dir.join(name), b"x").unwrap();
+        }
+
+        let mut files = vec![
+            MediaFile::new(dir.join("Movie.mkv")),
+            MediaFile::new(dir.join("Movie.Extended.mkv")),
+        ];
+        attach_sidecars(&mut files);
+
+        let suffixes = |file: &MediaFile| -> Vec<String> {
+            file.sidecars.iter().map(|s| s.suffix.clone()).collect()
+        };
+        assert_eq!(suffixes(&files[0]), vec![".en.srt", ".idx", ".sub"]);
+        assert_eq!(suffixes(&files[1]), vec!["-poster.jpg", ".en.srt"]);
+
+        // The longer-named video still owns its subtitles when it isn't loaded
+        let mut files = vec![MediaFile::new(dir.join("Movie.mkv"))];
+        attach_sidecars(&mut files);
+        assert_eq!(suffixes(&files[0]), vec![".en.srt", ".idx", ".sub"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_attach_sidecars_normalizes_subtitle_names() {
+        let dir = temp_dir("sidecar", "normalize");
+        for name in [
+            "Movie.mkv",
+            "Movie.English.srt",
+            "Movie.eng.forced.srt",
+            "Movie.en.srt",
+            "Movie-poster.jpg",
+        ] {
+            fs::write(dir.join(name), b"x").unwrap();
+        }
+        fs::write(
+            dir.join("Movie.srt"),
+            "1\n00:00:01,000 --> 00:00:02,000\nNo sé qué está pasando. Por favor, dime la \
+             verdad. Es que no puedo creer lo que me dices, pero te voy a ayudar con eso \
+             para que todo esté bien en la casa.\n",
+        )
+        .unwrap();
+
+        let mut files = vec![MediaFile::new(dir.join("Movie.mkv"))];
+        attach_sidecars(&mut files);
+        let suffixes: Vec<&str> = files[0]
+            .sidecars
+            .iter()
+            .map(|s| s.suffix.as_str())
+            .collect();
+        assert_eq!(
+            suffixes,
+            vec![
+                "-poster.jpg",
+                ".en.srt",
+                ".en.2.srt",
+                ".en.forced.srt",
+                ".es.srt"
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_attach_sidecars_from_subs_folders() {
+        let dir = temp_dir("sidecar", "subs");
+        let movie = dir.join("movie");
+        fs::create_dir_all(movie.join("Subs")).unwrap();
+        fs::write(movie.join("Movie.2019.mkv"), b"x").unwrap();
+        fs::write(movie.join("Subs").join("2_English.srt"), b"x").unwrap();
+        fs::write(movie.join("Subs").join("3_English_SDH.srt"), b"x").unwrap();
+        fs::write(movie.join("Subs").join("Eng.srt"), b"x").unwrap();
+        fs::write(movie.join("Subs").join("track.srt"), b"x").unwrap();
+
+        let show = dir.join("show");
+        fs::create_dir_all(show.join("Subs").join("Show.S01E01")).unwrap();
+        fs::write(show.join("Show.S01E01.mkv"), b"x").unwrap();
+        fs::write(show.join("Show.S01E02.mkv"), b"x").unwrap();
+        fs::write(
+            show.join("Subs").join("Show.S01E01").join("4_French.srt"),
+            b"x",
+        )
+        .unwrap();
+        // Loose subtitles are ambiguous with several videos around
+        fs::write(show.join("Subs").join("English.srt"), b"x").unwrap();
+
+        let mut files = vec![
+            MediaFile::new(movie.join("Movie.2019.mkv")),
+            MediaFile::new(show.join("Show.S01E01.mkv")),
+            MediaFile::new(show.join("Show.S01E02.mkv")),
+        ];
+        attach_sidecars(&mut files);
+
+        let suffixes = |file: &MediaFile| -> Vec<String> {
+            file.sidecars.iter().map(|s| s.suffix.clone()).collect()
+        };
+        assert_eq!(
+            suffixes(&files[0]),
+            vec![".en.srt", ".en.sdh.srt", ".en.2.srt", ".track.srt"]
+        );
+        assert_eq!(suffixes(&files[1]), vec![".fr.srt"]);
+        assert!(files[2].sidecars.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
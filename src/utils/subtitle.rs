@@ -0,0 +1,656 @@
+//! Subtitle language tags and language detection
+//!
+//! Subtitles come named `English.srt`, `eng.srt`, `2_English.srt` or after
+//! their video with no language at all. This module turns those names into
+//! the `.en.forced.srt` style Plex and Jellyfin expect, guessing the
+//! language from the subtitle text when the name doesn't say.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Subtitle file extensions
+pub const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "ass", "ssa", "sub", "idx", "vtt", "sup", "smi"];
+
+/// Subtitle formats stored as text, whose language can be guessed
+const TEXT_EXTENSIONS: &[&str] = &["srt", "ass", "ssa", "vtt", "smi"];
+
+/// How much of a subtitle file to read when guessing its language
+const SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// A language with its ISO 639-1 and 639-2 codes and the names it goes by
+#[derive(Debug, PartialEq, Eq)]
+pub struct Language {
+    pub code: &'static str,  // ISO 639-1, e.g. "de"
+    pub code3: &'static str, // ISO 639-2/B, e.g. "ger"
+    pub name: &'static str,
+    aliases: &'static [&'static str], // 639-2/T codes, native and other names, lowercase
+}
+
+const fn language(
+    code: &'static str,
+    code3: &'static str,
+    name: &'static str,
+    aliases: &'static [&'static str],
+) -> Language {
+    Language {
+        code,
+        code3,
+        name,
+        aliases,
+    }
+}
+
+static LANGUAGES: &[Language] = &[
+    language("en", "eng", "English", &[]),
+    language("de", "ger", "German", &["deu", "deutsch"]),
+    language("fr", "fre", "French", &["fra", "français", "francais"]),
+    language(
+        "es",
+        "spa",
+        "Spanish",
+        &["español", "espanol", "castellano", "latino"],
+    ),
+    language("it", "ita", "Italian", &["italiano"]),
+    language(
+        "pt",
+        "por",
+        "Portuguese",
+        &["português", "portugues", "brazilian"],
+    ),
+    language("nl", "dut", "Dutch", &["nld", "nederlands", "flemish"]),
+    language("sv", "swe", "Swedish", &["svenska"]),
+    language(
+        "no",
+        "nor",
+        "Norwegian",
+        &["nb", "nob", "nn", "nno", "norsk"],
+    ),
+    language("da", "dan", "Danish", &["dansk"]),
+    language("fi", "fin", "Finnish", &["suomi"]),
+    language("is", "ice", "Icelandic", &["isl", "íslenska"]),
+    language("pl", "pol", "Polish", &["polski"]),
+    language("cs", "cze", "Czech", &["ces", "čeština", "cestina"]),
+    language("sk", "slo", "Slovak", &["slk", "slovenčina", "slovencina"]),
+    language("sl", "slv", "Slovenian", &["slovene", "slovenščina"]),
+    language("hu", "hun", "Hungarian", &["magyar"]),
+    language("ro", "rum", "Romanian", &["ron", "română", "romana"]),
+    language("hr", "hrv", "Croatian", &["hrvatski"]),
+    language("sr", "srp", "Serbian", &["srpski"]),
+    language("bg", "bul", "Bulgarian", &["български"]),
+    language("ru", "rus", "Russian", &["русский"]),
+    language("uk", "ukr", "Ukrainian", &["українська"]),
+    language("el", "gre", "Greek", &["ell", "ελληνικά"]),
+    language("tr", "tur", "Turkish", &["türkçe", "turkce"]),
+    language("et", "est", "Estonian", &["eesti"]),
+    language("lv", "lav", "Latvian", &["latviešu"]),
+    language("lt", "lit", "Lithuanian", &["lietuvių"]),
+    language("ar", "ara", "Arabic", &["العربية"]),
+    language("he", "heb", "Hebrew", &["iw", "עברית"]),
+    language("fa", "per", "Persian", &["fas", "farsi"]),
+    // "hi" alone means hearing impaired in subtitle names
+    language("hi", "hin", "Hindi", &[]),
+    language("th", "tha", "Thai", &["ไทย"]),
+    language("vi", "vie", "Vietnamese", &["tiếng việt"]),
+    language("id", "ind", "Indonesian", &["bahasa", "indonesia"]),
+    language("ms", "may", "Malay", &["msa", "melayu"]),
+    language("ja", "jpn", "Japanese", &["日本語"]),
+    language(
+        "zh",
+        "chi",
+        "Chinese",
+        &["zho", "中文", "chs", "cht", "mandarin"],
+    ),
+    language("ko", "kor", "Korean", &["한국어"]),
+];
+
+/// Look up a language by ISO 639-1/639-2 code or name, ignoring case
+pub fn find_language(token: &str) -> Option<&'static Language> {
+    let token = token.trim().to_lowercase();
+    if token.is_empty() {
+        return None;
+    }
+    LANGUAGES.iter().find(|l| {
+        l.code == token
+            || l.code3 == token
+            || l.name.to_lowercase() == token
+            || l.aliases.contains(&token.as_str())
+    })
+}
+
+/// Language and markers of a subtitle file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtitleTags {
+    pub language: Option<String>, // ISO 639-1, with a region if one was given ("pt-BR")
+    pub forced: bool,             // Only foreign-language parts
+    pub sdh: bool,                // For the deaf and hard of hearing
+    pub cc: bool,                 // Closed captions
+}
+
+impl SubtitleTags {
+    /// Read the language and markers from a subtitle's name (without extension),
+    /// e.g. "English", "2_eng_forced" or ".pt-BR.sdh"
+    pub fn from_name(name: &str) -> Self {
+        let mut tags = Self::default();
+        let tokens: Vec<String> = name
+            .split(['.', '_', ' ', '[', ']', '(', ')'])
+            .filter(|t| !t.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+
+        // Two-letter codes are also words ("is", "no", "id"), so they only count
+        // as the last token before the markers, as in ".no.forced"
+        let last_word = tokens.iter().rposition(|t| !is_marker(t));
+
+        for (i, token) in tokens.iter().enumerate() {
+            match token.as_str() {
+                "forced" | "foreign" => tags.forced = true,
+                "sdh" | "hi" => tags.sdh = true,
+                "hearing" if tokens.get(i + 1).is_some_and(|t| t == "impaired") => tags.sdh = true,
+                "cc" => tags.cc = true,
+                _ if tags.language.is_some() => {}
+                _ if token.len() == 2 && Some(i) != last_word => {}
+                _ => tags.language = token_language(token),
+            }
+        }
+        tags
+    }
+
+    /// Filename suffix after the video's name, e.g. ".en.sdh.forced.srt"
+    pub fn suffix(&self, extension: &str) -> String {
+        let mut suffix = String::new();
+        if let Some(language) = &self.language {
+            suffix.push('.');
+            suffix.push_str(language);
+        }
+        if self.sdh {
+            suffix.push_str(".sdh");
+        } else if self.cc {
+            suffix.push_str(".cc");
+        }
+        if self.forced {
+            suffix.push_str(".forced");
+        }
+        suffix.push('.');
+        suffix.push_str(&extension.to_lowercase());
+        suffix
+    }
+}
+
+/// Tokens that mark a kind of subtitle rather than its language
+fn is_marker(token: &str) -> bool {
+    matches!(
+        token,
+        "forced" | "foreign" | "sdh" | "hi" | "hearing" | "impaired" | "cc"
+    )
+}
+
+/// Language of a single name token: "eng", "English", "pt-BR" or "en-US"
+fn token_language(token: &str) -> Option<String> {
+    if let Some(language) = find_language(token) {
+        return Some(language.code.to_string());
+    }
+    let (language, region) = token.split_once('-')?;
+    let language = find_language(language)?;
+    if region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()) {
+        Some(format!("{}-{}", language.code, region.to_uppercase()))
+    } else {
+        Some(language.code.to_string())
+    }
+}
+
+pub fn is_subtitle_file(extension: &str) -> bool {
+    SUBTITLE_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+}
+
+/// Tags of a subtitle file from its name, guessing the language from its
+/// text when the name doesn't give one
+pub fn read_tags(path: &Path, name: &str) -> SubtitleTags {
+    let mut tags = SubtitleTags::from_name(name);
+    if tags.language.is_none() {
+        tags.language = read_sample(path)
+            .and_then(|sample| guess_language(&subtitle_text(&sample)))
+            .map(str::to_string);
+    }
+    tags
+}
+
+/// The start of a text subtitle file, decoded leniently
+fn read_sample(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    if !TEXT_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(SAMPLE_BYTES)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// The spoken lines of an SRT, VTT, ASS/SSA or SAMI file, without timings,
+/// numbering or formatting tags
+pub fn subtitle_text(contents: &str) -> String {
+    let mut text = String::new();
+    // ASS/SSA files are split into "[Section]"s, with the text in "Dialogue:" lines
+    let mut in_ass_section = false;
+    for line in contents.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.starts_with('[') && line.ends_with(']') {
+            in_ass_section = true;
+            continue;
+        }
+        let dialogue = if in_ass_section {
+            // The text is the last of ten comma-separated fields
+            match line.strip_prefix("Dialogue:") {
+                Some(fields) => fields.splitn(10, ',').nth(9).unwrap_or_default(),
+                None => continue,
+            }
+        } else if line.is_empty()
+            || line.contains("-->")
+            || line.chars().all(|c| c.is_ascii_digit())
+            || line.starts_with("WEBVTT")
+            || line.starts_with("NOTE")
+        {
+            continue;
+        } else {
+            line
+        };
+        text.push_str(&strip_tags(dialogue).replace("\\N", " "));
+        text.push('\n');
+    }
+    text
+}
+
+/// Remove `<i>`, `<font ...>` and `{\an8}` style markup
+fn strip_tags(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut depth: Option<char> = None;
+    for c in line.chars() {
+        match (depth, c) {
+            (None, '<') => depth = Some('>'),
+            (None, '{') => depth = Some('}'),
+            (Some(end), c) if c == end => depth = None,
+            (Some(_), _) => {}
+            (None, c) => result.push(c),
+        }
+    }
+    result
+}
+
+/// Most frequent short words of languages written in Latin script
+static STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "you", "to", "is", "it", "that", "of", "what", "this", "i'm", "don't",
+            "in", "me", "we", "have", "are", "for", "was", "my", "your", "he", "with", "just",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "ich", "und", "nicht", "du", "das", "die", "ist", "der", "sie", "es", "wir", "was",
+            "zu", "ein", "mit", "mir", "den", "auf", "ja", "mich", "dich", "hast", "bin", "haben",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "je", "de", "est", "pas", "le", "vous", "la", "tu", "que", "un", "il", "et", "à", "ne",
+            "les", "ce", "en", "on", "ça", "une", "pour", "moi", "qui", "c'est",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "que", "de", "no", "a", "la", "el", "es", "y", "en", "lo", "un", "por", "qué", "me",
+            "una", "te", "los", "se", "con", "para", "mi", "está", "eso", "pero",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "non", "di", "che", "è", "e", "la", "il", "un", "a", "per", "in", "una", "mi", "sono",
+            "ho", "ma", "l'", "lo", "ha", "le", "si", "ti", "cosa", "questo",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "que", "não", "de", "o", "a", "é", "e", "do", "da", "um", "para", "eu", "se", "com",
+            "uma", "os", "no", "você", "em", "me", "isso", "está", "na", "mas",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "ik", "je", "het", "de", "dat", "is", "een", "niet", "en", "wat", "van", "we", "in",
+            "ze", "hij", "op", "te", "zijn", "er", "maar", "me", "die", "heb", "jij",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "jag", "det", "du", "är", "inte", "att", "en", "och", "har", "vi", "på", "som", "för",
+            "med", "han", "vad", "ett", "kan", "mig", "så", "den", "till", "här", "om",
+        ],
+    ),
+    (
+        "da",
+        &[
+            "jeg", "det", "du", "er", "ikke", "at", "en", "og", "har", "vi", "på", "til", "for",
+            "mig", "hvad", "med", "så", "den", "der", "kan", "han", "nu", "her", "af",
+        ],
+    ),
+    (
+        "no",
+        &[
+            "jeg", "det", "du", "er", "ikke", "å", "en", "og", "har", "vi", "på", "til", "for",
+            "meg", "hva", "med", "så", "den", "deg", "kan", "han", "nå", "her", "av",
+        ],
+    ),
+    (
+        "fi",
+        &[
+            "on", "ei", "se", "että", "en", "ja", "mitä", "hän", "minä", "sinä", "oli", "me",
+            "olen", "se", "tämä", "kun", "mutta", "jos", "nyt", "vain", "sen", "ole", "niin",
+            "kanssa",
+        ],
+    ),
+    (
+        "pl",
+        &[
+            "nie", "to", "się", "w", "na", "i", "jest", "z", "że", "do", "co", "jak", "mnie", "ja",
+            "tak", "ci", "mi", "ale", "już", "go", "tu", "o", "czy", "jestem",
+        ],
+    ),
+    (
+        "cs",
+        &[
+            "to", "je", "se", "na", "že", "ne", "jsem", "a", "v", "co", "tak", "by", "mi", "jsi",
+            "já", "ale", "ty", "jak", "tady", "tu", "mě", "do", "za", "není",
+        ],
+    ),
+    (
+        "hu",
+        &[
+            "a", "az", "nem", "hogy", "és", "is", "egy", "meg", "mi", "ez", "de", "van", "ha",
+            "csak", "én", "már", "te", "itt", "most", "mit", "vagy", "igen", "kell", "még",
+        ],
+    ),
+    (
+        "ro",
+        &[
+            "nu", "să", "și", "de", "e", "o", "la", "ce", "în", "mai", "pe", "cu", "ai", "am",
+            "un", "este", "te", "mă", "din", "ca", "asta", "sunt", "dar", "tu",
+        ],
+    ),
+    (
+        "tr",
+        &[
+            "bir", "bu", "ne", "ve", "de", "da", "için", "ben", "sen", "mi", "çok", "var", "o",
+            "değil", "ama", "şey", "beni", "seni", "evet", "hayır", "gibi", "daha", "mı", "onu",
+        ],
+    ),
+    (
+        "id",
+        &[
+            "yang", "aku", "tidak", "kau", "ini", "itu", "dan", "di", "apa", "kita", "ada", "saya",
+            "kamu", "dia", "akan", "ke", "untuk", "dengan", "tak", "bisa", "sudah", "harus",
+            "kami", "ya",
+        ],
+    ),
+];
+
+/// Fewer words than this aren't enough to tell languages apart
+const MIN_WORDS: usize = 20;
+
+/// Guess the language of subtitle text: by script for non-Latin alphabets,
+/// otherwise by how often each language's most common words appear
+pub fn guess_language(text: &str) -> Option<&'static str> {
+    if let Some(language) = guess_by_script(text) {
+        return Some(language);
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphabetic() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let mut scores: Vec<(&str, usize)> = STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| {
+            let hits = words
+                .iter()
+                .filter(|w| stopwords.contains(&w.as_str()))
+                .count();
+            (*language, hits)
+        })
+        .collect();
+    scores.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+
+    let (best, hits) = scores[0];
+    let runner_up = scores[1].1;
+    // Stopwords should be a good share of the text and clearly favor one language
+    (hits * 10 >= words.len() && hits * 4 >= runner_up * 5).then_some(best)
+}
+
+/// Languages that can be told apart by their alphabet alone
+fn guess_by_script(text: &str) -> Option<&'static str> {
+    let mut counts: [usize; 10] = [0; 10];
+    let mut letters = 0;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        let script = match c {
+            '\u{3040}'..='\u{30ff}' => 0, // Hiragana and katakana
+            '\u{ac00}'..='\u{d7af}' | '\u{1100}'..='\u{11ff}' => 1, // Hangul
+            '\u{4e00}'..='\u{9fff}' => 2, // CJK ideographs
+            'і' | 'ї' | 'є' | 'ґ' | 'І' | 'Ї' | 'Є' | 'Ґ' => 3, // Ukrainian-only Cyrillic
+            '\u{0400}'..='\u{04ff}' => 4, // Cyrillic
+            '\u{0370}'..='\u{03ff}' => 5, // Greek
+            '\u{0600}'..='\u{06ff}' => 6, // Arabic
+            '\u{0590}'..='\u{05ff}' => 7, // Hebrew
+            '\u{0e00}'..='\u{0e7f}' => 8, // Thai
+            _ => 9,
+        };
+        counts[script] += 1;
+    }
+    if letters == 0 || counts[9] * 2 >= letters {
+        return None;
+    }
+
+    // Japanese mixes kana into its kanji; Chinese has none
+    if counts[0] > 0 && counts[0] * 10 >= counts[2] {
+        return Some("ja");
+    }
+    let (script, _) = counts[..9]
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, count)| **count)?;
+    Some(match script {
+        1 => "ko",
+        2 => "zh",
+        // A Ukrainian name in Russian text isn't enough; і alone is ~5% of Ukrainian
+        3 | 4 if counts[3] * 50 >= counts[3] + counts[4] => "uk",
+        4 => "ru",
+        5 => "el",
+        6 => "ar",
+        7 => "he",
+        8 => "th",
+        _ => "ja",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::temp_dir;
+
+    #[test]
+    fn test_find_language() {
+        assert_eq!(find_language("eng").unwrap().code, "en");
+        assert_eq!(find_language("English").unwrap().code, "en");
+        assert_eq!(find_language("DEU").unwrap().code, "de");
+        assert_eq!(find_language("ger").unwrap().code3, "ger");
+        assert_eq!(find_language("Français").unwrap().code, "fr");
+        assert_eq!(find_language("pt").unwrap().name, "Portuguese");
+        assert!(find_language("forced").is_none());
+        assert!(find_language("").is_none());
+    }
+
+    #[test]
+    fn test_tags_from_name() {
+        let tags = SubtitleTags::from_name("2_English");
+        assert_eq!(tags.language.as_deref(), Some("en"));
+        assert!(!tags.forced && !tags.sdh);
+
+        let tags = SubtitleTags::from_name("Eng");
+        assert_eq!(tags.language.as_deref(), Some("en"));
+
+        let tags = SubtitleTags::from_name(".German.Forced");
+        assert_eq!(tags.language.as_deref(), Some("de"));
+        assert!(tags.forced);
+
+        let tags = SubtitleTags::from_name("3_English [SDH]");
+        assert!(tags.sdh);
+
+        let tags = SubtitleTags::from_name(".en.hi");
+        assert_eq!(tags.language.as_deref(), Some("en"));
+        assert!(tags.sdh);
+
+        let tags = SubtitleTags::from_name("English Hearing Impaired");
+        assert!(tags.sdh);
+
+        let tags = SubtitleTags::from_name(".pt-br.cc");
+        assert_eq!(tags.language.as_deref(), Some("pt-BR"));
+        assert!(tags.cc);
+
+        assert_eq!(SubtitleTags::from_name(".1080p"), SubtitleTags::default());
+    }
+
+    #[test]
+    fn test_two_letter_words_are_not_languages() {
+        assert_eq!(SubtitleTags::from_name(".This.Is.No.Joke").language, None);
+        assert_eq!(SubtitleTags::from_name(" ID Card").language, None);
+
+        let tags = SubtitleTags::from_name(".Is.no.forced");
+        assert_eq!(tags.language.as_deref(), Some("no"));
+        assert!(tags.forced);
+        let tags = SubtitleTags::from_name(".id.hearing.impaired");
+        assert_eq!(tags.language.as_deref(), Some("id"));
+        // Longer codes and names count anywhere
+        assert_eq!(
+            SubtitleTags::from_name("2_eng_Commentary")
+                .language
+                .as_deref(),
+            Some("en")
+        );
+    }
+
+    #[test]
+    fn test_tags_suffix() {
+        let tags = SubtitleTags {
+            language: Some("en".to_string()),
+            forced: true,
+            sdh: true,
+            cc: false,
+        };
+        assert_eq!(tags.suffix("SRT"), ".en.sdh.forced.srt");
+        assert_eq!(SubtitleTags::default().suffix("srt"), ".srt");
+    }
+
+    #[test]
+    fn test_subtitle_text_strips_markup() {
+        let srt = "\u{feff}1\n00:00:01,000 --> 00:00:02,000\n<i>Hello there.</i>\n\n2\n00:00:03,000 --> 00:00:04,000\n{\\an8}General Kenobi!\n";
+        assert_eq!(subtitle_text(srt), "Hello there.\nGeneral Kenobi!\n");
+
+        let ass = "[Script Info]\nScriptType: v4.00+\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Line one\\Nline two, really\n";
+        assert_eq!(subtitle_text(ass), "Line one line two, really\n");
+    }
+
+    #[test]
+    fn test_guess_language_by_stopwords() {
+        let english = "I don't know what you're talking about. We have to go, it is \
+            getting late and the car is waiting for us. What is this? This is my house \
+            and you are in my way.";
+        assert_eq!(guess_language(english), Some("en"));
+
+        let german = "Ich weiß nicht, was du meinst. Wir haben keine Zeit, das ist \
+            nicht mein Problem. Kannst du mir helfen? Ich bin mit dir, und es ist \
+            gut so. Was hast du auf dem Tisch?";
+        assert_eq!(guess_language(german), Some("de"));
+
+        let spanish = "No sé qué está pasando. Por favor, dime la verdad. Es que no \
+            puedo creer lo que me dices, pero te voy a ayudar con eso para que \
+            todo esté bien en la casa.";
+        assert_eq!(guess_language(spanish), Some("es"));
+
+        let french = "Je ne sais pas ce que tu veux dire. C'est pas grave, on va \
+            trouver une solution pour le problème. Il est à la maison et les \
+            enfants sont avec moi.";
+        assert_eq!(guess_language(french), Some("fr"));
+    }
+
+    #[test]
+    fn test_guess_language_by_script() {
+        assert_eq!(
+            guess_language("何をしているの？ ちょっと待って。"),
+            Some("ja")
+        );
+        assert_eq!(guess_language("你在做什么？我不知道。"), Some("zh"));
+        assert_eq!(guess_language("뭐 하고 있어? 잠깐만 기다려."), Some("ko"));
+        assert_eq!(
+            guess_language("Что ты делаешь? Подожди минутку."),
+            Some("ru")
+        );
+        assert_eq!(
+            guess_language("Що ти робиш? Зачекай хвилинку, її немає."),
+            Some("uk")
+        );
+        assert_eq!(guess_language("Τι κάνεις; Περίμενε λίγο."), Some("el"));
+        // One Ukrainian place name doesn't make Russian text Ukrainian
+        assert_eq!(
+            guess_language(
+                "Мы завтра утром поедем в Київ. Я не знаю, что ты делаешь, \
+                 но нам надо торопиться, потому что поезд уходит очень рано."
+            ),
+            Some("ru")
+        );
+    }
+
+    #[test]
+    fn test_guess_language_needs_enough_text() {
+        assert_eq!(guess_language("Okay."), None);
+        assert_eq!(guess_language(""), None);
+        // Names and numbers alone don't say anything
+        let names = "John Smith Mary Jones Paris London Tokyo Berlin Madrid Rome \
+            Alpha Bravo Charlie Delta Echo Foxtrot Golf Hotel India Juliet Kilo";
+        assert_eq!(guess_language(names), None);
+    }
+
+    #[test]
+    fn test_read_tags_guesses_from_text() {
+        let dir = temp_dir("subtitle", "read");
+        let path = dir.join("Movie.srt");
+        std::fs::write(
+            &path,
+            "1\n00:00:01,000 --> 00:00:02,000\nIch weiß nicht, was du meinst. Wir haben \
+             keine Zeit, das ist nicht mein Problem.\n\n2\n00:00:03,000 --> 00:00:04,000\n\
+             Kannst du mir helfen? Ich bin mit dir, und es ist gut so.\n",
+        )
+        .unwrap();
+
+        assert_eq!(read_tags(&path, "").language.as_deref(), Some("de"));
+        // A language in the name wins
+        assert_eq!(read_tags(&path, ".fr").language.as_deref(), Some("fr"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
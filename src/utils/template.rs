@@ -0,0 +1,538 @@
+//! Template language used by rename patterns
+//!
+//! ```text
+//! {title}                   field value
+//! {episode:03}              number zero-padded to 3 digits
+//! {title|upper}             filters: upper, lower, title_case, truncate(n),
+//! {title|replace( ,.)}               replace(a,b), default(text)
+//! [ - {episode_title}]      optional section, dropped if a field inside is missing
+//! [1080p]                   brackets without a field inside are kept as written
+//! \[ \] \{ \} \\            literal brackets and backslash
+//! ```
+
+use std::collections::HashMap;
+
+/// A value available to templates
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(i64),
+}
+
+/// Field values keyed by placeholder name
+pub type Context = HashMap<&'static str, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Upper,
+    Lower,
+    TitleCase,
+    Truncate(usize),
+    Replace(String, String),
+    Default(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Placeholder {
+    name: String,
+    width: Option<usize>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Field(Placeholder),
+    Optional(Vec<Node>),
+}
+
+/// A parsed, validated template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Parse a template, rejecting placeholders that aren't in `fields` and
+    /// widths on fields that aren't in `numeric`
+    pub fn parse(source: &str, fields: &[&str], numeric: &[&str]) -> Result<Self, String> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut pos = 0;
+        let fields = Fields {
+            all: fields,
+            numeric,
+        };
+        let nodes = parse_nodes(&chars, &mut pos, false, fields)?;
+        Ok(Self { nodes })
+    }
+
+    /// Render the template. `escape` is applied to every field value after filters.
+    pub fn render(&self, context: &Context, escape: &dyn Fn(&str) -> String) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, context, escape, &mut out);
+        out
+    }
+}
+
+/// Placeholders a template may use, and which of them are numbers
+#[derive(Clone, Copy)]
+struct Fields<'a> {
+    all: &'a [&'a str],
+    numeric: &'a [&'a str],
+}
+
+fn parse_nodes(
+    chars: &[char],
+    pos: &mut usize,
+    in_section: bool,
+    fields: Fields,
+) -> Result<Vec<Node>, String> {
+    let mut nodes = Vec::new();
+    let mut text = String::new();
+
+    while *pos < chars.len() {
+        let c = chars[*pos];
+        *pos += 1;
+        match c {
+            '\\' => match chars.get(*pos) {
+                Some(&next @ ('{' | '}' | '[' | ']' | '\\')) => {
+                    text.push(next);
+                    *pos += 1;
+                }
+                _ => text.push('\\'),
+            },
+            '{' => {
+                flush_text(&mut text, &mut nodes);
+                let content = read_placeholder(chars, pos)?;
+                nodes.push(Node::Field(parse_placeholder(&content, fields)?));
+            }
+            '}' => return Err("Unmatched '}' in pattern".to_string()),
+            '[' => match section_end(chars, *pos) {
+                Some((_, true)) => {
+                    flush_text(&mut text, &mut nodes);
+                    let inner = parse_nodes(chars, pos, true, fields)?;
+                    nodes.push(Node::Optional(inner));
+                }
+                // Patterns written before optional sections existed use
+                // brackets as plain text, e.g. "{title} [1080p]"
+                Some((end, false)) => {
+                    text.push('[');
+                    text.push_str(&unescape(&chars[*pos..end]));
+                    text.push(']');
+                    *pos = end + 1;
+                }
+                None => text.push('['),
+            },
+            ']' if in_section => {
+                flush_text(&mut text, &mut nodes);
+                return Ok(nodes);
+            }
+            ']' => text.push(']'),
+            _ => text.push(c),
+        }
+    }
+
+    if in_section {
+        return Err("Unclosed '[' in pattern".to_string());
+    }
+    flush_text(&mut text, &mut nodes);
+    Ok(nodes)
+}
+
+/// Index of the ']' closing a '[' whose content starts at `start`, and
+/// whether a placeholder appears before it
+fn section_end(chars: &[char], start: usize) -> Option<(usize, bool)> {
+    let mut depth = 0usize;
+    let mut has_field = false;
+    let mut pos = start;
+    while pos < chars.len() {
+        match chars[pos] {
+            '\\' => pos += 1,
+            '{' => has_field = true,
+            '[' => depth += 1,
+            ']' if depth == 0 => return Some((pos, has_field)),
+            ']' => depth -= 1,
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Text with its backslash escapes resolved
+fn unescape(chars: &[char]) -> String {
+    let mut text = String::new();
+    let mut chars = chars.iter().peekable();
+    while let Some(&c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&&next @ ('{' | '}' | '[' | ']' | '\\'))) => {
+                text.push(next);
+                chars.next();
+            }
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+fn flush_text(text: &mut String, nodes: &mut Vec<Node>) {
+    if !text.is_empty() {
+        nodes.push(Node::Text(std::mem::take(text)));
+    }
+}
+
+/// Read up to the closing '}' (quotes may contain any character)
+fn read_placeholder(chars: &[char], pos: &mut usize) -> Result<String, String> {
+    let mut content = String::new();
+    let mut in_quotes = false;
+    while *pos < chars.len() {
+        let c = chars[*pos];
+        *pos += 1;
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '}' if !in_quotes => return Ok(content),
+            _ => {}
+        }
+        content.push(c);
+    }
+    Err(format!("Unclosed '{{' in pattern: {{{}", content))
+}
+
+/// Split on `separator` outside quotes and parentheses
+fn split_top_level(s: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut depth = 0usize;
+    for c in s.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '(' if !in_quotes => depth += 1,
+            ')' if !in_quotes => depth = depth.saturating_sub(1),
+            _ if c == separator && !in_quotes && depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current);
+    parts
+}
+
+fn parse_placeholder(content: &str, fields: Fields) -> Result<Placeholder, String> {
+    let parts = split_top_level(content, '|');
+    let (name, spec) = match parts[0].split_once(':') {
+        Some((name, spec)) => (name.trim(), Some(spec.trim())),
+        None => (parts[0].trim(), None),
+    };
+
+    if name.is_empty() {
+        return Err("Empty placeholder {} in pattern".to_string());
+    }
+    if !fields.all.contains(&name) {
+        return Err(format!(
+            "Unknown placeholder {{{}}}. Available: {}",
+            name,
+            fields
+                .all
+                .iter()
+                .map(|f| format!("{{{}}}", f))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let width = match spec {
+        Some(spec) => Some(
+            spec.parse::<usize>()
+                .map_err(|_| format!("Invalid format '{}' in {{{}}}", spec, name))?,
+        ),
+        None => None,
+    };
+    if width.is_some() && !fields.numeric.contains(&name) {
+        return Err(format!(
+            "{{{}}} isn't a number, so it can't take a width",
+            name
+        ));
+    }
+
+    let filters = parts[1..]
+        .iter()
+        .map(|f| parse_filter(f.trim(), name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Placeholder {
+        name: name.to_string(),
+        width,
+        filters,
+    })
+}
+
+fn parse_filter(filter: &str, field: &str) -> Result<Filter, String> {
+    let (name, args) = match filter.find('(') {
+        Some(open) if filter.ends_with(')') => {
+            let inner = &filter[open + 1..filter.len() - 1];
+            let args: Vec<String> = split_top_level(inner, ',')
+                .iter()
+                .map(|a| unquote(a))
+                .collect();
+            (filter[..open].trim(), args)
+        }
+        Some(_) => {
+            return Err(format!(
+                "Missing ')' after filter '{}' in {{{}}}",
+                filter, field
+            ))
+        }
+        None => (filter, Vec::new()),
+    };
+
+    let expect_args = |count: usize, usage: &str| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(format!(
+                "Filter {} in {{{}}} expects {}",
+                name, field, usage
+            ))
+        }
+    };
+
+    match name {
+        "upper" => expect_args(0, "no arguments").map(|_| Filter::Upper),
+        "lower" => expect_args(0, "no arguments").map(|_| Filter::Lower),
+        "title_case" => expect_args(0, "no arguments").map(|_| Filter::TitleCase),
+        "truncate" => {
+            expect_args(1, "a length, e.g. truncate(20)")?;
+            args[0].parse().map(Filter::Truncate).map_err(|_| {
+                format!(
+                    "Filter truncate in {{{}}} expects a length, e.g. truncate(20)",
+                    field
+                )
+            })
+        }
+        "replace" => {
+            expect_args(2, "two arguments, e.g. replace( ,.)")?;
+            Ok(Filter::Replace(args[0].clone(), args[1].clone()))
+        }
+        "default" => {
+            expect_args(1, "one argument, e.g. default(Unknown)")?;
+            Ok(Filter::Default(args[0].clone()))
+        }
+        _ => Err(format!("Unknown filter '{}' in {{{}}}", name, field)),
+    }
+}
+
+/// Strip surrounding double quotes; unquoted arguments are kept as written
+fn unquote(arg: &str) -> String {
+    let trimmed = arg.trim();
+    if trimmed.len() >= 2 && trimmed.starts_with('"') && trimmed.ends_with('"') {
+        trimmed[1..trimmed.len() - 1].to_string()
+    } else if trimmed.is_empty() {
+        // Keep a bare space argument, e.g. replace( ,.)
+        arg.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Render nodes into `out`. Returns false if a field was missing.
+fn render_nodes(
+    nodes: &[Node],
+    context: &Context,
+    escape: &dyn Fn(&str) -> String,
+    out: &mut String,
+) -> bool {
+    let mut complete = true;
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Field(placeholder) => match render_field(placeholder, context, escape) {
+                Some(value) => out.push_str(&value),
+                None => complete = false,
+            },
+            Node::Optional(inner) => {
+                let mut section = String::new();
+                if render_nodes(inner, context, escape, &mut section) {
+                    out.push_str(&section);
+                }
+            }
+        }
+    }
+    complete
+}
+
+fn render_field(
+    placeholder: &Placeholder,
+    context: &Context,
+    escape: &dyn Fn(&str) -> String,
+) -> Option<String> {
+    let mut value = match context.get(placeholder.name.as_str()) {
+        Some(Value::Number(n)) => Some(match placeholder.width {
+            Some(width) => format!("{:0width$}", n, width = width),
+            None => n.to_string(),
+        }),
+        Some(Value::Text(s)) if !s.trim().is_empty() => Some(s.clone()),
+        _ => None,
+    };
+
+    for filter in &placeholder.filters {
+        value = match (filter, value) {
+            (Filter::Default(default), None) => Some(default.clone()),
+            (_, None) => None,
+            (filter, Some(v)) => Some(apply_filter(filter, v)),
+        };
+    }
+
+    value.map(|v| escape(&v)).filter(|v| !v.is_empty())
+}
+
+fn apply_filter(filter: &Filter, value: String) -> String {
+    match filter {
+        Filter::Upper => value.to_uppercase(),
+        Filter::Lower => value.to_lowercase(),
+        Filter::TitleCase => value
+            .split(' ')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => {
+                        first.to_uppercase().collect::<String>() + &chars.as_str().to_lowercase()
+                    }
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Filter::Truncate(len) => value
+            .chars()
+            .take(*len)
+            .collect::<String>()
+            .trim_end()
+            .to_string(),
+        Filter::Replace(from, to) if !from.is_empty() => value.replace(from.as_str(), to),
+        Filter::Replace(..) | Filter::Default(_) => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[&str] = &["title", "year", "season", "episode", "episode_title"];
+    const NUMERIC: &[&str] = &["year", "season", "episode"];
+
+    fn context() -> Context {
+        let mut context = Context::new();
+        context.insert("title", Value::Text("The Office".to_string()));
+        context.insert("season", Value::Number(2));
+        context.insert("episode", Value::Number(7));
+        context
+    }
+
+    fn render(source: &str) -> String {
+        Template::parse(source, FIELDS, NUMERIC)
+            .unwrap()
+            .render(&context(), &|s| s.to_string())
+    }
+
+    #[test]
+    fn test_plain_fields_and_padding() {
+        assert_eq!(
+            render("{title} S{season:02}E{episode:03}"),
+            "The Office S02E007"
+        );
+        assert_eq!(render("{episode}"), "7");
+    }
+
+    #[test]
+    fn test_optional_section_dropped_when_field_missing() {
+        assert_eq!(render("{title}[ ({year})]"), "The Office");
+        assert_eq!(render("{title}[ - {episode_title}]"), "The Office");
+        assert_eq!(render("{title}[ - E{episode:02}]"), "The Office - E07");
+    }
+
+    #[test]
+    fn test_nested_sections() {
+        assert_eq!(render("{title}[ S{season}[ ({year})]]"), "The Office S2");
+    }
+
+    #[test]
+    fn test_missing_field_outside_section_is_empty() {
+        assert_eq!(render("{title} {year}"), "The Office ");
+    }
+
+    #[test]
+    fn test_case_filters() {
+        assert_eq!(render("{title|upper}"), "THE OFFICE");
+        assert_eq!(render("{title|lower}"), "the office");
+        assert_eq!(render("{title|lower|title_case}"), "The Office");
+    }
+
+    #[test]
+    fn test_truncate_and_replace_filters() {
+        assert_eq!(render("{title|truncate(4)}"), "The");
+        assert_eq!(render("{title|replace( ,.)}"), "The.Office");
+        assert_eq!(render("{title|replace(\"The \",\"\")}"), "Office");
+    }
+
+    #[test]
+    fn test_default_filter() {
+        assert_eq!(render("{episode_title|default(TBA)}"), "TBA");
+        assert_eq!(render("[{year|default(Unknown)}]"), "Unknown");
+        assert_eq!(render("{title|default(TBA)}"), "The Office");
+    }
+
+    #[test]
+    fn test_brackets_without_fields_are_literal() {
+        assert_eq!(render("{title} [1080p]"), "The Office [1080p]");
+        assert_eq!(render("[x264] {title}[ ({year})]"), "[x264] The Office");
+        assert_eq!(render("{title}[ [HD] {year}]"), "The Office");
+        assert_eq!(render("{title} ["), "The Office [");
+        assert_eq!(render("{title}]"), "The Office]");
+        assert_eq!(render("[\\{x\\}] {title}"), "[{x}] The Office");
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(render("\\[{title}\\]"), "[The Office]");
+        assert_eq!(render("\\{title\\}"), "{title}");
+    }
+
+    #[test]
+    fn test_escape_applied_to_values() {
+        let template = Template::parse("{title}/x", FIELDS, NUMERIC).unwrap();
+        let rendered = template.render(&context(), &|s| s.replace(' ', "_"));
+        assert_eq!(rendered, "The_Office/x");
+    }
+
+    #[test]
+    fn test_unknown_placeholder_is_error() {
+        let err = Template::parse("{title} {resolution}", FIELDS, NUMERIC).unwrap_err();
+        assert!(err.contains("Unknown placeholder {resolution}"));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(Template::parse("{title", FIELDS, NUMERIC).is_err());
+        assert!(Template::parse("title}", FIELDS, NUMERIC).is_err());
+        assert!(Template::parse("{}", FIELDS, NUMERIC).is_err());
+        assert!(Template::parse("{episode:xx}", FIELDS, NUMERIC).is_err());
+        // A width only pads numbers
+        let err = Template::parse("{title:03}", FIELDS, NUMERIC).unwrap_err();
+        assert!(err.contains("{title}"));
+        assert!(Template::parse("{episode_title:2|upper}", FIELDS, NUMERIC).is_err());
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        assert!(Template::parse("{title|shout}", FIELDS, NUMERIC)
+            .unwrap_err()
+            .contains("Unknown filter 'shout'"));
+        assert!(Template::parse("{title|truncate(abc)}", FIELDS, NUMERIC).is_err());
+        assert!(Template::parse("{title|replace(a)}", FIELDS, NUMERIC).is_err());
+        assert!(Template::parse("{title|upper(1)}", FIELDS, NUMERIC).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+//! Scratch directories for tests that touch the filesystem
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Empty directory under the system temp dir, unique to this test process.
+/// `module` keeps tests in different modules from sharing a directory.
+pub fn temp_dir(module: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("reel_{}_{}_{}", module, name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
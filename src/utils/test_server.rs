@@ -0,0 +1,97 @@
+//! Minimal HTTP server for testing API clients against canned responses
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Canned response for requests whose "METHOD /path?query" starts with `prefix`
+pub struct Route {
+    prefix: String,
+    status: u16,
+    body: String,
+}
+
+pub fn route(prefix: &str, status: u16, body: &str) -> Route {
+    Route {
+        prefix: prefix.to_string(),
+        status,
+        body: body.to_string(),
+    }
+}
+
+/// A request the server received
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub line: String, // "METHOD /path?query"
+    pub authorization: Option<String>,
+    pub body: String,
+}
+
+/// Serve `routes` on a random local port for the rest of the test run.
+/// Returns the base URL and a log of received requests. Unmatched
+/// requests get a 404.
+pub fn serve(routes: Vec<Route>) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let requests = Arc::clone(&log);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            respond(stream, &routes, &requests);
+        }
+    });
+
+    (base_url, log)
+}
+
+/// Answer one request, logging it before the response goes out so a test
+/// never sees its client return before the request is recorded
+fn respond(mut stream: TcpStream, routes: &[Route], log: &Mutex<Vec<Request>>) -> Option<()> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+
+    let mut first_line = String::new();
+    reader.read_line(&mut first_line).ok()?;
+    let mut parts = first_line.split_whitespace();
+    let line = format!("{} {}", parts.next()?, parts.next()?);
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':')?;
+        let value = value.trim().to_string();
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.parse().unwrap_or(0),
+            "authorization" => authorization = Some(value),
+            _ => {}
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+
+    let (status, response_body) = routes
+        .iter()
+        .find(|r| line.starts_with(&r.prefix))
+        .map(|r| (r.status, r.body.as_str()))
+        .unwrap_or((404, "{}"));
+    let response = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response_body.len(),
+        response_body
+    );
+    log.lock().unwrap().push(Request {
+        line,
+        authorization,
+        body: String::from_utf8_lossy(&body).to_string(),
+    });
+    stream.write_all(response.as_bytes()).ok()
+}
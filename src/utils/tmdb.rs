@@ -1,28 +1,107 @@
-use crate::model::{MediaMetadata, MediaType, SearchResult};
+use crate::model::{MediaMetadata, MediaType, ProviderKind, SearchResult};
+use crate::utils::http::{self, RequestError};
+use crate::utils::metadata::{
+    best_special_match, episode_metadata, BatchFileInfo, EpisodeDetails, EpisodeListing,
+    MetadataProvider, SeasonLayout,
+};
+use crate::utils::rate_limit::RateLimiter;
+use crate::utils::tmdb_cache::TmdbCache;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
 
 const TMDB_BASE_URL: &str = "https://api.themoviedb.org/3";
 
+/// Episodes of a multi-episode file fetched at the same time
+const MAX_EPISODE_REQUESTS: usize = 4;
+
+/// HTTP client and limiter shared by every `TmdbClient`
+fn shared_http() -> &'static (reqwest::Client, RateLimiter) {
+    static SHARED: OnceLock<(reqwest::Client, RateLimiter)> = OnceLock::new();
+    // TMDB allows roughly 50 requests a second; stay well under it
+    SHARED.get_or_init(|| http::shared_http(20, 20.0))
+}
+
 /// TMDB API client
 pub struct TmdbClient {
     api_key: String,
+    base_url: String,
     client: reqwest::Client,
+    limiter: &'static RateLimiter,
+    cache: Option<TmdbCache>,
+    episode_orders: HashMap<u64, String>, // Episode group ID by show ID
+    group_orders: Mutex<HashMap<String, Arc<GroupOrder>>>, // Fetched groups by ID
+    language: Option<String>,             // e.g. "de-DE"; None = TMDB's default (English)
 }
 
 impl TmdbClient {
     pub fn new(api_key: String) -> Self {
+        let (client, limiter) = shared_http();
         Self {
             api_key,
-            client: reqwest::Client::new(),
+            base_url: TMDB_BASE_URL.to_string(),
+            client: client.clone(),
+            limiter,
+            cache: None,
+            episode_orders: HashMap::new(),
+            group_orders: Mutex::new(HashMap::new()),
+            language: None,
         }
     }
 
+    /// Send requests to another server, e.g. a local stub in tests
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Serve responses from (and save them to) a disk cache
+    pub fn with_cache(mut self, cache: Option<TmdbCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Number episodes of these shows by an episode group instead of aired order
+    pub fn with_episode_orders(mut self, episode_orders: HashMap<u64, String>) -> Self {
+        self.episode_orders = episode_orders;
+        self
+    }
+
+    /// Ask for titles, overviews and episode names in `language`
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// `&language=` query parameter, empty for TMDB's default language
+    fn language_param(&self) -> String {
+        self.language
+            .as_ref()
+            .map(|language| format!("&language={}", language))
+            .unwrap_or_default()
+    }
+
+    /// GET a URL and parse the JSON response
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, String> {
+        let body = self.get_text(url).await?;
+        serde_json::from_str(&body).map_err(|e| format!("Parse error: {}", e))
+    }
+
+    /// GET a URL, going through the cache when one is set
+    async fn get_text(&self, url: &str) -> Result<String, RequestError> {
+        let send = self.limiter.send(|| self.client.get(url));
+        http::get_cached("TMDB", self.cache.as_ref(), url, send).await
+    }
+
     /// Verify API key is valid by making a test request
     pub async fn verify_api_key(&self) -> bool {
-        let url = format!("{}/configuration?api_key={}", TMDB_BASE_URL, self.api_key);
+        let url = format!("{}/configuration?api_key={}", self.base_url, self.api_key);
 
-        match self.client.get(&url).send().await {
-            Ok(response) => response.status().is_success(),
+        match self.limiter.send(|| self.client.get(&url)).await {
+            Ok((status, _)) => status.is_success(),
             Err(_) => false,
         }
     }
@@ -34,31 +113,25 @@ impl TmdbClient {
         year: Option<u32>,
     ) -> Result<Vec<SearchResult>, String> {
         let mut url = format!(
-            "{}/search/movie?api_key={}&query={}&include_adult=false",
-            TMDB_BASE_URL,
+            "{}/search/movie?api_key={}&query={}&include_adult=false{}",
+            self.base_url,
             self.api_key,
-            urlencoding::encode(query)
+            urlencoding::encode(query),
+            self.language_param()
         );
 
         if let Some(y) = year {
             url.push_str(&format!("&year={}", y));
         }
 
-        let response: TmdbSearchResponse = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?
-            .json()
-            .await
-            .map_err(|e| format!("Parse error: {}", e))?;
+        let response: TmdbSearchResponse = self.get_json(&url).await?;
 
         Ok(response
             .results
             .into_iter()
             .map(|r| SearchResult {
-                tmdb_id: r.id,
+                provider: ProviderKind::Tmdb,
+                id: r.id,
                 title: r.title.unwrap_or_else(|| r.name.unwrap_or_default()),
                 year: r
                     .release_date
@@ -68,6 +141,8 @@ impl TmdbClient {
                 overview: r.overview,
                 poster_path: r.poster_path,
                 vote_average: r.vote_average,
+                popularity: r.popularity,
+                confidence: None,
             })
             .collect())
     }
@@ -79,31 +154,25 @@ impl TmdbClient {
         year: Option<u32>,
     ) -> Result<Vec<SearchResult>, String> {
         let mut url = format!(
-            "{}/search/tv?api_key={}&query={}&include_adult=false",
-            TMDB_BASE_URL,
+            "{}/search/tv?api_key={}&query={}&include_adult=false{}",
+            self.base_url,
             self.api_key,
-            urlencoding::encode(query)
+            urlencoding::encode(query),
+            self.language_param()
         );
 
         if let Some(y) = year {
             url.push_str(&format!("&first_air_date_year={}", y));
         }
 
-        let response: TmdbSearchResponse = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?
-            .json()
-            .await
-            .map_err(|e| format!("Parse error: {}", e))?;
+        let response: TmdbSearchResponse = self.get_json(&url).await?;
 
         Ok(response
             .results
             .into_iter()
             .map(|r| SearchResult {
-                tmdb_id: r.id,
+                provider: ProviderKind::Tmdb,
+                id: r.id,
                 title: r.name.unwrap_or_else(|| r.title.unwrap_or_default()),
                 year: r
                     .first_air_date
@@ -113,6 +182,8 @@ impl TmdbClient {
                 overview: r.overview,
                 poster_path: r.poster_path,
                 vote_average: r.vote_average,
+                popularity: r.popularity,
+                confidence: None,
             })
             .collect())
     }
@@ -120,21 +191,14 @@ impl TmdbClient {
     /// Multi-search (movies and TV shows)
     pub async fn search_multi(&self, query: &str) -> Result<Vec<SearchResult>, String> {
         let url = format!(
-            "{}/search/multi?api_key={}&query={}&include_adult=false",
-            TMDB_BASE_URL,
+            "{}/search/multi?api_key={}&query={}&include_adult=false{}",
+            self.base_url,
             self.api_key,
-            urlencoding::encode(query)
+            urlencoding::encode(query),
+            self.language_param()
         );
 
-        let response: TmdbSearchResponse = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?
-            .json()
-            .await
-            .map_err(|e| format!("Parse error: {}", e))?;
+        let response: TmdbSearchResponse = self.get_json(&url).await?;
 
         Ok(response
             .results
@@ -145,7 +209,8 @@ impl TmdbClient {
             .map(|r| {
                 let is_movie = r.media_type.as_deref() == Some("movie");
                 SearchResult {
-                    tmdb_id: r.id,
+                    provider: ProviderKind::Tmdb,
+                    id: r.id,
                     title: if is_movie {
                         r.title.unwrap_or_else(|| r.name.unwrap_or_default())
                     } else {
@@ -168,6 +233,8 @@ impl TmdbClient {
                     overview: r.overview,
                     poster_path: r.poster_path,
                     vote_average: r.vote_average,
+                    popularity: r.popularity,
+                    confidence: None,
                 }
             })
             .collect())
@@ -176,23 +243,20 @@ impl TmdbClient {
     /// Get movie details
     pub async fn get_movie_details(&self, movie_id: u64) -> Result<MediaMetadata, String> {
         let url = format!(
-            "{}/movie/{}?api_key={}",
-            TMDB_BASE_URL, movie_id, self.api_key
+            "{}/movie/{}?api_key={}{}",
+            self.base_url,
+            movie_id,
+            self.api_key,
+            self.language_param()
         );
 
-        let movie: TmdbMovieDetails = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?
-            .json()
-            .await
-            .map_err(|e| format!("Parse error: {}", e))?;
+        let movie: TmdbMovieDetails = self.get_json(&url).await?;
 
         Ok(MediaMetadata {
             tmdb_id: movie.id,
-            title: movie.title,
+            provider: ProviderKind::Tmdb,
+            provider_id: movie.id,
+            title: localized_or_original(movie.title, movie.original_title.as_deref()),
             original_title: movie.original_title,
             year: movie
                 .release_date
@@ -207,23 +271,40 @@ impl TmdbClient {
         })
     }
 
-    /// Get TV show details
-    pub async fn get_tv_details(&self, tv_id: u64) -> Result<MediaMetadata, String> {
-        let url = format!("{}/tv/{}?api_key={}", TMDB_BASE_URL, tv_id, self.api_key);
+    /// Get TV show details along with its season layout
+    pub async fn get_tv_details_with_seasons(
+        &self,
+        tv_id: u64,
+    ) -> Result<(MediaMetadata, SeasonLayout), String> {
+        let url = format!(
+            "{}/tv/{}?api_key={}{}",
+            self.base_url,
+            tv_id,
+            self.api_key,
+            self.language_param()
+        );
 
-        let tv: TmdbTvDetails = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?
-            .json()
-            .await
-            .map_err(|e| format!("Parse error: {}", e))?;
+        let tv: TmdbTvDetails = self.get_json(&url).await?;
 
-        Ok(MediaMetadata {
+        let layout = SeasonLayout::new(
+            tv.seasons
+                .iter()
+                .map(|s| (s.season_number, s.episode_count))
+                .collect(),
+        )
+        .with_premieres(
+            tv.seasons
+                .iter()
+                .filter_map(|s| Some((s.season_number, s.air_date.clone()?)))
+                .collect(),
+        );
+
+        let name = localized_or_original(tv.name, tv.original_name.as_deref());
+        let metadata = MediaMetadata {
             tmdb_id: tv.id,
-            title: tv.name.clone(),
+            provider: ProviderKind::Tmdb,
+            provider_id: tv.id,
+            title: name.clone(),
             original_title: tv.original_name,
             year: tv
                 .first_air_date
@@ -234,53 +315,513 @@ impl TmdbClient {
             backdrop_path: tv.backdrop_path,
             vote_average: tv.vote_average,
             genres: tv.genres.into_iter().map(|g| g.name).collect(),
-            show_name: Some(tv.name),
+            show_name: Some(name),
             ..Default::default()
-        })
+        };
+        Ok((metadata, layout))
     }
 
-    /// Get TV episode details
+    /// Get TV episode details for a file, covering every episode of a multi-episode
+    /// file and mapping absolute numbers onto season/episode
     pub async fn get_episode_details(
+        &self,
+        show: &MediaMetadata,
+        layout: &SeasonLayout,
+        file: &BatchFileInfo,
+    ) -> Result<MediaMetadata, String> {
+        let remapped;
+        let file = match self.episode_orders.get(&show.tmdb_id) {
+            Some(group_id) => {
+                remapped = self.get_group_order(group_id).await?.remap(file);
+                &remapped
+            }
+            None => file,
+        };
+
+        let resolved = match (file.season, file.episode, file.absolute_episode) {
+            (Some(s), Some(e), _) => Some((s, e)),
+            (_, _, Some(absolute)) => layout.to_season_episode(absolute),
+            _ if file.special => match &file.episode_title {
+                Some(name) => self.find_special(show.tmdb_id, name, file.year).await?,
+                None => None,
+            },
+            _ => match &file.air_date {
+                Some(date) => {
+                    self.find_episode_by_date(show.tmdb_id, layout, date)
+                        .await?
+                }
+                None => None,
+            },
+        };
+
+        let Some((season, episode)) = resolved else {
+            let mut metadata = show.clone();
+            metadata.absolute_episode = file.absolute_episode;
+            if file.special {
+                metadata.season_number = Some(0);
+            }
+            return Ok(metadata);
+        };
+
+        let episodes = self
+            .fetch_episodes(show.tmdb_id, season, episode, file.episode_end)
+            .await?;
+        let mut metadata = episode_metadata(show, season, episode, file.episode_end, episodes);
+        metadata.absolute_episode = file
+            .absolute_episode
+            .or_else(|| layout.to_absolute(season, episode));
+        Ok(metadata)
+    }
+
+    /// Image base URL and the sizes TMDB offers for each kind of image
+    pub async fn get_image_config(&self) -> Result<ImageConfig, String> {
+        let url = format!("{}/configuration?api_key={}", self.base_url, self.api_key);
+        let config: TmdbConfiguration = self.get_json(&url).await?;
+        Ok(config.images)
+    }
+
+    /// Poster and backdrop of a show, with the poster of each season
+    pub async fn get_show_artwork(&self, tv_id: u64) -> Result<ShowArtwork, String> {
+        let url = format!(
+            "{}/tv/{}?api_key={}{}",
+            self.base_url,
+            tv_id,
+            self.api_key,
+            self.language_param()
+        );
+
+        let tv: TmdbTvDetails = self.get_json(&url).await?;
+
+        Ok(ShowArtwork {
+            poster_path: tv.poster_path,
+            backdrop_path: tv.backdrop_path,
+            season_posters: tv
+                .seasons
+                .into_iter()
+                .filter_map(|s| Some((s.season_number, s.poster_path?)))
+                .collect(),
+        })
+    }
+
+    /// Download an image, from the disk cache if it was downloaded before
+    pub async fn download_image(&self, url: &str) -> Result<Vec<u8>, String> {
+        if let Some(cache) = &self.cache {
+            if let Some(bytes) = cache.get_image(url) {
+                return Ok(bytes);
+            }
+            if cache.is_offline() {
+                return Err("Offline mode: image not cached".to_string());
+            }
+        }
+
+        let (status, bytes) = self.limiter.send_bytes(|| self.client.get(url)).await?;
+        if !status.is_success() {
+            return Err(format!("Image download failed: HTTP {}", status));
+        }
+
+        if let Some(cache) = &self.cache {
+            // A failed write only costs a download next time
+            let _ = cache.put_image(url, &bytes);
+        }
+        Ok(bytes)
+    }
+
+    /// Download small posters for the search results, keyed by their source
+    /// (a TMDB image path or a full URL). Full URLs, such as TheTVDB's posters,
+    /// are fetched through this client too, so they share TMDB's rate limit and
+    /// image cache.
+    pub async fn download_thumbnails(
+        &self,
+        sources: Vec<String>,
+    ) -> Vec<(String, Result<Vec<u8>, String>)> {
+        let config = if sources.iter().any(|s| !s.starts_with("http")) {
+            Some(self.get_image_config().await)
+        } else {
+            None
+        };
+
+        let downloads = sources.into_iter().map(|source| {
+            let config = &config;
+            async move {
+                let url = if source.starts_with("http") {
+                    Ok(source.clone())
+                } else {
+                    match config {
+                        Some(Ok(config)) => {
+                            Ok(config.url(ImageKind::Poster, THUMBNAIL_SIZE, &source))
+                        }
+                        Some(Err(e)) => Err(e.clone()),
+                        None => Err("No image configuration".to_string()),
+                    }
+                };
+                let result = match url {
+                    Ok(url) => self.download_image(&url).await,
+                    Err(e) => Err(e),
+                };
+                (source, result)
+            }
+        });
+        futures::future::join_all(downloads).await
+    }
+
+    /// List the alternate episode orderings TMDB has for a show
+    pub async fn get_episode_groups(&self, tv_id: u64) -> Result<Vec<EpisodeGroup>, String> {
+        let url = format!(
+            "{}/tv/{}/episode_groups?api_key={}",
+            self.base_url, tv_id, self.api_key
+        );
+
+        let response: TmdbEpisodeGroups = self.get_json(&url).await?;
+
+        Ok(response
+            .results
+            .into_iter()
+            .map(|g| EpisodeGroup {
+                id: g.id,
+                name: g.name,
+                kind: g.kind,
+                episode_count: g.episode_count,
+            })
+            .collect())
+    }
+
+    /// Fetch an episode group's layout, once per client
+    async fn get_group_order(&self, group_id: &str) -> Result<Arc<GroupOrder>, String> {
+        let fetched = self
+            .group_orders
+            .lock()
+            .ok()
+            .and_then(|orders| orders.get(group_id).cloned());
+        if let Some(order) = fetched {
+            return Ok(order);
+        }
+
+        let url = format!(
+            "{}/tv/episode_group/{}?api_key={}",
+            self.base_url, group_id, self.api_key
+        );
+        let details: TmdbEpisodeGroupDetails = self.get_json(&url).await?;
+
+        let order = Arc::new(GroupOrder::new(
+            details
+                .groups
+                .into_iter()
+                .map(|group| {
+                    let mut episodes = group.episodes;
+                    episodes.sort_by_key(|ep| ep.order);
+                    let episodes = episodes
+                        .into_iter()
+                        .map(|ep| (ep.season_number, ep.episode_number))
+                        .collect();
+                    (group.order, episodes)
+                })
+                .collect(),
+        ));
+        if let Ok(mut orders) = self.group_orders.lock() {
+            orders.insert(group_id.to_string(), Arc::clone(&order));
+        }
+        Ok(order)
+    }
+
+    /// Find the episode that aired on `date` (YYYY-MM-DD) by scanning season listings
+    async fn find_episode_by_date(
+        &self,
+        tv_id: u64,
+        layout: &SeasonLayout,
+        date: &str,
+    ) -> Result<Option<(u32, u32)>, String> {
+        for season in layout.seasons_for_date(date) {
+            let listing = self.get_season_listing(tv_id, season).await?;
+            let found = listing
+                .iter()
+                .find(|ep| ep.air_date.as_deref() == Some(date));
+            if let Some(ep) = found {
+                return Ok(Some((season, ep.episode_number)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find a special in season 0 by fuzzy-matching its name
+    async fn find_special(
+        &self,
+        tv_id: u64,
+        name: &str,
+        year: Option<u32>,
+    ) -> Result<Option<(u32, u32)>, String> {
+        let listing = self.get_season_listing(tv_id, 0).await?;
+        Ok(best_special_match(&listing, name, year).map(|episode| (0, episode)))
+    }
+
+    /// Fetch the episode list of a season
+    async fn get_season_listing(
+        &self,
+        tv_id: u64,
+        season: u32,
+    ) -> Result<Vec<EpisodeListing>, String> {
+        let url = format!(
+            "{}/tv/{}/season/{}?api_key={}{}",
+            self.base_url,
+            tv_id,
+            season,
+            self.api_key,
+            self.language_param()
+        );
+
+        let listing: TmdbSeasonDetails = self.get_json(&url).await?;
+
+        Ok(listing.episodes)
+    }
+
+    /// Fetch a single episode, `None` if TMDB doesn't know it
+    async fn fetch_episode(
         &self,
         tv_id: u64,
         season: u32,
         episode: u32,
-    ) -> Result<MediaMetadata, String> {
-        // First get the show details
-        let show = self.get_tv_details(tv_id).await?;
+    ) -> Result<Option<EpisodeDetails>, String> {
+        let url = format!(
+            "{}/tv/{}/season/{}/episode/{}?api_key={}{}",
+            self.base_url,
+            tv_id,
+            season,
+            episode,
+            self.api_key,
+            self.language_param()
+        );
+
+        match self.get_text(&url).await {
+            Ok(body) => {
+                let mut details: EpisodeDetails =
+                    serde_json::from_str(&body).map_err(|e| format!("Parse error: {}", e))?;
+                // Untranslated episodes come back as "Episode 5"; use the original name
+                if self.language.is_some() && is_placeholder_name(&details.name, episode) {
+                    details.name = self
+                        .original_episode_name(tv_id, season, episode)
+                        .await
+                        .unwrap_or_default();
+                }
+                Ok(Some(details))
+            }
+            Err(RequestError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
 
-        // Then get the episode details
+    /// Name of an episode in the show's original language, `None` if TMDB
+    /// only has a placeholder there too
+    async fn original_episode_name(&self, tv_id: u64, season: u32, episode: u32) -> Option<String> {
+        // Same URL as the show lookup, so this is normally a cache hit
         let url = format!(
-            "{}/tv/{}/season/{}/episode/{}?api_key={}",
-            TMDB_BASE_URL, tv_id, season, episode, self.api_key
+            "{}/tv/{}?api_key={}{}",
+            self.base_url,
+            tv_id,
+            self.api_key,
+            self.language_param()
         );
+        let tv: TmdbTvDetails = self.get_json(&url).await.ok()?;
 
-        let ep: TmdbEpisodeDetails = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?
-            .json()
-            .await
-            .map_err(|e| format!("Parse error: {}", e))?;
+        let url = format!(
+            "{}/tv/{}/season/{}/episode/{}?api_key={}&language={}",
+            self.base_url, tv_id, season, episode, self.api_key, tv.original_language?
+        );
+        let details: EpisodeDetails = self.get_json(&url).await.ok()?;
+        (!is_placeholder_name(&details.name, episode)).then_some(details.name)
+    }
 
-        Ok(MediaMetadata {
-            tmdb_id: tv_id,
-            title: show.title.clone(),
-            original_title: show.original_title,
-            year: show.year,
-            overview: ep.overview,
-            poster_path: ep.still_path.or(show.poster_path),
-            backdrop_path: show.backdrop_path,
-            vote_average: ep.vote_average.or(show.vote_average),
-            genres: show.genres,
-            season_number: Some(season),
-            episode_number: Some(episode),
-            episode_title: Some(ep.name),
-            air_date: ep.air_date,
-            show_name: show.show_name,
-        })
+    /// Fetch every episode from `first` to `last` (inclusive), skipping unknown ones
+    async fn fetch_episodes(
+        &self,
+        tv_id: u64,
+        season: u32,
+        first: u32,
+        last: Option<u32>,
+    ) -> Result<Vec<EpisodeDetails>, String> {
+        let last = last.unwrap_or(first).max(first);
+        let requests = (first..=last).map(|e| self.fetch_episode(tv_id, season, e));
+
+        // Keep long ranges from flooding the rate limiter, in episode order
+        let results: Vec<_> = futures::stream::iter(requests)
+            .buffered(MAX_EPISODE_REQUESTS)
+            .collect()
+            .await;
+
+        let mut episodes = Vec::new();
+        for result in results {
+            if let Some(details) = result? {
+                episodes.push(details);
+            }
+        }
+        Ok(episodes)
+    }
+}
+
+/// A localized title, or the original one when TMDB has no translation
+fn localized_or_original(title: String, original: Option<&str>) -> String {
+    match original {
+        Some(original) if title.trim().is_empty() => original.to_string(),
+        _ => title,
+    }
+}
+
+/// Words TMDB puts before the number of an untranslated episode
+const PLACEHOLDER_WORDS: &[&str] = &[
+    "episode",
+    "folge",
+    "épisode",
+    "episodio",
+    "episódio",
+    "aflevering",
+    "avsnitt",
+    "afsnit",
+    "jakso",
+    "odcinek",
+    "epizoda",
+    "epizód",
+    "bölüm",
+    "эпизод",
+    "серия",
+    "епізод",
+    "серія",
+];
+
+/// Whether an episode name is TMDB's stand-in for a missing translation,
+/// e.g. "Episode 5", "Folge 5" or "第5話", or no name at all
+fn is_placeholder_name(name: &str, episode: u32) -> bool {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return true;
+    }
+    let number = episode.to_string();
+    let words: Vec<&str> = name.split_whitespace().collect();
+    match words.as_slice() {
+        [word, n] => PLACEHOLDER_WORDS.contains(word) && *n == number,
+        // Japanese/Chinese "第5話", "第5集", Korean "5화"
+        [word] => {
+            let cjk = word
+                .strip_prefix('第')
+                .and_then(|rest| rest.strip_suffix(['話', '话', '集']));
+            cjk.or_else(|| word.strip_suffix('화')) == Some(number.as_str())
+        }
+        _ => false,
+    }
+}
+
+/// An alternate episode ordering of a show (DVD, absolute, story arc...)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpisodeGroup {
+    pub id: String,
+    pub name: String,
+    pub kind: u8, // TMDB group type, see `kind_name`
+    pub episode_count: u32,
+}
+
+impl EpisodeGroup {
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            1 => "Original air date",
+            2 => "Absolute",
+            3 => "DVD",
+            4 => "Digital",
+            5 => "Story arc",
+            6 => "Production",
+            7 => "TV",
+            _ => "Other",
+        }
+    }
+}
+
+/// Episodes of an episode group as TMDB's default (season, episode) numbers,
+/// grouped by the group's own "seasons" in order
+#[derive(Debug, Clone, Default)]
+pub struct GroupOrder {
+    groups: Vec<(u32, Vec<(u32, u32)>)>, // (group order, episodes)
+}
+
+impl GroupOrder {
+    pub fn new(mut groups: Vec<(u32, Vec<(u32, u32)>)>) -> Self {
+        groups.sort_by_key(|(order, _)| *order);
+        Self { groups }
+    }
+
+    /// Default numbering of episode `episode` of group season `season`
+    pub fn to_default(&self, season: u32, episode: u32) -> Option<(u32, u32)> {
+        let (_, episodes) = self.groups.iter().find(|(order, _)| *order == season)?;
+        episodes.get(episode.checked_sub(1)? as usize).copied()
+    }
+
+    /// Default numbering of the `absolute`-th episode across the group, skipping specials
+    pub fn absolute_to_default(&self, absolute: u32) -> Option<(u32, u32)> {
+        self.groups
+            .iter()
+            .filter(|(order, _)| *order > 0)
+            .flat_map(|(_, episodes)| episodes)
+            .nth(absolute.checked_sub(1)? as usize)
+            .copied()
+    }
+
+    /// Renumber a file parsed in this group's order to TMDB's default order.
+    /// Numbers the group doesn't cover are left alone.
+    pub fn remap(&self, file: &BatchFileInfo) -> BatchFileInfo {
+        let mut remapped = file.clone();
+        let target = match (file.season, file.episode, file.absolute_episode) {
+            (Some(s), Some(e), _) => self.to_default(s, e),
+            (_, _, Some(absolute)) => self.absolute_to_default(absolute),
+            _ => None,
+        };
+        let Some((season, episode)) = target else {
+            return remapped;
+        };
+
+        remapped.season = Some(season);
+        remapped.episode = Some(episode);
+        remapped.absolute_episode = None;
+        // A multi-episode file only stays one if its last episode lands in the same season
+        remapped.episode_end = match (file.season, file.episode_end) {
+            (Some(s), Some(end)) => self
+                .to_default(s, end)
+                .filter(|&(end_season, end)| end_season == season && end > episode)
+                .map(|(_, end)| end),
+            _ => None,
+        };
+        remapped
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for TmdbClient {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Tmdb
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        media_type: MediaType,
+        year: Option<u32>,
+    ) -> Result<Vec<SearchResult>, String> {
+        match media_type {
+            MediaType::Movie => self.search_movies(query, year).await,
+            MediaType::TvShow => self.search_tv(query, year).await,
+            MediaType::Unknown => self.search_multi(query).await,
+        }
+    }
+
+    async fn movie_details(&self, id: u64) -> Result<MediaMetadata, String> {
+        self.get_movie_details(id).await
+    }
+
+    async fn show_details(&self, id: u64) -> Result<(MediaMetadata, SeasonLayout), String> {
+        self.get_tv_details_with_seasons(id).await
+    }
+
+    async fn episode_details(
+        &self,
+        show: &MediaMetadata,
+        layout: &SeasonLayout,
+        file: &BatchFileInfo,
+    ) -> Result<MediaMetadata, String> {
+        self.get_episode_details(show, layout, file).await
     }
 }
 
@@ -301,6 +842,7 @@ struct TmdbSearchResult {
     release_date: Option<String>,
     first_air_date: Option<String>,
     vote_average: Option<f32>,
+    popularity: Option<f32>,
     media_type: Option<String>,
 }
 
@@ -322,21 +864,126 @@ struct TmdbTvDetails {
     id: u64,
     name: String,
     original_name: Option<String>,
+    original_language: Option<String>, // ISO 639-1, e.g. "ja"
     overview: Option<String>,
     poster_path: Option<String>,
     backdrop_path: Option<String>,
     first_air_date: Option<String>,
     vote_average: Option<f32>,
     genres: Vec<TmdbGenre>,
+    #[serde(default)]
+    seasons: Vec<TmdbSeasonSummary>,
 }
 
 #[derive(Deserialize)]
-struct TmdbEpisodeDetails {
-    name: String,
-    overview: Option<String>,
-    still_path: Option<String>,
+struct TmdbSeasonSummary {
+    season_number: u32,
+    #[serde(default)]
+    episode_count: u32,
     air_date: Option<String>,
-    vote_average: Option<f32>,
+    poster_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TmdbConfiguration {
+    images: ImageConfig,
+}
+
+/// Poster size shown next to search results and matches
+pub const THUMBNAIL_SIZE: &str = "w92";
+
+/// Kind of TMDB image, each offered in its own sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    Poster,
+    Backdrop,
+    Still,
+}
+
+/// Where TMDB serves images from and in which sizes
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ImageConfig {
+    pub secure_base_url: String,
+    #[serde(default)]
+    pub poster_sizes: Vec<String>,
+    #[serde(default)]
+    pub backdrop_sizes: Vec<String>,
+    #[serde(default)]
+    pub still_sizes: Vec<String>,
+}
+
+impl ImageConfig {
+    /// URL of the image at `path` in `size`, or in its original size if TMDB
+    /// doesn't offer `size` for this kind of image
+    pub fn url(&self, kind: ImageKind, size: &str, path: &str) -> String {
+        let sizes = match kind {
+            ImageKind::Poster => &self.poster_sizes,
+            ImageKind::Backdrop => &self.backdrop_sizes,
+            ImageKind::Still => &self.still_sizes,
+        };
+        let size = if sizes.iter().any(|s| s == size) {
+            size
+        } else {
+            "original"
+        };
+        format!(
+            "{}/{}/{}",
+            self.secure_base_url.trim_end_matches('/'),
+            size,
+            path.trim_start_matches('/')
+        )
+    }
+}
+
+/// Show-level artwork: TMDB image paths
+#[derive(Debug, Clone, Default)]
+pub struct ShowArtwork {
+    pub poster_path: Option<String>,
+    pub backdrop_path: Option<String>,
+    pub season_posters: HashMap<u32, String>,
+}
+
+#[derive(Deserialize)]
+struct TmdbSeasonDetails {
+    #[serde(default)]
+    episodes: Vec<EpisodeListing>,
+}
+
+#[derive(Deserialize)]
+struct TmdbEpisodeGroups {
+    #[serde(default)]
+    results: Vec<TmdbEpisodeGroup>,
+}
+
+#[derive(Deserialize)]
+struct TmdbEpisodeGroup {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    kind: u8,
+    #[serde(default)]
+    episode_count: u32,
+}
+
+#[derive(Deserialize)]
+struct TmdbEpisodeGroupDetails {
+    #[serde(default)]
+    groups: Vec<TmdbGroupSeason>,
+}
+
+#[derive(Deserialize)]
+struct TmdbGroupSeason {
+    order: u32,
+    #[serde(default)]
+    episodes: Vec<TmdbGroupEpisode>,
+}
+
+#[derive(Deserialize)]
+struct TmdbGroupEpisode {
+    season_number: u32,
+    episode_number: u32,
+    #[serde(default)]
+    order: u32,
 }
 
 #[derive(Deserialize)]
@@ -345,7 +992,7 @@ struct TmdbGenre {
 }
 
 /// URL encoding helper
-mod urlencoding {
+pub(crate) mod urlencoding {
     pub fn encode(s: &str) -> String {
         let mut result = String::new();
         for c in s.chars() {
@@ -374,237 +1021,348 @@ pub async fn verify_api_key(api_key: &str) -> bool {
     client.verify_api_key().await
 }
 
-/// Search for media (auto-detect type)
-pub async fn search_media(
-    api_key: &str,
-    query: &str,
-    media_type: MediaType,
-    year: Option<u32>,
-) -> Result<Vec<SearchResult>, String> {
-    if api_key.is_empty() {
-        return Err("TMDB API key not set".to_string());
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{self, route, Request, Route};
+    use std::sync::{Arc, Mutex};
 
-    let client = TmdbClient::new(api_key.to_string());
+    fn show() -> MediaMetadata {
+        MediaMetadata {
+            tmdb_id: 1396,
+            title: "Breaking Bad".to_string(),
+            show_name: Some("Breaking Bad".to_string()),
+            poster_path: Some("/show.jpg".to_string()),
+            ..Default::default()
+        }
+    }
 
-    match media_type {
-        MediaType::Movie => client.search_movies(query, year).await,
-        MediaType::TvShow => client.search_tv(query, year).await,
-        MediaType::Unknown => client.search_multi(query).await,
+    #[test]
+    fn test_localized_or_original() {
+        assert_eq!(
+            localized_or_original("Das Boot".to_string(), Some("Das Boot")),
+            "Das Boot"
+        );
+        assert_eq!(
+            localized_or_original(String::new(), Some("千と千尋の神隠し")),
+            "千と千尋の神隠し"
+        );
+        assert_eq!(localized_or_original(String::new(), None), "");
     }
-}
 
-/// Fetch full metadata for a search result
-pub async fn fetch_metadata(
-    api_key: &str,
-    result: &SearchResult,
-    season: Option<u32>,
-    episode: Option<u32>,
-) -> Result<MediaMetadata, String> {
-    if api_key.is_empty() {
-        return Err("TMDB API key not set".to_string());
+    #[test]
+    fn test_is_placeholder_name() {
+        assert!(is_placeholder_name("Episode 5", 5));
+        assert!(is_placeholder_name("Folge 5", 5));
+        assert!(is_placeholder_name("Épisode 5", 5));
+        assert!(is_placeholder_name("Серия 5", 5));
+        assert!(is_placeholder_name("第5話", 5));
+        assert!(is_placeholder_name("", 5));
+        assert!(!is_placeholder_name("Episode 5", 6));
+        assert!(!is_placeholder_name("Pilot", 1));
+        // Real titles that happen to be a word and the episode number
+        assert!(!is_placeholder_name("Chapter 5", 5));
+        assert!(!is_placeholder_name("Part 2", 2));
+        assert!(!is_placeholder_name("Apollo 13", 13));
     }
 
-    let client = TmdbClient::new(api_key.to_string());
+    // ==================== EPISODE GROUPS ====================
 
-    match result.media_type {
-        MediaType::Movie => client.get_movie_details(result.tmdb_id).await,
-        MediaType::TvShow => {
-            if let (Some(s), Some(e)) = (season, episode) {
-                // Try to get episode details, fall back to TV show details with season/episode info
-                match client.get_episode_details(result.tmdb_id, s, e).await {
-                    Ok(metadata) => Ok(metadata),
-                    Err(_) => {
-                        // Episode not found on TMDB, get show details and add season/episode from parsed info
-                        let mut metadata = client.get_tv_details(result.tmdb_id).await?;
-                        metadata.season_number = Some(s);
-                        metadata.episode_number = Some(e);
-                        Ok(metadata)
-                    }
-                }
-            } else {
-                client.get_tv_details(result.tmdb_id).await
-            }
-        }
-        MediaType::Unknown => {
-            // For unknown type, try multi-search approach - default to movie
-            client.get_movie_details(result.tmdb_id).await
+    fn tv_file(season: Option<u32>, episode: Option<u32>) -> BatchFileInfo {
+        BatchFileInfo {
+            index: 0,
+            title: "Firefly".to_string(),
+            year: None,
+            season,
+            episode,
+            episode_end: None,
+            absolute_episode: None,
+            air_date: None,
+            special: false,
+            episode_title: None,
+            media_type: MediaType::TvShow,
         }
     }
-}
 
-/// File info for batch matching
-#[derive(Clone)]
-pub struct BatchFileInfo {
-    pub index: usize,
-    pub title: String,
-    pub year: Option<u32>,
-    pub season: Option<u32>,
-    pub episode: Option<u32>,
-    pub media_type: MediaType,
-}
+    /// Firefly's DVD order: the pilot aired last
+    fn dvd_order() -> GroupOrder {
+        GroupOrder::new(vec![
+            (1, vec![(1, 11), (1, 1), (1, 2), (1, 3)]),
+            (0, vec![(0, 1)]),
+        ])
+    }
 
-/// Optimized batch matching - groups by title, fetches show once, episodes in parallel
-pub async fn batch_match_files(
-    api_key: &str,
-    files: Vec<BatchFileInfo>,
-) -> Vec<(usize, Result<MediaMetadata, String>)> {
-    use futures::future::join_all;
-    use std::collections::HashMap;
+    #[test]
+    fn test_group_order_to_default() {
+        let order = dvd_order();
+        assert_eq!(order.to_default(1, 1), Some((1, 11)));
+        assert_eq!(order.to_default(1, 2), Some((1, 1)));
+        assert_eq!(order.to_default(0, 1), Some((0, 1)));
+        assert_eq!(order.to_default(1, 9), None);
+        assert_eq!(order.to_default(2, 1), None);
+        assert_eq!(order.to_default(1, 0), None);
+    }
 
-    if api_key.is_empty() {
-        return files
-            .iter()
-            .map(|f| (f.index, Err("TMDB API key not set".to_string())))
-            .collect();
+    #[test]
+    fn test_group_order_absolute_skips_specials() {
+        let order = dvd_order();
+        assert_eq!(order.absolute_to_default(1), Some((1, 11)));
+        assert_eq!(order.absolute_to_default(4), Some((1, 3)));
+        assert_eq!(order.absolute_to_default(5), None);
     }
 
-    let client = TmdbClient::new(api_key.to_string());
-    let mut results: Vec<(usize, Result<MediaMetadata, String>)> = Vec::new();
+    #[test]
+    fn test_group_order_remap() {
+        let order = dvd_order();
 
-    // Separate movies and TV shows
-    let mut movies: Vec<BatchFileInfo> = Vec::new();
-    let mut tv_shows: HashMap<String, Vec<BatchFileInfo>> = HashMap::new();
+        let remapped = order.remap(&tv_file(Some(1), Some(1)));
+        assert_eq!((remapped.season, remapped.episode), (Some(1), Some(11)));
 
-    for file in files {
-        if file.title.is_empty() {
-            results.push((file.index, Err("No title parsed".to_string())));
-            continue;
-        }
+        // Episodes the group doesn't cover keep their numbers
+        let remapped = order.remap(&tv_file(Some(3), Some(5)));
+        assert_eq!((remapped.season, remapped.episode), (Some(3), Some(5)));
 
-        match file.media_type {
-            MediaType::Movie | MediaType::Unknown => movies.push(file),
-            MediaType::TvShow => {
-                let key = file.title.to_lowercase();
-                tv_shows.entry(key).or_default().push(file);
-            }
-        }
+        let mut double = tv_file(Some(1), Some(2));
+        double.episode_end = Some(3);
+        let remapped = order.remap(&double);
+        assert_eq!(remapped.episode, Some(1));
+        assert_eq!(remapped.episode_end, Some(2));
+
+        // Out of order in default numbering, so no longer a range
+        let mut double = tv_file(Some(1), Some(1));
+        double.episode_end = Some(2);
+        assert_eq!(order.remap(&double).episode_end, None);
+
+        let mut absolute = tv_file(None, None);
+        absolute.absolute_episode = Some(2);
+        let remapped = order.remap(&absolute);
+        assert_eq!((remapped.season, remapped.episode), (Some(1), Some(1)));
+        assert_eq!(remapped.absolute_episode, None);
     }
 
-    // Process movies (each needs individual search)
-    for movie in movies {
-        match client.search_movies(&movie.title, movie.year).await {
-            Ok(search_results) if !search_results.is_empty() => {
-                match client.get_movie_details(search_results[0].tmdb_id).await {
-                    Ok(metadata) => results.push((movie.index, Ok(metadata))),
-                    Err(e) => results.push((movie.index, Err(e))),
-                }
-            }
-            Ok(_) => results.push((movie.index, Err("No results found".to_string()))),
-            Err(e) => results.push((movie.index, Err(e))),
-        }
-        // Small delay for movies
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    #[test]
+    fn test_episode_group_kind_name() {
+        let group = EpisodeGroup {
+            id: "x".to_string(),
+            name: "DVD Order".to_string(),
+            kind: 3,
+            episode_count: 14,
+        };
+        assert_eq!(group.kind_name(), "DVD");
+        assert_eq!(EpisodeGroup { kind: 42, ..group }.kind_name(), "Other");
     }
 
-    // Process TV shows - grouped by title
-    for (_, episodes) in tv_shows {
-        if episodes.is_empty() {
-            continue;
-        }
+    // ==================== STUB SERVER ====================
 
-        let first = &episodes[0];
+    fn stub_client(routes: Vec<Route>) -> (TmdbClient, Arc<Mutex<Vec<Request>>>) {
+        let (base_url, log) = test_server::serve(routes);
+        let client = TmdbClient::new("test-key".to_string()).with_base_url(base_url);
+        (client, log)
+    }
 
-        // Search for the show ONCE
-        let search_result = match client.search_tv(&first.title, first.year).await {
-            Ok(r) if !r.is_empty() => r[0].clone(),
-            Ok(_) => {
-                for ep in &episodes {
-                    results.push((ep.index, Err("No results found".to_string())));
-                }
-                continue;
-            }
-            Err(e) => {
-                for ep in &episodes {
-                    results.push((ep.index, Err(e.clone())));
-                }
-                continue;
-            }
-        };
+    #[tokio::test]
+    async fn test_search_movies_against_stub_server() {
+        let (client, log) = stub_client(vec![route(
+            "GET /search/movie",
+            200,
+            r#"{"results":[{"id":603,"title":"The Matrix","release_date":"1999-03-31","popularity":80.5}]}"#,
+        )]);
 
-        // Get show details ONCE
-        let show_details = match client.get_tv_details(search_result.tmdb_id).await {
-            Ok(d) => d,
-            Err(e) => {
-                for ep in &episodes {
-                    results.push((ep.index, Err(e.clone())));
-                }
-                continue;
-            }
+        let results = client
+            .search_movies("The Matrix", Some(1999))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, 603);
+        assert_eq!(results[0].provider, ProviderKind::Tmdb);
+        assert_eq!(results[0].year, Some(1999));
+
+        let requests = log.lock().unwrap();
+        assert!(requests[0].line.contains("query=The%20Matrix"));
+        assert!(requests[0].line.contains("year=1999"));
+    }
+
+    #[tokio::test]
+    async fn test_api_errors_reach_the_caller() {
+        let (client, _) = stub_client(vec![route(
+            "GET /search/tv",
+            401,
+            r#"{"status_code":7,"status_message":"Invalid API key: You must be granted a valid key.","success":false}"#,
+        )]);
+
+        let error = client.search_tv("Anything", None).await.unwrap_err();
+        assert_eq!(
+            error,
+            "TMDB error: Invalid API key: You must be granted a valid key."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_episode_keeps_show_metadata() {
+        // Episode requests fall through to the stub's 404
+        let (client, _) = stub_client(Vec::new());
+        let file = BatchFileInfo {
+            index: 0,
+            title: "Breaking Bad".to_string(),
+            year: None,
+            season: Some(9),
+            episode: Some(1),
+            episode_end: None,
+            absolute_episode: None,
+            air_date: None,
+            special: false,
+            episode_title: None,
+            media_type: MediaType::TvShow,
         };
 
-        // Fetch all episodes concurrently (in batches to avoid rate limits)
-        let batch_size = 5; // 5 concurrent requests
-        for chunk in episodes.chunks(batch_size) {
-            let futures: Vec<_> = chunk
-                .iter()
-                .map(|ep| {
-                    let client = TmdbClient::new(api_key.to_string());
-                    let tmdb_id = search_result.tmdb_id;
-                    let show = show_details.clone();
-                    let season = ep.season;
-                    let episode_num = ep.episode;
-                    let index = ep.index;
-
-                    async move {
-                        if let (Some(s), Some(e)) = (season, episode_num) {
-                            // Fetch episode directly (without re-fetching show details)
-                            let url = format!(
-                                "{}/tv/{}/season/{}/episode/{}?api_key={}",
-                                TMDB_BASE_URL, tmdb_id, s, e, client.api_key
-                            );
-
-                            match client.client.get(&url).send().await {
-                                Ok(response) => {
-                                    match response.json::<TmdbEpisodeDetails>().await {
-                                        Ok(ep_details) => {
-                                            let metadata = MediaMetadata {
-                                                tmdb_id,
-                                                title: show.title.clone(),
-                                                original_title: show.original_title.clone(),
-                                                year: show.year,
-                                                overview: ep_details.overview,
-                                                poster_path: ep_details
-                                                    .still_path
-                                                    .or(show.poster_path.clone()),
-                                                backdrop_path: show.backdrop_path.clone(),
-                                                vote_average: ep_details
-                                                    .vote_average
-                                                    .or(show.vote_average),
-                                                genres: show.genres.clone(),
-                                                season_number: Some(s),
-                                                episode_number: Some(e),
-                                                episode_title: Some(ep_details.name),
-                                                air_date: ep_details.air_date,
-                                                show_name: show.show_name.clone(),
-                                            };
-                                            (index, Ok(metadata))
-                                        }
-                                        Err(_) => {
-                                            // Episode not found, use show details with parsed info
-                                            let mut metadata = show.clone();
-                                            metadata.season_number = Some(s);
-                                            metadata.episode_number = Some(e);
-                                            (index, Ok(metadata))
-                                        }
-                                    }
-                                }
-                                Err(e) => (index, Err(format!("Network error: {}", e))),
-                            }
-                        } else {
-                            (index, Ok(show.clone()))
-                        }
-                    }
-                })
-                .collect();
+        let metadata = client
+            .episode_details(&show(), &SeasonLayout::default(), &file)
+            .await
+            .unwrap();
+        assert_eq!(metadata.title, "Breaking Bad");
+        assert_eq!(metadata.season_number, Some(9));
+        assert_eq!(metadata.episode_title, None);
+    }
+
+    #[tokio::test]
+    async fn test_language_is_requested_and_falls_back() {
+        let (client, log) = stub_client(vec![
+            route(
+                "GET /tv/1396?",
+                200,
+                r#"{"id":1396,"name":"","original_name":"Breaking Bad","original_language":"en",
+                    "genres":[],"seasons":[{"season_number":1,"episode_count":7}]}"#,
+            ),
+            route(
+                "GET /tv/1396/season/1/episode/2?api_key=test-key&language=en",
+                200,
+                r#"{"name":"Cat's in the Bag..."}"#,
+            ),
+            route(
+                "GET /tv/1396/season/1/episode/1",
+                200,
+                r#"{"name":"Der Einstieg"}"#,
+            ),
+            route(
+                "GET /tv/1396/season/1/episode/2",
+                200,
+                r#"{"name":"Episode 2"}"#,
+            ),
+        ]);
+        let client = client.with_language(Some("de-DE".to_string()));
+
+        let (show, layout) = client.get_tv_details_with_seasons(1396).await.unwrap();
+        assert_eq!(show.title, "Breaking Bad");
+        assert_eq!(show.show_name.as_deref(), Some("Breaking Bad"));
 
-            let batch_results = join_all(futures).await;
-            results.extend(batch_results);
+        let mut file = tv_file(Some(1), Some(1));
+        let metadata = client
+            .get_episode_details(&show, &layout, &file)
+            .await
+            .unwrap();
+        assert_eq!(metadata.episode_title.as_deref(), Some("Der Einstieg"));
+
+        // An untranslated episode gets its original title rather than "Episode 2"
+        file.episode = Some(2);
+        let metadata = client
+            .get_episode_details(&show, &layout, &file)
+            .await
+            .unwrap();
+        assert_eq!(
+            metadata.episode_title.as_deref(),
+            Some("Cat's in the Bag...")
+        );
+
+        let requests = log.lock().unwrap();
+        let original: Vec<_> = requests
+            .iter()
+            .filter(|r| !r.line.contains("&language=de-DE"))
+            .collect();
+        assert_eq!(original.len(), 1);
+        assert!(original[0].line.ends_with("&language=en"));
+    }
+
+    #[tokio::test]
+    async fn test_episode_details_follow_chosen_episode_group() {
+        let (client, log) = stub_client(vec![
+            route(
+                "GET /tv/1437/episode_groups",
+                200,
+                r#"{"results":[{"id":"dvd","name":"DVD Order","type":3,"episode_count":14}]}"#,
+            ),
+            route(
+                "GET /tv/episode_group/dvd",
+                200,
+                r#"{"groups":[{"order":1,"episodes":[
+                    {"season_number":1,"episode_number":1,"order":1},
+                    {"season_number":1,"episode_number":11,"order":0}]}]}"#,
+            ),
+            route(
+                "GET /tv/1437/season/1/episode/11",
+                200,
+                r#"{"name":"Serenity","air_date":"2002-12-20"}"#,
+            ),
+        ]);
+        let client = client.with_episode_orders(HashMap::from([(1437, "dvd".to_string())]));
+
+        let groups = client.get_episode_groups(1437).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].id, "dvd");
+        assert_eq!(groups[0].kind_name(), "DVD");
 
-            // Small delay between batches
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let firefly = MediaMetadata {
+            tmdb_id: 1437,
+            title: "Firefly".to_string(),
+            show_name: Some("Firefly".to_string()),
+            ..Default::default()
+        };
+        let layout = SeasonLayout::default();
+        for _ in 0..2 {
+            let metadata = client
+                .get_episode_details(&firefly, &layout, &tv_file(Some(1), Some(1)))
+                .await
+                .unwrap();
+            assert_eq!(metadata.season_number, Some(1));
+            assert_eq!(metadata.episode_number, Some(11));
+            assert_eq!(metadata.episode_title.as_deref(), Some("Serenity"));
         }
+
+        // The group layout is fetched once per client
+        let requests = log.lock().unwrap();
+        let group_fetches = requests
+            .iter()
+            .filter(|r| r.line.starts_with("GET /tv/episode_group/"))
+            .count();
+        assert_eq!(group_fetches, 1);
     }
 
-    results
+    #[tokio::test]
+    async fn test_thumbnails_share_one_image_config() {
+        let (images, _) = test_server::serve(vec![
+            route("GET /t/p/w92/dune.jpg", 200, "DUNE"),
+            route("GET /artworks/dune.jpg", 200, "TVDB"),
+        ]);
+        let config = format!(
+            r#"{{"images":{{"secure_base_url":"{}/t/p/","poster_sizes":["w92","w500","original"]}}}}"#,
+            images
+        );
+        let (client, log) = stub_client(vec![route("GET /configuration", 200, &config)]);
+
+        let full_url = format!("{}/artworks/dune.jpg", images);
+        let thumbnails = client
+            .download_thumbnails(vec![
+                "/dune.jpg".to_string(),
+                full_url.clone(),
+                "/missing.jpg".to_string(),
+            ])
+            .await;
+
+        assert_eq!(
+            thumbnails[0],
+            ("/dune.jpg".to_string(), Ok(b"DUNE".to_vec()))
+        );
+        assert_eq!(thumbnails[1], (full_url, Ok(b"TVDB".to_vec())));
+        assert!(thumbnails[2].1.is_err());
+        assert_eq!(log.lock().unwrap().len(), 1);
+    }
 }
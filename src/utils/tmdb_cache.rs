@@ -0,0 +1,348 @@
+use crate::settings::AppSettings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How long cached metadata responses stay fresh, per endpoint type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheTtl {
+    pub search_hours: u64,
+    pub details_hours: u64,
+    pub episodes_hours: u64,
+}
+
+impl Default for CacheTtl {
+    fn default() -> Self {
+        Self {
+            search_hours: 24,
+            details_hours: 24 * 7,
+            episodes_hours: 24 * 3,
+        }
+    }
+}
+
+/// Kind of endpoint a request hits, each with its own TTL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Search,
+    Details,
+    Episodes,
+}
+
+impl Endpoint {
+    /// Classify a request URL by its path (TMDB or TheTVDB)
+    pub fn from_url(url: &str) -> Self {
+        let path = url.split('?').next().unwrap_or(url);
+        if path.contains("/search/") || path.ends_with("/search") {
+            Endpoint::Search
+        } else if path.contains("/season/") || path.contains("/episodes/") {
+            Endpoint::Episodes
+        } else {
+            Endpoint::Details
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Endpoint::Search => "search",
+            Endpoint::Details => "details",
+            Endpoint::Episodes => "episodes",
+        }
+    }
+}
+
+impl CacheTtl {
+    fn seconds(&self, endpoint: Endpoint) -> u64 {
+        let hours = match endpoint {
+            Endpoint::Search => self.search_hours,
+            Endpoint::Details => self.details_hours,
+            Endpoint::Episodes => self.episodes_hours,
+        };
+        hours * 60 * 60
+    }
+}
+
+/// A cached response body
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    stored_at: u64,
+    body: String,
+}
+
+/// Persistent cache of TMDB (and TheTVDB) responses, one JSON file per request
+#[derive(Debug, Clone)]
+pub struct TmdbCache {
+    dir: PathBuf,
+    ttl: CacheTtl,
+    offline: bool,
+}
+
+impl TmdbCache {
+    pub fn new(dir: PathBuf, ttl: CacheTtl, offline: bool) -> Self {
+        Self { dir, ttl, offline }
+    }
+
+    /// Cache under the app's data directory
+    pub fn open_default(ttl: CacheTtl, offline: bool) -> Option<Self> {
+        Self::default_dir().map(|dir| Self::new(dir, ttl, offline))
+    }
+
+    pub fn default_dir() -> Option<PathBuf> {
+        AppSettings::data_dir().map(|dir| dir.join("tmdb_cache"))
+    }
+
+    /// Serve only from the cache, never touching the network
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Cached body for a request URL if it's still fresh
+    pub fn get(&self, url: &str) -> Option<String> {
+        let max_age = self.ttl.seconds(Endpoint::from_url(url));
+        self.read(url)
+            .filter(|entry| now().saturating_sub(entry.stored_at) <= max_age)
+            .map(|entry| entry.body)
+    }
+
+    /// Cached body for a request URL regardless of age
+    pub fn get_stale(&self, url: &str) -> Option<String> {
+        self.read(url).map(|entry| entry.body)
+    }
+
+    /// Store a response body for a request URL
+    pub fn put(&self, url: &str, body: &str) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        let entry = CacheEntry {
+            key: cache_key(url),
+            stored_at: now(),
+            body: body.to_string(),
+        };
+        let contents = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize cache entry: {}", e))?;
+        fs::write(self.entry_path(url), contents)
+            .map_err(|e| format!("Failed to write cache entry: {}", e))
+    }
+
+    /// Cached image for an image URL. Images at a given path never change, so
+    /// they don't expire.
+    pub fn get_image(&self, url: &str) -> Option<Vec<u8>> {
+        fs::read(self.image_path(url)).ok()
+    }
+
+    /// Store a downloaded image for an image URL
+    pub fn put_image(&self, url: &str, bytes: &[u8]) -> Result<(), String> {
+        let path = self.image_path(url);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        fs::write(path, bytes).map_err(|e| format!("Failed to write cached image: {}", e))
+    }
+
+    /// Delete every cached response and image, returning how many were removed
+    pub fn clear(&self) -> Result<usize, String> {
+        Ok(clear_dir(&self.dir, Some("json"))? + clear_dir(&self.dir.join("images"), None)?)
+    }
+
+    fn read(&self, url: &str) -> Option<CacheEntry> {
+        let contents = fs::read_to_string(self.entry_path(url)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&contents).ok()?;
+        // Guard against hash collisions
+        (entry.key == cache_key(url)).then_some(entry)
+    }
+
+    fn image_path(&self, url: &str) -> PathBuf {
+        let extension = Path::new(url)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "img".to_string());
+        self.dir
+            .join("images")
+            .join(format!("{:016x}.{}", fnv1a(url.as_bytes()), extension))
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        let key = cache_key(url);
+        let endpoint = Endpoint::from_url(url).name();
+        self.dir
+            .join(format!("{}-{:016x}.json", endpoint, fnv1a(key.as_bytes())))
+    }
+}
+
+/// Remove the files in `dir` with the given extension (or all files)
+fn clear_dir(dir: &Path, extension: Option<&str>) -> Result<usize, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(format!("Failed to read cache directory: {}", e)),
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let matches = match extension {
+            Some(extension) => path.extension().is_some_and(|ext| ext == extension),
+            None => path.is_file(),
+        };
+        if matches {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove cache entry: {}", e))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Request URL without the API key, so keys can change without losing the cache
+fn cache_key(url: &str) -> String {
+    match url.split_once('?') {
+        Some((path, query)) => {
+            let params: Vec<&str> = query
+                .split('&')
+                .filter(|param| !param.starts_with("api_key="))
+                .collect();
+            if params.is_empty() {
+                path.to_string()
+            } else {
+                format!("{}?{}", path, params.join("&"))
+            }
+        }
+        None => url.to_string(),
+    }
+}
+
+/// 64-bit FNV-1a, stable across builds unlike std's hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::temp_dir;
+
+    fn temp_cache(name: &str, ttl: CacheTtl) -> TmdbCache {
+        TmdbCache::new(temp_dir("tmdb_cache", name), ttl, false)
+    }
+
+    #[test]
+    fn test_endpoint_from_url() {
+        let base = "https://api.themoviedb.org/3";
+        assert_eq!(
+            Endpoint::from_url(&format!("{}/search/tv?query=x", base)),
+            Endpoint::Search
+        );
+        assert_eq!(
+            Endpoint::from_url(&format!("{}/movie/603", base)),
+            Endpoint::Details
+        );
+        assert_eq!(
+            Endpoint::from_url(&format!("{}/tv/1396/season/1/episode/2", base)),
+            Endpoint::Episodes
+        );
+
+        let tvdb = "https://api4.thetvdb.com/v4";
+        assert_eq!(
+            Endpoint::from_url(&format!("{}/search?query=x&type=series", tvdb)),
+            Endpoint::Search
+        );
+        assert_eq!(
+            Endpoint::from_url(&format!("{}/series/81189/extended", tvdb)),
+            Endpoint::Details
+        );
+        assert_eq!(
+            Endpoint::from_url(&format!("{}/series/81189/episodes/default?page=0", tvdb)),
+            Endpoint::Episodes
+        );
+    }
+
+    #[test]
+    fn test_cache_key_ignores_api_key() {
+        assert_eq!(
+            cache_key("https://x/3/search/movie?api_key=abc&query=Matrix"),
+            "https://x/3/search/movie?query=Matrix"
+        );
+        assert_eq!(
+            cache_key("https://x/3/tv/1?api_key=abc"),
+            "https://x/3/tv/1"
+        );
+        assert_eq!(
+            cache_key("https://x/3/tv/1?api_key=abc"),
+            cache_key("https://x/3/tv/1?api_key=other")
+        );
+    }
+
+    #[test]
+    fn test_put_get_and_clear() {
+        let cache = temp_cache("roundtrip", CacheTtl::default());
+        let url = "https://x/3/movie/603?api_key=abc";
+
+        assert!(cache.get(url).is_none());
+        cache.put(url, r#"{"id":603}"#).unwrap();
+        assert_eq!(cache.get(url).as_deref(), Some(r#"{"id":603}"#));
+        // A different key reaches the same entry
+        assert!(cache.get("https://x/3/movie/603?api_key=xyz").is_some());
+        assert!(cache.get("https://x/3/movie/604?api_key=abc").is_none());
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert!(cache.get(url).is_none());
+        assert_eq!(cache.clear().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_images_are_cached_and_cleared() {
+        let cache = temp_cache("images", CacheTtl::default());
+        let url = "https://image.tmdb.org/t/p/w500/poster.jpg";
+
+        assert!(cache.get_image(url).is_none());
+        cache.put_image(url, b"\xff\xd8jpeg").unwrap();
+        assert_eq!(cache.get_image(url).unwrap(), b"\xff\xd8jpeg");
+        assert!(cache.image_path(url).to_string_lossy().ends_with(".jpg"));
+        assert!(cache
+            .get_image("https://image.tmdb.org/t/p/original/poster.jpg")
+            .is_none());
+
+        cache.put("https://x/3/movie/603", "{}").unwrap();
+        assert_eq!(cache.clear().unwrap(), 2);
+        assert!(cache.get_image(url).is_none());
+    }
+
+    #[test]
+    fn test_expired_entries_are_only_served_stale() {
+        let ttl = CacheTtl {
+            search_hours: 0,
+            ..CacheTtl::default()
+        };
+        let cache = temp_cache("expired", ttl);
+        let search = "https://x/3/search/tv?query=Office";
+        let details = "https://x/3/tv/2316";
+
+        cache.put(search, "[]").unwrap();
+        cache.put(details, "{}").unwrap();
+
+        // Written "now", so a zero TTL is still fresh for this second; backdate it
+        let path = cache.entry_path(search);
+        let mut entry: CacheEntry =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        entry.stored_at -= 10;
+        fs::write(&path, serde_json::to_string(&entry).unwrap()).unwrap();
+
+        assert!(cache.get(search).is_none());
+        assert_eq!(cache.get_stale(search).as_deref(), Some("[]"));
+        assert!(cache.get(details).is_some());
+
+        cache.clear().unwrap();
+    }
+}
@@ -0,0 +1,699 @@
+use crate::model::{MediaMetadata, MediaType, ProviderKind, SearchResult};
+use crate::utils::http;
+use crate::utils::metadata::{
+    best_special_match, episode_metadata, BatchFileInfo, EpisodeDetails, EpisodeListing,
+    MetadataProvider, SeasonLayout,
+};
+use crate::utils::rate_limit::RateLimiter;
+use crate::utils::tmdb::urlencoding;
+use crate::utils::tmdb_cache::TmdbCache;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+
+const TVDB_BASE_URL: &str = "https://api4.thetvdb.com/v4";
+/// Stop paging through a show's episodes after this many pages (500 episodes each)
+const MAX_EPISODE_PAGES: u32 = 20;
+
+/// Which of TheTVDB's episode orderings to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeasonType {
+    #[default]
+    Default, // Aired order, as used by Sonarr
+    Official,
+    Dvd,
+    Absolute,
+    Alternate,
+    Regional,
+}
+
+impl SeasonType {
+    fn slug(&self) -> &'static str {
+        match self {
+            SeasonType::Default => "default",
+            SeasonType::Official => "official",
+            SeasonType::Dvd => "dvd",
+            SeasonType::Absolute => "absolute",
+            SeasonType::Alternate => "alternate",
+            SeasonType::Regional => "regional",
+        }
+    }
+}
+
+/// TheTVDB credentials and episode ordering
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TvdbSettings {
+    pub api_key: String,
+    pub pin: String, // Subscriber PIN, only needed for user-supported keys
+    pub season_type: SeasonType,
+}
+
+/// HTTP client and limiter shared by every `TvdbClient`
+fn shared_http() -> &'static (reqwest::Client, RateLimiter) {
+    static SHARED: OnceLock<(reqwest::Client, RateLimiter)> = OnceLock::new();
+    SHARED.get_or_init(|| http::shared_http(10, 10.0))
+}
+
+/// Login tokens by server, API key and PIN; they're valid for a month, so one
+/// login covers every lookup in a session
+fn tokens() -> &'static Mutex<HashMap<String, String>> {
+    static TOKENS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn parse_year(year: Option<&str>) -> Option<u32> {
+    year?.get(..4)?.parse().ok()
+}
+
+/// TheTVDB v4 API client
+pub struct TvdbClient {
+    api_key: String,
+    pin: String,
+    season_type: SeasonType,
+    base_url: String,
+    client: reqwest::Client,
+    limiter: &'static RateLimiter,
+    cache: Option<TmdbCache>,
+    episodes: Mutex<HashMap<u64, Arc<Vec<TvdbEpisode>>>>, // By series ID
+}
+
+impl TvdbClient {
+    pub fn new(api_key: String) -> Self {
+        let (client, limiter) = shared_http();
+        Self {
+            api_key,
+            pin: String::new(),
+            season_type: SeasonType::Default,
+            base_url: TVDB_BASE_URL.to_string(),
+            client: client.clone(),
+            limiter,
+            cache: None,
+            episodes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Create a client from saved settings
+    pub fn from_settings(settings: &TvdbSettings) -> Self {
+        let mut client = Self::new(settings.api_key.clone());
+        client.pin = settings.pin.clone();
+        client.season_type = settings.season_type;
+        client
+    }
+
+    /// Send requests to another server, e.g. a local stub in tests
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Serve responses from (and save them to) a disk cache
+    pub fn with_cache(mut self, cache: Option<TmdbCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    fn token_key(&self) -> String {
+        format!("{} {} {}", self.base_url, self.api_key, self.pin)
+    }
+
+    /// Exchange the API key (and PIN) for a bearer token
+    async fn login(&self) -> Result<String, String> {
+        let url = format!("{}/login", self.base_url);
+        let mut body = serde_json::json!({ "apikey": self.api_key });
+        if !self.pin.is_empty() {
+            body["pin"] = self.pin.clone().into();
+        }
+
+        let (status, text) = self
+            .limiter
+            .send(|| self.client.post(&url).json(&body))
+            .await?;
+        if !status.is_success() {
+            return Err(http::api_error("TheTVDB", status, &text));
+        }
+        let response: TvdbResponse<TvdbLogin> =
+            serde_json::from_str(&text).map_err(|e| format!("Parse error: {}", e))?;
+
+        let token = response.data.token;
+        if let Ok(mut tokens) = tokens().lock() {
+            tokens.insert(self.token_key(), token.clone());
+        }
+        Ok(token)
+    }
+
+    async fn token(&self) -> Result<String, String> {
+        let saved = tokens()
+            .lock()
+            .ok()
+            .and_then(|tokens| tokens.get(&self.token_key()).cloned());
+        match saved {
+            Some(token) => Ok(token),
+            None => self.login().await,
+        }
+    }
+
+    /// GET a URL with the bearer token, logging in again if it has expired
+    async fn send(&self, url: &str) -> Result<(StatusCode, String), String> {
+        let token = self.token().await?;
+        let response = self
+            .limiter
+            .send(|| self.client.get(url).bearer_auth(&token))
+            .await?;
+        if response.0 != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let token = self.login().await?;
+        self.limiter
+            .send(|| self.client.get(url).bearer_auth(&token))
+            .await
+    }
+
+    /// GET a URL, going through the cache when one is set
+    async fn get_text(&self, url: &str) -> Result<String, String> {
+        let body = http::get_cached("TheTVDB", self.cache.as_ref(), url, self.send(url)).await?;
+        Ok(body)
+    }
+
+    /// GET a URL and parse the `data` of the JSON response
+    async fn get_data<T: DeserializeOwned>(&self, url: &str) -> Result<T, String> {
+        let body = self.get_text(url).await?;
+        let response: TvdbResponse<T> =
+            serde_json::from_str(&body).map_err(|e| format!("Parse error: {}", e))?;
+        Ok(response.data)
+    }
+
+    /// Search for TV series
+    pub async fn search_series(
+        &self,
+        query: &str,
+        year: Option<u32>,
+    ) -> Result<Vec<SearchResult>, String> {
+        let mut url = format!(
+            "{}/search?query={}&type=series",
+            self.base_url,
+            urlencoding::encode(query)
+        );
+        if let Some(y) = year {
+            url.push_str(&format!("&year={}", y));
+        }
+
+        let results: Vec<TvdbSearchResult> = self.get_data(&url).await?;
+
+        Ok(results
+            .into_iter()
+            .filter_map(|r| {
+                Some(SearchResult {
+                    provider: ProviderKind::Tvdb,
+                    id: r.tvdb_id.parse().ok()?,
+                    title: r.name,
+                    year: parse_year(r.year.as_deref()),
+                    media_type: MediaType::TvShow,
+                    overview: r.overview,
+                    poster_path: r.image_url,
+                    vote_average: None,
+                    popularity: None,
+                    confidence: None,
+                })
+            })
+            .collect())
+    }
+
+    /// Get series details
+    pub async fn get_series(&self, series_id: u64) -> Result<MediaMetadata, String> {
+        let url = format!("{}/series/{}/extended?short=true", self.base_url, series_id);
+
+        let series: TvdbSeries = self.get_data(&url).await?;
+
+        Ok(MediaMetadata {
+            provider: ProviderKind::Tvdb,
+            provider_id: series.id,
+            title: series.name.clone(),
+            year: parse_year(series.year.as_deref().or(series.first_aired.as_deref())),
+            overview: series.overview,
+            poster_path: series.image,
+            genres: series.genres.into_iter().map(|g| g.name).collect(),
+            show_name: Some(series.name),
+            ..Default::default()
+        })
+    }
+
+    /// All episodes of a series in the configured ordering, fetched once per client
+    async fn get_episodes(&self, series_id: u64) -> Result<Arc<Vec<TvdbEpisode>>, String> {
+        let fetched = self
+            .episodes
+            .lock()
+            .ok()
+            .and_then(|episodes| episodes.get(&series_id).cloned());
+        if let Some(episodes) = fetched {
+            return Ok(episodes);
+        }
+
+        let mut episodes = Vec::new();
+        for page in 0..MAX_EPISODE_PAGES {
+            let url = format!(
+                "{}/series/{}/episodes/{}?page={}",
+                self.base_url,
+                series_id,
+                self.season_type.slug(),
+                page
+            );
+            let body = self.get_text(&url).await?;
+            let response: TvdbEpisodesResponse =
+                serde_json::from_str(&body).map_err(|e| format!("Parse error: {}", e))?;
+
+            episodes.extend(response.data.episodes);
+            let has_next = response
+                .links
+                .and_then(|links| links.next)
+                .is_some_and(|next| !next.is_null());
+            if !has_next {
+                break;
+            }
+        }
+
+        let episodes = Arc::new(episodes);
+        if let Ok(mut cached) = self.episodes.lock() {
+            cached.insert(series_id, Arc::clone(&episodes));
+        }
+        Ok(episodes)
+    }
+
+    /// Get series details along with its season layout
+    pub async fn get_series_with_seasons(
+        &self,
+        series_id: u64,
+    ) -> Result<(MediaMetadata, SeasonLayout), String> {
+        let metadata = self.get_series(series_id).await?;
+        let episodes = self.get_episodes(series_id).await?;
+        Ok((metadata, season_layout(&episodes)))
+    }
+
+    /// Get episode details for a file, covering every episode of a multi-episode
+    /// file and mapping absolute numbers onto season/episode
+    pub async fn get_episode_details(
+        &self,
+        show: &MediaMetadata,
+        layout: &SeasonLayout,
+        file: &BatchFileInfo,
+    ) -> Result<MediaMetadata, String> {
+        let all = self.get_episodes(show.provider_id).await?;
+        let numbered = || {
+            all.iter()
+                .filter_map(|ep| Some((ep.season_number?, ep.number?, ep)))
+        };
+
+        let resolved = match (file.season, file.episode, file.absolute_episode) {
+            (Some(s), Some(e), _) => Some((s, e)),
+            (_, _, Some(absolute)) => numbered()
+                .find(|(season, _, ep)| *season > 0 && ep.absolute_number == Some(absolute))
+                .map(|(season, episode, _)| (season, episode))
+                .or_else(|| layout.to_season_episode(absolute)),
+            _ if file.special => match &file.episode_title {
+                Some(name) => {
+                    let listing: Vec<EpisodeListing> = numbered()
+                        .filter(|(season, _, _)| *season == 0)
+                        .map(|(_, episode, ep)| EpisodeListing {
+                            episode_number: episode,
+                            name: ep.name.clone().unwrap_or_default(),
+                            air_date: ep.aired.clone(),
+                        })
+                        .collect();
+                    best_special_match(&listing, name, file.year).map(|episode| (0, episode))
+                }
+                None => None,
+            },
+            _ => match &file.air_date {
+                Some(date) => numbered()
+                    .find(|(season, _, ep)| *season > 0 && ep.aired.as_ref() == Some(date))
+                    .map(|(season, episode, _)| (season, episode)),
+                None => None,
+            },
+        };
+
+        let Some((season, episode)) = resolved else {
+            let mut metadata = show.clone();
+            metadata.absolute_episode = file.absolute_episode;
+            if file.special {
+                metadata.season_number = Some(0);
+            }
+            return Ok(metadata);
+        };
+
+        let last = file.episode_end.unwrap_or(episode).max(episode);
+        let mut matching: Vec<(u32, &TvdbEpisode)> = numbered()
+            .filter(|(s, e, _)| *s == season && (episode..=last).contains(e))
+            .map(|(_, e, ep)| (e, ep))
+            .collect();
+        matching.sort_by_key(|(e, _)| *e);
+
+        let absolute = matching.first().and_then(|(_, ep)| ep.absolute_number);
+        let details = matching
+            .into_iter()
+            .map(|(_, ep)| EpisodeDetails {
+                name: ep.name.clone().unwrap_or_default(),
+                overview: ep.overview.clone(),
+                still_path: ep.image.clone(),
+                air_date: ep.aired.clone(),
+                vote_average: None,
+            })
+            .collect();
+
+        let mut metadata = episode_metadata(show, season, episode, file.episode_end, details);
+        metadata.absolute_episode = file
+            .absolute_episode
+            .or(absolute)
+            .or_else(|| layout.to_absolute(season, episode));
+        Ok(metadata)
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for TvdbClient {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Tvdb
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        media_type: MediaType,
+        year: Option<u32>,
+    ) -> Result<Vec<SearchResult>, String> {
+        match media_type {
+            // Leave movies to the next provider
+            MediaType::Movie => Ok(Vec::new()),
+            MediaType::TvShow | MediaType::Unknown => self.search_series(query, year).await,
+        }
+    }
+
+    async fn movie_details(&self, _id: u64) -> Result<MediaMetadata, String> {
+        Err("TheTVDB is only used for TV shows".to_string())
+    }
+
+    async fn show_details(&self, id: u64) -> Result<(MediaMetadata, SeasonLayout), String> {
+        self.get_series_with_seasons(id).await
+    }
+
+    async fn episode_details(
+        &self,
+        show: &MediaMetadata,
+        layout: &SeasonLayout,
+        file: &BatchFileInfo,
+    ) -> Result<MediaMetadata, String> {
+        self.get_episode_details(show, layout, file).await
+    }
+}
+
+/// Episode counts and premiere dates per season from an episode list
+fn season_layout(episodes: &[TvdbEpisode]) -> SeasonLayout {
+    let mut counts: BTreeMap<u32, u32> = BTreeMap::new();
+    let mut premieres: BTreeMap<u32, String> = BTreeMap::new();
+    for ep in episodes {
+        let Some(season) = ep.season_number else {
+            continue;
+        };
+        *counts.entry(season).or_default() += 1;
+        if let Some(aired) = &ep.aired {
+            let premiere = premieres.entry(season).or_insert_with(|| aired.clone());
+            if aired < premiere {
+                *premiere = aired.clone();
+            }
+        }
+    }
+    SeasonLayout::new(counts.into_iter().collect()).with_premieres(premieres.into_iter().collect())
+}
+
+// TheTVDB API response types
+
+#[derive(Deserialize)]
+struct TvdbResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct TvdbLogin {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct TvdbSearchResult {
+    tvdb_id: String,
+    name: String,
+    year: Option<String>,
+    overview: Option<String>,
+    image_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TvdbSeries {
+    id: u64,
+    name: String,
+    year: Option<String>,
+    first_aired: Option<String>,
+    overview: Option<String>,
+    image: Option<String>,
+    #[serde(default)]
+    genres: Vec<TvdbGenre>,
+}
+
+#[derive(Deserialize)]
+struct TvdbGenre {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct TvdbEpisodesResponse {
+    data: TvdbEpisodePage,
+    links: Option<TvdbLinks>,
+}
+
+#[derive(Deserialize)]
+struct TvdbEpisodePage {
+    #[serde(default)]
+    episodes: Vec<TvdbEpisode>,
+}
+
+#[derive(Deserialize)]
+struct TvdbLinks {
+    next: Option<serde_json::Value>, // URL of the next page, null on the last
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TvdbEpisode {
+    name: Option<String>,
+    aired: Option<String>,
+    overview: Option<String>,
+    image: Option<String>,
+    number: Option<u32>,
+    season_number: Option<u32>,
+    absolute_number: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{self, route, Request, Route};
+
+    const LOGIN: &str = r#"{"status":"success","data":{"token":"tvdb-token"}}"#;
+
+    const EPISODES: &str = r#"{
+        "status": "success",
+        "data": {
+            "series": {"id": 81189},
+            "episodes": [
+                {"name": "Good Cop Bad Cop", "aired": "2009-02-17", "number": 1, "seasonNumber": 0, "absoluteNumber": null},
+                {"name": "Pilot", "aired": "2008-01-20", "overview": "Walt starts cooking.", "number": 1, "seasonNumber": 1, "absoluteNumber": 1},
+                {"name": "Cat's in the Bag...", "aired": "2008-01-27", "number": 2, "seasonNumber": 1, "absoluteNumber": 2},
+                {"name": "Seven Thirty-Seven", "aired": "2009-03-08", "number": 1, "seasonNumber": 2, "absoluteNumber": 3}
+            ]
+        },
+        "links": {"prev": null, "self": "page=0", "next": null}
+    }"#;
+
+    fn stub_client(routes: Vec<Route>) -> (TvdbClient, Arc<Mutex<Vec<Request>>>) {
+        let (base_url, log) = test_server::serve(routes);
+        let settings = TvdbSettings {
+            api_key: "tvdb-key".to_string(),
+            pin: "1234".to_string(),
+            season_type: SeasonType::Dvd,
+        };
+        let client = TvdbClient::from_settings(&settings).with_base_url(base_url);
+        (client, log)
+    }
+
+    fn series_routes() -> Vec<Route> {
+        vec![
+            route("POST /login", 200, LOGIN),
+            route(
+                "GET /series/81189/extended",
+                200,
+                r#"{"status":"success","data":{"id":81189,"name":"Breaking Bad","year":"2008","image":"https://artworks.thetvdb.com/poster.jpg","genres":[{"name":"Drama"}]}}"#,
+            ),
+            route("GET /series/81189/episodes/dvd", 200, EPISODES),
+        ]
+    }
+
+    fn file(season: Option<u32>, episode: Option<u32>) -> BatchFileInfo {
+        BatchFileInfo {
+            index: 0,
+            title: "Breaking Bad".to_string(),
+            year: None,
+            season,
+            episode,
+            episode_end: None,
+            absolute_episode: None,
+            air_date: None,
+            special: false,
+            episode_title: None,
+            media_type: MediaType::TvShow,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_logs_in_and_sends_token() {
+        let (client, log) = stub_client(vec![
+            route("POST /login", 200, LOGIN),
+            route(
+                "GET /search",
+                200,
+                r#"{"status":"success","data":[{"tvdb_id":"81189","name":"Breaking Bad","year":"2008","image_url":"https://artworks.thetvdb.com/poster.jpg"}]}"#,
+            ),
+        ]);
+
+        let results = client
+            .search("Breaking Bad", MediaType::TvShow, Some(2008))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].provider, ProviderKind::Tvdb);
+        assert_eq!(results[0].id, 81189);
+        assert_eq!(results[0].year, Some(2008));
+
+        let requests = log.lock().unwrap();
+        assert!(requests[0].body.contains(r#""apikey":"tvdb-key""#));
+        assert!(requests[0].body.contains(r#""pin":"1234""#));
+        assert!(requests[1]
+            .line
+            .contains("query=Breaking%20Bad&type=series&year=2008"));
+        assert_eq!(
+            requests[1].authorization.as_deref(),
+            Some("Bearer tvdb-token")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_changing_the_pin_logs_in_again() {
+        let (client, log) = stub_client(vec![
+            route("POST /login", 200, LOGIN),
+            route("GET /search", 200, r#"{"status":"success","data":[]}"#),
+        ]);
+        client.search_series("Breaking Bad", None).await.unwrap();
+
+        let other_pin = TvdbClient::from_settings(&TvdbSettings {
+            api_key: "tvdb-key".to_string(),
+            pin: "5678".to_string(),
+            season_type: SeasonType::Dvd,
+        })
+        .with_base_url(client.base_url.clone());
+        other_pin.search_series("Breaking Bad", None).await.unwrap();
+
+        let requests = log.lock().unwrap();
+        let logins: Vec<_> = requests
+            .iter()
+            .filter(|r| r.line.starts_with("POST /login"))
+            .collect();
+        assert_eq!(logins.len(), 2);
+        assert!(logins[1].body.contains(r#""pin":"5678""#));
+    }
+
+    #[tokio::test]
+    async fn test_movies_are_left_to_other_providers() {
+        let (client, log) = stub_client(Vec::new());
+
+        let results = client.search("The Matrix", MediaType::Movie, None).await;
+        assert!(results.unwrap().is_empty());
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_login_error_message_reaches_the_caller() {
+        let (client, _) = stub_client(vec![route(
+            "POST /login",
+            401,
+            r#"{"status":"failure","message":"Unauthorized","data":null}"#,
+        )]);
+
+        let error = client.search_series("Anything", None).await.unwrap_err();
+        assert_eq!(error, "TheTVDB error: Unauthorized");
+    }
+
+    #[tokio::test]
+    async fn test_episode_details_use_season_type_ordering() {
+        let (client, log) = stub_client(series_routes());
+
+        let (show, layout) = client.show_details(81189).await.unwrap();
+        assert_eq!(show.provider, ProviderKind::Tvdb);
+        assert_eq!(show.provider_id, 81189);
+        assert_eq!(show.tmdb_id, 0);
+        assert_eq!(show.year, Some(2008));
+        assert_eq!(show.genres, vec!["Drama".to_string()]);
+        assert_eq!(layout.to_season_episode(3), Some((2, 1)));
+
+        let metadata = client
+            .episode_details(&show, &layout, &file(Some(1), Some(2)))
+            .await
+            .unwrap();
+        assert_eq!(
+            metadata.episode_title,
+            Some("Cat's in the Bag...".to_string())
+        );
+        assert_eq!(metadata.absolute_episode, Some(2));
+
+        let mut absolute = file(None, None);
+        absolute.absolute_episode = Some(3);
+        let metadata = client
+            .episode_details(&show, &layout, &absolute)
+            .await
+            .unwrap();
+        assert_eq!(metadata.season_number, Some(2));
+        assert_eq!(metadata.episode_number, Some(1));
+
+        let mut daily = file(None, None);
+        daily.air_date = Some("2008-01-20".to_string());
+        let metadata = client
+            .episode_details(&show, &layout, &daily)
+            .await
+            .unwrap();
+        assert_eq!(metadata.episode_title, Some("Pilot".to_string()));
+        assert_eq!(metadata.overview, Some("Walt starts cooking.".to_string()));
+
+        let mut special = file(None, None);
+        special.special = true;
+        special.episode_title = Some("Special Good Cop Bad Cop".to_string());
+        let metadata = client
+            .episode_details(&show, &layout, &special)
+            .await
+            .unwrap();
+        assert_eq!(metadata.season_number, Some(0));
+        assert_eq!(metadata.episode_number, Some(1));
+
+        // One login, and the episode list is only fetched once
+        let requests = log.lock().unwrap();
+        let count = |prefix: &str| {
+            requests
+                .iter()
+                .filter(|r| r.line.starts_with(prefix))
+                .count()
+        };
+        assert_eq!(count("POST /login"), 1);
+        assert_eq!(count("GET /series/81189/episodes/dvd?page=0"), 1);
+    }
+}
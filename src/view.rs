@@ -1,12 +1,18 @@
 use crate::message::Message;
-use crate::model::{AppState, MediaType, RenamePattern};
+use crate::model::{
+    AppState, MediaType, ProviderKind, RenamePattern, Thumbnail, SEARCH_RESULTS_SHOWN,
+};
 use crate::theme::{
     get_colors, CardStyle, DangerButtonStyle, FileItemStyle, PanelStyle, PrimaryButtonStyle,
-    SecondaryButtonStyle, SuccessButtonStyle, TextInputStyle, ThemeMode, ToggleStyle, TooltipStyle,
+    ProgressBarStyle, SecondaryButtonStyle, SuccessButtonStyle, TextInputStyle, ThemeMode,
+    ToggleStyle, TooltipStyle,
 };
+use crate::utils::file_ops::FileOperation;
+use crate::utils::nfo::NfoPolicy;
+use crate::utils::renamer;
 use iced::widget::{
-    button, checkbox, column, container, row, scrollable, text, text_input, tooltip, Column, Row,
-    Space,
+    button, checkbox, column, container, image, progress_bar, row, scrollable, text, text_input,
+    tooltip, Column, Row, Space,
 };
 use iced::{Alignment, Color, Element, Font, Length, Theme};
 use iced_aw::core::icons::bootstrap::{icon_to_text, Bootstrap};
@@ -60,6 +66,16 @@ pub fn build_view(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Messag
             .backdrop(Message::HideRenamePreview)
             .on_esc(Message::HideRenamePreview)
             .into()
+    } else if state.pattern_editor.is_some() {
+        modal(base, Some(build_pattern_editor_modal(state, theme_mode)))
+            .backdrop(Message::CancelPatternEditor)
+            .on_esc(Message::CancelPatternEditor)
+            .into()
+    } else if state.show_rename_history {
+        modal(base, Some(build_history_modal(state, theme_mode)))
+            .backdrop(Message::HideRenameHistory)
+            .on_esc(Message::HideRenameHistory)
+            .into()
     } else {
         base
     }
@@ -246,6 +262,24 @@ fn build_file_list_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_,
                         color: colors.success,
                     })))
                     .into()
+                } else if file.needs_review() {
+                    tooltip(
+                        container(
+                            text("?")
+                                .size(9)
+                                .style(iced::theme::Text::Color(Color::WHITE)),
+                        )
+                        .padding([2, 7])
+                        .style(iced::theme::Container::Custom(Box::new(BadgeStyle {
+                            color: colors.warning,
+                        }))),
+                        "Ambiguous match - select the file to confirm",
+                        tooltip::Position::Left,
+                    )
+                    .style(iced::theme::Container::Custom(Box::new(TooltipStyle {
+                        mode: theme_mode,
+                    })))
+                    .into()
                 } else {
                     container(
                         text("--")
@@ -400,6 +434,7 @@ fn build_actions_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, M
         .iter()
         .filter(|f| f.matched_metadata.is_none())
         .count();
+    let review_count = state.files.iter().filter(|f| f.needs_review()).count();
 
     // API Key Section - with verification status in header and verify button next to input
     let api_status_text: Element<Message> = if state.api_key_verifying {
@@ -547,6 +582,55 @@ fn build_actions_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, M
     ]
     .spacing(0);
 
+    // Metadata source for TV shows; TheTVDB needs its own key
+    let tv_provider = state.tv_provider();
+    let provider_buttons: Vec<Element<Message>> = [ProviderKind::Tmdb, ProviderKind::Tvdb]
+        .into_iter()
+        .map(|provider| {
+            let is_selected = provider == tv_provider;
+            button(
+                text(provider.display_name())
+                    .size(11)
+                    .style(iced::theme::Text::Color(if is_selected {
+                        colors.accent_primary
+                    } else {
+                        colors.text_secondary
+                    })),
+            )
+            .style(iced::theme::Button::Custom(Box::new(PatternButtonStyle {
+                mode: theme_mode,
+                is_selected,
+            })))
+            .padding([5, 8])
+            .on_press(Message::TvProviderChanged(provider))
+            .into()
+        })
+        .collect();
+
+    let mut tv_source_section = column![row![
+        text("TV Metadata")
+            .size(13)
+            .style(iced::theme::Text::Color(colors.text_primary)),
+        Space::with_width(Length::Fill),
+        Row::with_children(provider_buttons).spacing(6),
+    ]
+    .align_items(Alignment::Center)]
+    .spacing(0);
+
+    if tv_provider == ProviderKind::Tvdb {
+        tv_source_section = tv_source_section.push(Space::with_height(8)).push(
+            text_input("Paste your TheTVDB API key here...", &state.tvdb.api_key)
+                .on_input(Message::TvdbApiKeyChanged)
+                .style(iced::theme::TextInput::Custom(Box::new(TextInputStyle {
+                    mode: theme_mode,
+                })))
+                .padding(10)
+                .size(12)
+                .secure(true)
+                .width(Length::Fill),
+        );
+    }
+
     // Find and Match Section with loading state in button
     let is_matching = state.loading;
     let matched_count = state
@@ -559,7 +643,11 @@ fn build_actions_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, M
             .size(13)
             .style(iced::theme::Text::Color(colors.text_primary)),
         Space::with_width(Length::Fill),
-        if unmatched_count > 0 {
+        if review_count > 0 {
+            text(format!("{} to review", review_count))
+                .size(11)
+                .style(iced::theme::Text::Color(colors.warning))
+        } else if unmatched_count > 0 {
             text(format!("{} unmatched", unmatched_count))
                 .size(11)
                 .style(iced::theme::Text::Color(colors.warning))
@@ -659,6 +747,168 @@ fn build_actions_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, M
         .into()
     };
 
+    // TMDB cache controls
+    let cache_row = row![
+        tooltip(
+            checkbox("Offline mode", state.offline_mode)
+                .on_toggle(Message::ToggleOfflineMode)
+                .size(14)
+                .text_size(11)
+                .style(iced::theme::Checkbox::Custom(Box::new(
+                    SmallCheckboxStyle { mode: theme_mode }
+                ))),
+            "Only use TMDB responses cached on disk",
+            tooltip::Position::Top,
+        )
+        .style(iced::theme::Container::Custom(Box::new(TooltipStyle {
+            mode: theme_mode
+        }))),
+        Space::with_width(Length::Fill),
+        button(
+            row![
+                icon_to_text(Bootstrap::Trash).size(11.0),
+                Space::with_width(5),
+                text("Clear cache").size(11),
+            ]
+            .align_items(Alignment::Center)
+        )
+        .style(iced::theme::Button::Custom(Box::new(
+            SecondaryButtonStyle { mode: theme_mode }
+        )))
+        .padding([4, 8])
+        .on_press(Message::ClearTmdbCache),
+    ]
+    .align_items(Alignment::Center);
+
+    // Language titles and episode names are fetched in
+    let language_row = row![
+        text("Metadata language")
+            .size(11)
+            .style(iced::theme::Text::Color(colors.text_secondary)),
+        Space::with_width(Length::Fill),
+        match &state.rename_pattern.language {
+            Some(language) => text(format!("Pattern uses {}", language))
+                .size(10)
+                .style(iced::theme::Text::Color(colors.accent_primary)),
+            None => text("").size(10),
+        },
+        Space::with_width(8),
+        tooltip(
+            text_input("en-US", &state.metadata_language)
+                .on_input(Message::MetadataLanguageChanged)
+                .on_submit(Message::ApplyMetadataLanguage)
+                .style(iced::theme::TextInput::Custom(Box::new(TextInputStyle {
+                    mode: theme_mode
+                })))
+                .padding([4, 8])
+                .size(11)
+                .width(Length::Fixed(70.0)),
+            "Press Enter to fetch matched files again in this language",
+            tooltip::Position::Top,
+        )
+        .style(iced::theme::Container::Custom(Box::new(TooltipStyle {
+            mode: theme_mode
+        }))),
+    ]
+    .align_items(Alignment::Center);
+
+    // Poster and title of the selected file's match
+    let matched_section: Element<Message> = match state
+        .selected_file()
+        .and_then(|f| f.matched_metadata.as_ref())
+    {
+        Some(metadata) => {
+            let mut details: Vec<String> = metadata.year.iter().map(u32::to_string).collect();
+            if let (Some(season), Some(episode)) = (metadata.season_number, metadata.episode_number)
+            {
+                details.push(format!("S{:02}E{:02}", season, episode));
+            }
+            details.extend(metadata.episode_title.clone());
+
+            column![
+                Space::with_height(14),
+                row![
+                    poster_thumbnail(state, metadata.poster_path.as_ref(), theme_mode),
+                    Space::with_width(10),
+                    column![
+                        text("Matched")
+                            .size(10)
+                            .style(iced::theme::Text::Color(colors.text_secondary)),
+                        text(metadata.show_name.as_ref().unwrap_or(&metadata.title))
+                            .size(12)
+                            .style(iced::theme::Text::Color(colors.text_primary)),
+                        text(details.join(" • "))
+                            .size(10)
+                            .style(iced::theme::Text::Color(colors.text_secondary)),
+                    ]
+                    .spacing(1),
+                ]
+                .align_items(Alignment::Center),
+            ]
+            .spacing(0)
+            .into()
+        }
+        None => Space::with_height(0).into(),
+    };
+
+    // Episode ordering of the selected file's show, when TMDB has alternatives
+    let episode_order_section: Element<Message> = match state.selected_show_id().and_then(|id| {
+        state
+            .episode_groups
+            .get(&id)
+            .filter(|groups| !groups.is_empty())
+            .map(|groups| (id, groups))
+    }) {
+        Some((show_id, groups)) => {
+            let chosen = state.episode_orders.get(&show_id);
+            let order_button = |label: String, group_id: Option<String>| -> Element<Message> {
+                let is_selected = chosen == group_id.as_ref();
+                button(
+                    text(label)
+                        .size(11)
+                        .style(iced::theme::Text::Color(if is_selected {
+                            colors.accent_primary
+                        } else {
+                            colors.text_secondary
+                        })),
+                )
+                .style(iced::theme::Button::Custom(Box::new(PatternButtonStyle {
+                    mode: theme_mode,
+                    is_selected,
+                })))
+                .padding([5, 8])
+                .width(Length::Fill)
+                .on_press(Message::EpisodeOrderSelected(show_id, group_id))
+                .into()
+            };
+
+            let mut buttons = vec![order_button("Aired order".to_string(), None)];
+            buttons.extend(groups.iter().map(|group| {
+                order_button(
+                    format!(
+                        "{} ({}, {} episodes)",
+                        group.name,
+                        group.kind_name(),
+                        group.episode_count
+                    ),
+                    Some(group.id.clone()),
+                )
+            }));
+
+            column![
+                Space::with_height(14),
+                text("Episode Order")
+                    .size(13)
+                    .style(iced::theme::Text::Color(colors.text_primary)),
+                Space::with_height(8),
+                Column::with_children(buttons).spacing(4),
+            ]
+            .spacing(0)
+            .into()
+        }
+        None => Space::with_height(0).into(),
+    };
+
     // Count selected files for manual search
     let selected_count = state
         .files
@@ -729,10 +979,18 @@ fn build_actions_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, M
                 .style(iced::theme::Text::Color(colors.text_primary)),
             Space::with_height(16),
             api_section,
+            Space::with_height(14),
+            tv_source_section,
             Space::with_height(20),
             match_section,
             Space::with_height(10),
             match_button,
+            Space::with_height(8),
+            cache_row,
+            Space::with_height(8),
+            language_row,
+            matched_section,
+            episode_order_section,
             manual_section,
         ]
         .spacing(0)
@@ -788,7 +1046,7 @@ fn build_search_results(state: &AppState, theme_mode: ThemeMode) -> Element<'_,
             .search_results
             .iter()
             .enumerate()
-            .take(12) // Show more results
+            .take(SEARCH_RESULTS_SHOWN)
             .map(|(idx, result)| {
                 // Use text badges instead of Bootstrap icons
                 let type_text = match result.media_type {
@@ -815,6 +1073,8 @@ fn build_search_results(state: &AppState, theme_mode: ThemeMode) -> Element<'_,
                 button(
                     container(
                         row![
+                            poster_thumbnail(state, result.poster_path.as_ref(), theme_mode),
+                            Space::with_width(8),
                             type_badge,
                             Space::with_width(8),
                             column![
@@ -836,6 +1096,13 @@ fn build_search_results(state: &AppState, theme_mode: ThemeMode) -> Element<'_,
                                     } else {
                                         text("").size(10)
                                     },
+                                    if let Some(confidence) = result.confidence {
+                                        text(format!(" • {:.0}% match", confidence * 100.0))
+                                            .size(10)
+                                            .style(iced::theme::Text::Color(colors.info))
+                                    } else {
+                                        text("").size(10)
+                                    },
                                 ],
                             ]
                             .spacing(1),
@@ -872,42 +1139,139 @@ fn build_search_results(state: &AppState, theme_mode: ThemeMode) -> Element<'_,
     }
 }
 
+/// Poster thumbnail, or a placeholder while it loads or when there is none
+fn poster_thumbnail<'a>(
+    state: &'a AppState,
+    source: Option<&String>,
+    theme_mode: ThemeMode,
+) -> Element<'a, Message> {
+    let colors = get_colors(theme_mode);
+    let (width, height) = (Length::Fixed(30.0), Length::Fixed(45.0));
+
+    let thumbnail = source.and_then(|source| state.thumbnails.get(source));
+    if let Some(Thumbnail::Loaded(handle)) = thumbnail {
+        return image(handle.clone()).width(width).height(height).into();
+    }
+
+    let loading = source.is_some() && !matches!(thumbnail, Some(Thumbnail::Failed));
+    let icon = if loading {
+        Bootstrap::ArrowRepeat
+    } else {
+        Bootstrap::Film
+    };
+    container(
+        icon_to_text(icon)
+            .size(12.0)
+            .style(iced::theme::Text::Color(colors.text_disabled)),
+    )
+    .width(width)
+    .height(height)
+    .center_x()
+    .center_y()
+    .style(iced::theme::Container::Custom(Box::new(
+        PosterPlaceholderStyle { mode: theme_mode },
+    )))
+    .into()
+}
+
 // ============== RENAME PANEL ==============
 
 fn build_rename_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Message> {
     let colors = get_colors(theme_mode);
 
     // Pattern selection
-    let patterns = RenamePattern::all_patterns();
-    let pattern_buttons: Vec<Element<Message>> = patterns
+    let pattern_button = |p: RenamePattern| -> Element<Message> {
+        let is_selected = p.name == state.rename_pattern.name;
+        button(
+            text(&p.name)
+                .size(11)
+                .style(iced::theme::Text::Color(if is_selected {
+                    colors.accent_primary
+                } else {
+                    colors.text_secondary
+                })),
+        )
+        .style(iced::theme::Button::Custom(Box::new(PatternButtonStyle {
+            mode: theme_mode,
+            is_selected,
+        })))
+        .padding([5, 10])
+        .on_press(Message::PatternChanged(p))
+        .into()
+    };
+    let pattern_buttons: Vec<Element<Message>> = RenamePattern::all_patterns()
         .into_iter()
-        .map(|p| {
-            let is_selected = p.name == state.rename_pattern.name;
-            button(
-                text(&p.name)
-                    .size(11)
-                    .style(iced::theme::Text::Color(if is_selected {
-                        colors.accent_primary
-                    } else {
-                        colors.text_secondary
-                    })),
-            )
-            .style(iced::theme::Button::Custom(Box::new(PatternButtonStyle {
-                mode: theme_mode,
-                is_selected,
-            })))
-            .padding([5, 10])
-            .on_press(Message::PatternChanged(p))
-            .into()
-        })
+        .map(pattern_button)
+        .collect();
+    let custom_buttons: Vec<Element<Message>> = state
+        .custom_patterns
+        .iter()
+        .cloned()
+        .map(pattern_button)
         .collect();
+    let custom_row: Element<Message> = if custom_buttons.is_empty() {
+        Space::with_height(0).into()
+    } else {
+        column![
+            Space::with_height(8),
+            scrollable(Row::with_children(custom_buttons).spacing(8)).direction(
+                scrollable::Direction::Horizontal(
+                    scrollable::Properties::new().width(4).scroller_width(4)
+                )
+            ),
+        ]
+        .into()
+    };
+
+    // New / duplicate / edit / delete - only user patterns can be edited or deleted
+    let is_custom = !state.rename_pattern.is_builtin();
+    let pattern_action = |icon: Bootstrap, label: &'static str, message: Option<Message>| {
+        tooltip(
+            button(icon_to_text(icon).size(11.0))
+                .style(iced::theme::Button::Custom(Box::new(
+                    SecondaryButtonStyle { mode: theme_mode },
+                )))
+                .padding([4, 7])
+                .on_press_maybe(message),
+            label,
+            tooltip::Position::Bottom,
+        )
+        .style(iced::theme::Container::Custom(Box::new(TooltipStyle {
+            mode: theme_mode,
+        })))
+    };
+    let pattern_actions = row![
+        pattern_action(Bootstrap::Plus, "New pattern", Some(Message::NewPattern)),
+        pattern_action(
+            Bootstrap::Files,
+            "Duplicate pattern",
+            Some(Message::DuplicatePattern)
+        ),
+        pattern_action(
+            Bootstrap::PencilSquare,
+            "Edit pattern",
+            is_custom.then_some(Message::EditPattern)
+        ),
+        pattern_action(
+            Bootstrap::Trash,
+            "Delete pattern",
+            is_custom.then_some(Message::DeletePattern)
+        ),
+    ]
+    .spacing(4);
 
     let pattern_selector = column![
-        text("Naming Pattern")
-            .size(13)
-            .style(iced::theme::Text::Color(colors.text_primary)),
+        row![
+            text("Naming Pattern")
+                .size(13)
+                .style(iced::theme::Text::Color(colors.text_primary)),
+            Space::with_width(Length::Fill),
+            pattern_actions,
+        ]
+        .align_items(Alignment::Center),
         Space::with_height(10),
         Row::with_children(pattern_buttons).spacing(8),
+        custom_row,
         Space::with_height(10),
         container(
             column![
@@ -970,6 +1334,113 @@ fn build_rename_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Me
         ]
         .align_items(Alignment::Center)
         .spacing(8),
+        Space::with_height(8),
+        tooltip(
+            checkbox("All or nothing", state.all_or_nothing)
+                .on_toggle(Message::ToggleAllOrNothing)
+                .size(14)
+                .text_size(11)
+                .style(iced::theme::Checkbox::Custom(Box::new(
+                    SmallCheckboxStyle { mode: theme_mode }
+                ))),
+            "Check every destination first and undo the batch if any file fails",
+            tooltip::Position::Top,
+        )
+        .style(iced::theme::Container::Custom(Box::new(TooltipStyle {
+            mode: theme_mode
+        }))),
+    ]
+    .spacing(0);
+
+    // How files are placed at their new paths
+    let operation_buttons: Vec<Element<Message>> = FileOperation::ALL
+        .into_iter()
+        .map(|op| {
+            let is_selected = op == state.file_operation;
+            button(
+                text(op.name())
+                    .size(11)
+                    .style(iced::theme::Text::Color(if is_selected {
+                        colors.accent_primary
+                    } else {
+                        colors.text_secondary
+                    })),
+            )
+            .style(iced::theme::Button::Custom(Box::new(PatternButtonStyle {
+                mode: theme_mode,
+                is_selected,
+            })))
+            .padding([5, 8])
+            .on_press(Message::FileOperationChanged(op))
+            .into()
+        })
+        .collect();
+
+    let operation_section = column![
+        text("Operation")
+            .size(13)
+            .style(iced::theme::Text::Color(colors.text_primary)),
+        Space::with_height(8),
+        Row::with_children(operation_buttons).spacing(6),
+    ]
+    .spacing(0);
+
+    // Kodi NFOs written next to renamed files
+    let nfo_buttons: Vec<Element<Message>> =
+        NfoPolicy::ALL
+            .into_iter()
+            .map(|policy| {
+                let is_selected = policy == state.nfo_policy;
+                button(text(policy.name()).size(11).style(iced::theme::Text::Color(
+                    if is_selected {
+                        colors.accent_primary
+                    } else {
+                        colors.text_secondary
+                    },
+                )))
+                .style(iced::theme::Button::Custom(Box::new(PatternButtonStyle {
+                    mode: theme_mode,
+                    is_selected,
+                })))
+                .padding([5, 8])
+                .on_press(Message::NfoPolicyChanged(policy))
+                .into()
+            })
+            .collect();
+
+    let nfo_section = column![
+        row![
+            text("NFO & Artwork")
+                .size(13)
+                .style(iced::theme::Text::Color(colors.text_primary)),
+            Space::with_width(Length::Fill),
+            text(match state.nfo_policy {
+                NfoPolicy::Off => "Not written",
+                NfoPolicy::Keep => "Existing NFOs are left alone",
+                NfoPolicy::Overwrite => "Existing NFOs are replaced",
+                NfoPolicy::Merge => "Existing NFOs are updated",
+            })
+            .size(10)
+            .style(iced::theme::Text::Color(colors.text_disabled)),
+        ]
+        .align_items(Alignment::Center),
+        Space::with_height(8),
+        Row::with_children(nfo_buttons).spacing(6),
+        Space::with_height(8),
+        tooltip(
+            checkbox("Download artwork", state.artwork.enabled)
+                .on_toggle(Message::ToggleArtwork)
+                .size(14)
+                .text_size(11)
+                .style(iced::theme::Checkbox::Custom(Box::new(
+                    SmallCheckboxStyle { mode: theme_mode }
+                ))),
+            "Save poster.jpg, fanart.jpg, season posters and episode thumbnails next to renamed files",
+            tooltip::Position::Top,
+        )
+        .style(iced::theme::Container::Custom(Box::new(TooltipStyle {
+            mode: theme_mode
+        }))),
     ]
     .spacing(0);
 
@@ -1074,16 +1545,80 @@ fn build_rename_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Me
         .width(Length::Fill)
     };
 
+    // Copy progress while a file is moved to another filesystem
+    let progress_section: Element<Message> = match &state.rename_progress {
+        Some(progress) => column![
+            text(format!(
+                "Copying {} ({}/{})",
+                progress.filename,
+                progress.file_index + 1,
+                progress.file_count
+            ))
+            .size(11)
+            .style(iced::theme::Text::Color(colors.text_secondary)),
+            Space::with_height(6),
+            progress_bar(0.0..=1.0, progress.fraction())
+                .height(6)
+                .style(iced::theme::ProgressBar::Custom(Box::new(
+                    ProgressBarStyle { mode: theme_mode }
+                ))),
+            Space::with_height(10),
+        ]
+        .into(),
+        None => Space::with_height(0).into(),
+    };
+
+    // Undo button - only enabled when the journal has something to revert
+    let can_undo = state.rename_journal.last_active_batch().is_some() && !state.loading;
+    let undo_btn = button(icon_to_text(Bootstrap::ArrowCounterclockwise).size(12.0))
+        .style(iced::theme::Button::Custom(Box::new(
+            SecondaryButtonStyle { mode: theme_mode },
+        )))
+        .padding([5, 8])
+        .on_press_maybe(can_undo.then_some(Message::UndoLastRename));
+
+    let header = row![
+        text("Rename")
+            .size(16)
+            .style(iced::theme::Text::Color(colors.text_primary)),
+        Space::with_width(Length::Fill),
+        tooltip(
+            undo_btn,
+            "Undo last rename (Ctrl+Z)",
+            tooltip::Position::Bottom
+        )
+        .style(iced::theme::Container::Custom(Box::new(TooltipStyle {
+            mode: theme_mode
+        }))),
+        Space::with_width(6),
+        tooltip(
+            button(icon_to_text(Bootstrap::ClockHistory).size(12.0))
+                .style(iced::theme::Button::Custom(Box::new(
+                    SecondaryButtonStyle { mode: theme_mode }
+                )))
+                .padding([5, 8])
+                .on_press(Message::ShowRenameHistory),
+            "Rename history",
+            tooltip::Position::Bottom,
+        )
+        .style(iced::theme::Container::Custom(Box::new(TooltipStyle {
+            mode: theme_mode
+        }))),
+    ]
+    .align_items(Alignment::Center);
+
     container(
         column![
-            text("Rename")
-                .size(16)
-                .style(iced::theme::Text::Color(colors.text_primary)),
+            header,
             Space::with_height(18),
             pattern_selector,
             Space::with_height(18),
             output_section,
             Space::with_height(18),
+            operation_section,
+            Space::with_height(18),
+            nfo_section,
+            Space::with_height(18),
             row![
                 text("Preview")
                     .size(13)
@@ -1097,6 +1632,7 @@ fn build_rename_panel(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Me
             Space::with_height(12),
             preview_section,
             Space::with_height(14),
+            progress_section,
             rename_btn,
         ]
         .spacing(0)
@@ -1157,11 +1693,23 @@ fn build_rename_modal(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Me
                 .style(iced::theme::Text::Color(colors.text_primary)),
             Space::with_height(6),
             text(format!(
-                "{} file(s) will be renamed:",
-                state.rename_preview.len()
+                "{} file(s) will be {}:",
+                state.rename_preview.len(),
+                state.file_operation.past_tense()
             ))
             .size(12)
             .style(iced::theme::Text::Color(colors.text_secondary)),
+            Space::with_height(4),
+            text(if state.file_operation.keeps_source() {
+                format!(
+                    "Operation: {} (original files are kept)",
+                    state.file_operation
+                )
+            } else {
+                format!("Operation: {}", state.file_operation)
+            })
+            .size(11)
+            .style(iced::theme::Text::Color(colors.accent_primary)),
             Space::with_height(14),
             preview_list,
             Space::with_height(14),
@@ -1177,7 +1725,11 @@ fn build_rename_modal(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Me
                     row![
                         icon_to_text(Bootstrap::CheckLg).size(13.0),
                         Space::with_width(6),
-                        text("Rename").size(12),
+                        text(match state.file_operation {
+                            FileOperation::Move => "Rename",
+                            other => other.name(),
+                        })
+                        .size(12),
                     ]
                     .align_items(Alignment::Center)
                 )
@@ -1200,6 +1752,300 @@ fn build_rename_modal(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Me
     .into()
 }
 
+// ============== PATTERN EDITOR MODAL ==============
+
+fn build_pattern_editor_modal(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Message> {
+    let colors = get_colors(theme_mode);
+    let Some(editor) = &state.pattern_editor else {
+        return Space::with_height(0).into();
+    };
+    let validation = state.validate_pattern_editor();
+
+    let field = |label: &'static str,
+                 placeholder: &'static str,
+                 value: &str,
+                 on_input: fn(String) -> Message,
+                 monospace: bool| {
+        let input = text_input(placeholder, value)
+            .on_input(on_input)
+            .style(iced::theme::TextInput::Custom(Box::new(TextInputStyle {
+                mode: theme_mode,
+            })))
+            .padding(8)
+            .size(12)
+            .width(Length::Fill);
+        column![
+            text(label)
+                .size(11)
+                .style(iced::theme::Text::Color(colors.text_secondary)),
+            Space::with_height(4),
+            if monospace {
+                input.font(JETBRAINS_MONO)
+            } else {
+                input
+            },
+        ]
+    };
+
+    // Live preview against the selected file
+    let preview: Element<Message> = match state.selected_file() {
+        Some(file) => {
+            let result = renamer::preview_filename(file, &editor.pattern());
+            column![
+                text(&file.filename)
+                    .size(11)
+                    .font(JETBRAINS_MONO)
+                    .style(iced::theme::Text::Color(colors.text_secondary)),
+                row![
+                    text("→")
+                        .size(11)
+                        .style(iced::theme::Text::Color(colors.accent_primary)),
+                    Space::with_width(6),
+                    match result {
+                        Ok(name) => text(name)
+                            .size(11)
+                            .font(JETBRAINS_MONO)
+                            .style(iced::theme::Text::Color(colors.success)),
+                        Err(e) => text(e)
+                            .size(11)
+                            .style(iced::theme::Text::Color(colors.error)),
+                    },
+                ]
+                .align_items(Alignment::Center),
+            ]
+            .spacing(3)
+            .into()
+        }
+        None => text("Select a file to see a preview")
+            .size(11)
+            .style(iced::theme::Text::Color(colors.text_disabled))
+            .into(),
+    };
+
+    let error: Element<Message> = match &validation {
+        Err(e) => text(e)
+            .size(11)
+            .style(iced::theme::Text::Color(colors.error))
+            .into(),
+        Ok(_) => Space::with_height(0).into(),
+    };
+
+    container(
+        column![
+            text(if editor.original_name.is_some() {
+                "Edit Pattern"
+            } else {
+                "New Pattern"
+            })
+            .size(17)
+            .style(iced::theme::Text::Color(colors.text_primary)),
+            Space::with_height(14),
+            field(
+                "Name",
+                "My pattern",
+                &editor.name,
+                Message::PatternNameChanged,
+                false
+            ),
+            Space::with_height(10),
+            field(
+                "Movie",
+                "{title}[ ({year})]",
+                &editor.movie_pattern,
+                Message::PatternMovieChanged,
+                true
+            ),
+            Space::with_height(10),
+            field(
+                "TV",
+                "{show} - S{season:02}E{episode:02}[ - {episode_title}]",
+                &editor.tv_pattern,
+                Message::PatternTvChanged,
+                true
+            ),
+            Space::with_height(10),
+            field(
+                "Metadata language (optional)",
+                "Same as the app setting, e.g. de-DE",
+                &editor.language,
+                Message::PatternLanguageChanged,
+                false
+            ),
+            Space::with_height(14),
+            text("Preview")
+                .size(11)
+                .style(iced::theme::Text::Color(colors.text_secondary)),
+            Space::with_height(4),
+            container(preview)
+                .padding([8, 10])
+                .width(Length::Fill)
+                .style(iced::theme::Container::Custom(Box::new(CodeBlockStyle {
+                    mode: theme_mode
+                }))),
+            Space::with_height(8),
+            error,
+            Space::with_height(14),
+            row![
+                Space::with_width(Length::Fill),
+                button(text("Cancel").size(12))
+                    .style(iced::theme::Button::Custom(Box::new(
+                        SecondaryButtonStyle { mode: theme_mode }
+                    )))
+                    .padding([8, 16])
+                    .on_press(Message::CancelPatternEditor),
+                Space::with_width(10),
+                button(
+                    row![
+                        icon_to_text(Bootstrap::Save).size(13.0),
+                        Space::with_width(6),
+                        text("Save").size(12),
+                    ]
+                    .align_items(Alignment::Center)
+                )
+                .style(iced::theme::Button::Custom(Box::new(SuccessButtonStyle {
+                    mode: theme_mode
+                })))
+                .padding([8, 16])
+                .on_press_maybe(validation.is_ok().then_some(Message::SavePattern)),
+            ]
+            .align_items(Alignment::Center),
+        ]
+        .spacing(0),
+    )
+    .padding(20)
+    .max_width(550)
+    .style(iced::theme::Container::Custom(Box::new(CardStyle {
+        mode: theme_mode,
+    })))
+    .into()
+}
+
+// ============== HISTORY MODAL ==============
+
+fn build_history_modal(state: &AppState, theme_mode: ThemeMode) -> Element<'_, Message> {
+    let colors = get_colors(theme_mode);
+    let batches = &state.rename_journal.batches;
+
+    let history: Element<Message> = if batches.is_empty() {
+        container(
+            text("No renames recorded yet")
+                .size(12)
+                .style(iced::theme::Text::Color(colors.text_disabled)),
+        )
+        .width(Length::Fill)
+        .center_x()
+        .padding([20, 0])
+        .into()
+    } else {
+        let items: Vec<Element<Message>> = batches
+            .iter()
+            .rev()
+            .map(|batch| {
+                let is_active = batch.is_active();
+                let status = if !is_active {
+                    "Reverted".to_string()
+                } else if batch.active_count() < batch.entries.len() {
+                    format!(
+                        "{} of {} file(s) still renamed",
+                        batch.active_count(),
+                        batch.entries.len()
+                    )
+                } else {
+                    format!("{} file(s)", batch.entries.len())
+                };
+
+                let title = match batch.operation {
+                    FileOperation::Move => {
+                        format!("{}  •  {}", batch.timestamp, batch.pattern_name)
+                    }
+                    op => format!("{}  •  {}  •  {}", batch.timestamp, batch.pattern_name, op),
+                };
+
+                let first_entry = batch.entries.first().map(|e| {
+                    e.new_path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                });
+
+                container(
+                    row![
+                        column![
+                            text(title)
+                                .size(12)
+                                .style(iced::theme::Text::Color(colors.text_primary)),
+                            text(status)
+                                .size(10)
+                                .style(iced::theme::Text::Color(if is_active {
+                                    colors.text_secondary
+                                } else {
+                                    colors.text_disabled
+                                })),
+                            text(first_entry.unwrap_or_default())
+                                .size(10)
+                                .font(JETBRAINS_MONO)
+                                .style(iced::theme::Text::Color(colors.text_disabled)),
+                        ]
+                        .spacing(2)
+                        .width(Length::Fill),
+                        button(text("Revert").size(11))
+                            .style(iced::theme::Button::Custom(Box::new(
+                                SecondaryButtonStyle { mode: theme_mode }
+                            )))
+                            .padding([5, 10])
+                            .on_press_maybe(
+                                (is_active && !state.loading)
+                                    .then_some(Message::RevertBatch(batch.id))
+                            ),
+                    ]
+                    .align_items(Alignment::Center)
+                    .spacing(8),
+                )
+                .padding([8, 10])
+                .width(Length::Fill)
+                .style(iced::theme::Container::Custom(Box::new(FileItemStyle {
+                    mode: theme_mode,
+                    is_selected: false,
+                })))
+                .into()
+            })
+            .collect();
+
+        scrollable(Column::with_children(items).spacing(6).width(Length::Fill))
+            .height(Length::Fixed(320.0))
+            .into()
+    };
+
+    container(
+        column![
+            text("Rename History")
+                .size(17)
+                .style(iced::theme::Text::Color(colors.text_primary)),
+            Space::with_height(6),
+            text("Reverting skips files that were changed or moved since the rename")
+                .size(12)
+                .style(iced::theme::Text::Color(colors.text_secondary)),
+            Space::with_height(14),
+            history,
+            Space::with_height(14),
+            button(text("Close").size(12))
+                .style(iced::theme::Button::Custom(Box::new(
+                    SecondaryButtonStyle { mode: theme_mode }
+                )))
+                .padding([8, 16])
+                .on_press(Message::HideRenameHistory),
+        ]
+        .spacing(0)
+        .align_items(Alignment::Center),
+    )
+    .padding(20)
+    .max_width(550)
+    .style(iced::theme::Container::Custom(Box::new(CardStyle {
+        mode: theme_mode,
+    })))
+    .into()
+}
+
 // ============== HELPER STYLES ==============
 
 struct TransparentButtonStyle;
@@ -1342,6 +2188,28 @@ impl iced::widget::container::StyleSheet for DefaultKeyContainerStyle {
     }
 }
 
+struct PosterPlaceholderStyle {
+    mode: ThemeMode,
+}
+
+impl iced::widget::container::StyleSheet for PosterPlaceholderStyle {
+    type Style = iced::Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> iced::widget::container::Appearance {
+        let colors = get_colors(self.mode);
+        iced::widget::container::Appearance {
+            text_color: Some(colors.text_disabled),
+            background: Some(iced::Background::Color(colors.bg_tertiary)),
+            border: iced::Border {
+                color: colors.border_light,
+                width: 1.0,
+                radius: 3.0.into(),
+            },
+            shadow: Default::default(),
+        }
+    }
+}
+
 struct DividerStyle {
     mode: ThemeMode,
 }